@@ -88,6 +88,90 @@ fn bench_compression_lzma2(c: &mut Criterion) {
     group.finish();
 }
 
+/// Compares preset 9 against extreme mode (`xz -9e` analog) on the ratio
+/// versus speed tradeoff, against liblzma's extreme preset as baseline.
+fn bench_compression_lzma2_extreme(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compression lzma2 extreme");
+    group.throughput(Throughput::Bytes(TEST_DATA.len() as u64));
+    group.sample_size(10);
+
+    group.bench_function("lzma-rust2 preset 9", |b| {
+        b.iter(|| {
+            let mut compressed = Vec::new();
+            let option = Lzma2Options::with_preset(9);
+            let mut writer = Lzma2Writer::new(black_box(&mut compressed), option);
+            writer.write_all(black_box(TEST_DATA)).unwrap();
+            writer.finish().unwrap();
+            black_box(compressed)
+        });
+    });
+
+    group.bench_function("lzma-rust2 preset 9 extreme", |b| {
+        b.iter(|| {
+            let mut compressed = Vec::new();
+            let mut option = Lzma2Options::with_preset(9);
+            option.lzma_options.set_extreme();
+            let mut writer = Lzma2Writer::new(black_box(&mut compressed), option);
+            writer.write_all(black_box(TEST_DATA)).unwrap();
+            writer.finish().unwrap();
+            black_box(compressed)
+        });
+    });
+
+    group.bench_function("liblzma preset 9 extreme", |b| {
+        b.iter(|| {
+            let mut compressed = Vec::new();
+            let stream =
+                stream::Stream::new_easy_encoder(9 | 0x80000000, stream::Check::None).unwrap();
+            let mut encoder = XzEncoder::new_stream(black_box(TEST_DATA), stream);
+            encoder.read_to_end(black_box(&mut compressed)).unwrap();
+            black_box(compressed)
+        });
+    });
+
+    group.finish();
+}
+
+/// Measures the CPU saving of the proactive incompressible-data detector
+/// on random input, against running the match finder for nothing.
+fn bench_compression_lzma2_skip_incompressible(c: &mut Criterion) {
+    // Deterministic pseudo-random bytes: incompressible, so the match
+    // finder finds nothing without the detector.
+    let mut state = 0x1234_5678_9ABC_DEF0u64;
+    let random: Vec<u8> = (0..8 << 20)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 56) as u8
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("compression lzma2 skip incompressible");
+    group.throughput(Throughput::Bytes(random.len() as u64));
+    group.sample_size(10);
+
+    for skip in [false, true] {
+        group.bench_with_input(
+            BenchmarkId::new("random 8 MiB, skip", skip),
+            &skip,
+            |b, &skip| {
+                b.iter(|| {
+                    let mut compressed = Vec::new();
+                    let mut option = Lzma2Options::with_preset(6);
+                    option.set_skip_incompressible(skip);
+                    let mut writer = Lzma2Writer::new(black_box(&mut compressed), option);
+                    writer.write_all(black_box(&random)).unwrap();
+                    writer.finish().unwrap();
+                    black_box(compressed)
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
 fn bench_decompression_lzma(c: &mut Criterion) {
     let mut group = c.benchmark_group("decompression lzma");
     group.throughput(Throughput::Bytes(TEST_DATA.len() as u64));
@@ -346,6 +430,8 @@ criterion_group!(
     benches,
     bench_compression_lzma,
     bench_compression_lzma2,
+    bench_compression_lzma2_extreme,
+    bench_compression_lzma2_skip_incompressible,
     bench_compression_mt,
     bench_decompression_lzma,
     bench_decompression_lzma2,
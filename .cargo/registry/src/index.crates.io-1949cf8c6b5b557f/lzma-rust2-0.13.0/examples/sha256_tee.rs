@@ -0,0 +1,37 @@
+//! Computes the SHA-256 of the uncompressed input while producing an XZ
+//! stream, in a single pass over the data.
+//!
+//! Run with: cargo run --release --example sha256_tee
+
+use std::io::{Read, Write};
+
+use lzma_rust2::{TeeWriter, XzOptions, XzReader, XzWriter};
+use sha2::{Digest, Sha256};
+
+fn main() {
+    let data: Vec<u8> = (0..1_000_000u32).flat_map(|i| i.to_le_bytes()).collect();
+
+    let mut hasher = Sha256::new();
+    let mut writer = TeeWriter::new(
+        XzWriter::new(Vec::new(), XzOptions::with_preset(3)).unwrap(),
+        |bytes: &[u8]| hasher.update(bytes),
+    );
+    writer.write_all(&data).unwrap();
+    let compressed = writer.into_inner().finish().unwrap();
+    let digest = hasher.finalize();
+
+    print!("sha256(input) = ");
+    for byte in digest {
+        print!("{byte:02x}");
+    }
+    println!();
+    println!("compressed {} -> {} bytes", data.len(), compressed.len());
+
+    // The hash matches one computed over the decompressed stream.
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert_eq!(Sha256::digest(&uncompressed)[..], digest[..]);
+    println!("hash matches the decompressed data");
+}
@@ -0,0 +1,62 @@
+//! Compresses many files while reusing one [`SharedWorkerPool`], instead of
+//! paying the worker thread spawn/teardown for every file.
+//!
+//! Run with: cargo run --release --example shared_pool
+
+use std::{io::Write, num::NonZeroU64, sync::Arc, time::Instant};
+
+use lzma_rust2::{SharedWorkerPool, XzOptions, XzWriterMt};
+
+const FILES: usize = 100;
+
+fn payload(index: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(256 * 1024);
+    let mut state = index as u64 + 1;
+    while data.len() < 256 * 1024 {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        data.extend_from_slice(format!("record {index} {}\n", state % 97).as_bytes());
+    }
+    data
+}
+
+fn compress(data: &[u8], pool: Option<&Arc<SharedWorkerPool>>) -> Vec<u8> {
+    let mut options = XzOptions::with_preset(0);
+    options.set_block_size(Some(NonZeroU64::new(256 * 1024).unwrap()));
+
+    let mut compressed = Vec::new();
+    let mut writer = match pool {
+        Some(pool) => {
+            XzWriterMt::new_with_pool(&mut compressed, options, 4, Arc::clone(pool)).unwrap()
+        }
+        None => XzWriterMt::new(&mut compressed, options, 4).unwrap(),
+    };
+    writer.write_all(data).unwrap();
+    writer.finish().unwrap();
+
+    compressed
+}
+
+fn main() {
+    let files: Vec<Vec<u8>> = (0..FILES).map(payload).collect();
+
+    let start = Instant::now();
+    let compressed: usize = files.iter().map(|data| compress(data, None).len()).sum();
+    println!(
+        "per-instance threads: {FILES} files, {compressed} bytes in {:?}",
+        start.elapsed()
+    );
+
+    let pool = Arc::new(SharedWorkerPool::new(4));
+    let start = Instant::now();
+    let compressed: usize = files
+        .iter()
+        .map(|data| compress(data, Some(&pool)).len())
+        .sum();
+    println!(
+        "shared pool ({} threads): {FILES} files, {compressed} bytes in {:?}",
+        pool.num_threads(),
+        start.elapsed()
+    );
+}
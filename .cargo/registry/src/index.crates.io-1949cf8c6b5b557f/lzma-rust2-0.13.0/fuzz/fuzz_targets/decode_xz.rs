@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Must never panic, only return an error for malformed input.
+    let _ = lzma_rust2::try_decode_xz(data);
+});
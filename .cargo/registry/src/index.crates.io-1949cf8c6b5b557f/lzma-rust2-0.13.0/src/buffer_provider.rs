@@ -0,0 +1,27 @@
+//! Scratch buffer provisioning for the decoders.
+
+use alloc::{vec, vec::Vec};
+
+/// Supplies the decoder's scratch buffers, so constrained environments can
+/// serve them from a pre-reserved pool instead of fresh allocations.
+///
+/// A provider must return a zeroed buffer of exactly the requested length.
+/// Buffers handed out to an [`Lzma2Reader`](crate::Lzma2Reader) can be
+/// reclaimed with
+/// [`Lzma2Reader::into_buffers`](crate::Lzma2Reader::into_buffers) once
+/// decoding is done, letting a pool recycle them across readers.
+pub trait BufferProvider {
+    /// Returns a zeroed buffer of exactly `len` bytes.
+    fn take_buffer(&mut self, len: usize) -> Vec<u8>;
+}
+
+/// The default provider: plain zeroed allocations from the global
+/// allocator.
+#[derive(Debug, Default)]
+pub struct GlobalBufferProvider;
+
+impl BufferProvider for GlobalBufferProvider {
+    fn take_buffer(&mut self, len: usize) -> Vec<u8> {
+        vec![0; len]
+    }
+}
@@ -0,0 +1,70 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// Window length used when mining repeated substrings from the samples.
+const WINDOW_SIZE: usize = 32;
+
+/// Stride between mined windows.
+const STRIDE: usize = 16;
+
+/// Builds a preset dictionary from a corpus of small samples, usable as
+/// [`LzmaOptions::preset_dict`](crate::LzmaOptions).
+///
+/// The heuristic mines fixed-size windows that repeat across the corpus and
+/// concatenates them with the most common windows last, so frequent content
+/// sits closest to the encoded data and matches use the shortest distances.
+/// Any remaining budget is filled with the tail of the longest sample. This
+/// is a naive trainer: it helps most when the samples share literal
+/// substrings, as is typical for structured small files like JSON, logs or
+/// configuration.
+///
+/// The returned dictionary is at most `max_dict_size` bytes and must be
+/// passed identically to the encoder and the decoder.
+pub fn train_preset_dict(samples: &[&[u8]], max_dict_size: usize) -> Vec<u8> {
+    if max_dict_size == 0 || samples.is_empty() {
+        return Vec::new();
+    }
+
+    let mut counts: BTreeMap<&[u8], u32> = BTreeMap::new();
+
+    for sample in samples {
+        let mut offset = 0;
+        while offset + WINDOW_SIZE <= sample.len() {
+            *counts
+                .entry(&sample[offset..offset + WINDOW_SIZE])
+                .or_insert(0) += 1;
+            offset += STRIDE;
+        }
+    }
+
+    // Keep only windows that repeat, most common first for selection. The
+    // BTreeMap ordering and the secondary key keep the result deterministic.
+    let mut repeated: Vec<(&[u8], u32)> =
+        counts.into_iter().filter(|(_, count)| *count > 1).collect();
+    repeated.sort_by(|left, right| right.1.cmp(&left.1).then(left.0.cmp(right.0)));
+    repeated.truncate(max_dict_size / WINDOW_SIZE);
+
+    // Most common windows go last, closest to the data.
+    repeated.reverse();
+
+    let mut mined = Vec::with_capacity(repeated.len() * WINDOW_SIZE);
+    for (window, _) in &repeated {
+        mined.extend_from_slice(window);
+    }
+
+    let mut dict = Vec::with_capacity(max_dict_size.min(mined.len() + WINDOW_SIZE));
+
+    // Fill the remaining budget with the tail of the longest sample, placed
+    // in front of the mined windows.
+    let filler_len = max_dict_size - mined.len();
+    if filler_len > 0 {
+        if let Some(longest) = samples.iter().max_by_key(|sample| sample.len()) {
+            let take = longest.len().min(filler_len);
+            dict.extend_from_slice(&longest[longest.len() - take..]);
+        }
+    }
+
+    dict.extend_from_slice(&mined);
+    dict.truncate(max_dict_size);
+
+    dict
+}
@@ -116,14 +116,20 @@ impl LZMAEncoder {
         dict_size: u32,
         extra_size_before: u32,
         mf: MfType,
+        hash_bits: Option<u32>,
     ) -> u32 {
         let mut m = 80;
         match mode {
             EncodeMode::Fast => {
-                m += FastEncoderMode::get_memory_usage(dict_size, extra_size_before, mf);
+                m += FastEncoderMode::get_memory_usage(dict_size, extra_size_before, mf, hash_bits);
             }
             EncodeMode::Normal => {
-                m += NormalEncoderMode::get_memory_usage(dict_size, extra_size_before, mf);
+                m += NormalEncoderMode::get_memory_usage(
+                    dict_size,
+                    extra_size_before,
+                    mf,
+                    hash_bits,
+                );
             }
         }
         m
@@ -141,6 +147,7 @@ impl LZMAEncoder {
         depth_limit: i32,
         dict_size: u32,
         nice_len: usize,
+        hash_bits: Option<u32>,
     ) -> (Self, LZMAEncoderModes) {
         let fast_mode = mode == EncodeMode::Fast;
         let mut mode: LZMAEncoderModes = if fast_mode {
@@ -167,6 +174,7 @@ impl LZMAEncoder {
                 nice_len as _,
                 MATCH_LEN_MAX as _,
                 depth_limit,
+                hash_bits,
             ),
             MfType::Bt4 => LZEncoder::new_bt4(
                 dict_size,
@@ -175,6 +183,7 @@ impl LZMAEncoder {
                 nice_len as _,
                 MATCH_LEN_MAX as _,
                 depth_limit,
+                hash_bits,
             ),
         };
 
@@ -223,6 +232,16 @@ impl LZMAEncoder {
         self.data.uncompressed_size = 0;
     }
 
+    /// Resets the encoder, its window and its match finder back to their
+    /// freshly constructed state, reusing all allocations. After this the
+    /// encoder produces a chunk that is independent of everything encoded
+    /// before, as if it had been newly created.
+    pub(crate) fn full_reset(&mut self, mode: &mut dyn LZMAEncoderTrait) {
+        self.lz.reset();
+        self.reset(mode);
+        self.data.uncompressed_size = 0;
+    }
+
     #[allow(unused)]
     pub(crate) fn encode_for_lzma1<W: Write>(
         &mut self,
@@ -20,13 +20,19 @@ impl NormalEncoderMode {
 
     pub(crate) const EXTRA_SIZE_AFTER: u32 = Self::OPTS;
 
-    pub(crate) fn get_memory_usage(dict_size: u32, extra_size_before: u32, mf: MfType) -> u32 {
+    pub(crate) fn get_memory_usage(
+        dict_size: u32,
+        extra_size_before: u32,
+        mf: MfType,
+        hash_bits: Option<u32>,
+    ) -> u32 {
         LZEncoder::get_memory_usage(
             dict_size,
             extra_size_before.max(Self::EXTRA_SIZE_BEFORE),
             Self::EXTRA_SIZE_AFTER,
             MATCH_LEN_MAX as u32,
             mf,
+            hash_bits,
         ) + Self::OPTS * 64 / 1024
     }
 
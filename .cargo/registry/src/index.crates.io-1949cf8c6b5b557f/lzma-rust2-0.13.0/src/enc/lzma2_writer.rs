@@ -1,12 +1,12 @@
 use alloc::vec::Vec;
-use core::num::NonZeroU64;
+use core::num::{NonZeroU32, NonZeroU64};
 
 use super::{
     encoder::{EncodeMode, LZMAEncoder, LZMAEncoderModes},
     lz::MfType,
     range_enc::{RangeEncoder, RangeEncoderBuffer},
 };
-use crate::{ByteWriter, Write};
+use crate::{ByteWriter, CountingWriter, Write};
 
 /// Encoder settings when compressing with LZMA and LZMA2.
 #[derive(Debug, Clone)]
@@ -29,6 +29,15 @@ pub struct LzmaOptions {
     pub depth_limit: i32,
     /// Preset dictionary data.
     pub preset_dict: Option<Vec<u8>>,
+    /// Optional override for the match finder hash table size, in bits.
+    ///
+    /// By default the hash table is sized from the dictionary size. On
+    /// memory-constrained systems a smaller value trades compression ratio
+    /// for a smaller hash table. Values outside `[16, 24]` are clamped; the
+    /// default sizing is used when `None`.
+    pub hash_bits_override: Option<u32>,
+    /// Expected input size, capping the match finder allocations.
+    pub input_size_hint: Option<u64>,
 }
 
 impl Default for LzmaOptions {
@@ -93,6 +102,8 @@ impl LzmaOptions {
             mf,
             depth_limit,
             preset_dict: None,
+            hash_bits_override: None,
+            input_size_hint: None,
         }
     }
 
@@ -109,11 +120,35 @@ impl LzmaOptions {
             mf: Default::default(),
             depth_limit: Default::default(),
             preset_dict: Default::default(),
+            hash_bits_override: Default::default(),
+            input_size_hint: Default::default(),
         };
         opt.set_preset(preset);
         opt
     }
 
+    /// Creates options with the given preset in extreme mode, analogous to
+    /// `xz -9e`.
+    #[inline]
+    pub fn with_preset_extreme(preset: u32) -> Self {
+        let mut opt = Self::with_preset(preset);
+        opt.set_extreme();
+        opt
+    }
+
+    /// Switches to a more thorough match search, analogous to xz's `-e`
+    /// modifier.
+    ///
+    /// This maxes out the nice length, forces the normal mode BT4 match
+    /// finder and raises the search depth. Compression gets noticeably
+    /// slower for a usually small ratio improvement.
+    pub fn set_extreme(&mut self) {
+        self.mode = EncodeMode::Normal;
+        self.mf = MfType::Bt4;
+        self.nice_len = Self::NICE_LEN_MAX;
+        self.depth_limit = 512;
+    }
+
     /// preset: [0..9]
     pub fn set_preset(&mut self, preset: u32) {
         let preset = preset.min(9);
@@ -141,11 +176,78 @@ impl LzmaOptions {
         }
     }
 
+    /// Sets the match finder nice length, clamped to
+    /// [`NICE_LEN_MIN`](Self::NICE_LEN_MIN) and
+    /// [`NICE_LEN_MAX`](Self::NICE_LEN_MAX).
+    ///
+    /// Values outside that range would mis-size the length encoder's price
+    /// tables, so they are clamped instead of taken verbatim. A higher nice
+    /// length makes the match finder try harder before settling.
+    pub fn set_nice_len(&mut self, nice_len: u32) {
+        self.nice_len = nice_len.clamp(Self::NICE_LEN_MIN, Self::NICE_LEN_MAX);
+    }
+
+    /// Sets the match finder search depth limit.
+    ///
+    /// Values of zero and below select the match finder's automatic depth,
+    /// which is derived from the nice length; they are normalized to `0`.
+    pub fn set_depth_limit(&mut self, depth_limit: i32) {
+        self.depth_limit = depth_limit.max(0);
+    }
+
+    /// Tunes the dictionary size down for the given input length.
+    ///
+    /// A dictionary larger than the input wastes memory, since the encoder can
+    /// never reference further back than the input is long. This selects the
+    /// smallest valid LZMA2 dictionary size (the `base << shift` encoding used
+    /// by the LZMA2 properties byte) that is at least `input_len`, clamped to
+    /// [`DICT_SIZE_MIN`](crate::DICT_SIZE_MIN) and
+    /// [`DICT_SIZE_MAX`](crate::DICT_SIZE_MAX). The dictionary size is only
+    /// ever reduced: if the currently configured size is already smaller, it is
+    /// left unchanged.
+    pub fn tune_dict_size_for(&mut self, input_len: u64) {
+        let mut candidate = crate::DICT_SIZE_MAX;
+
+        // Walk the valid LZMA2 dictionary sizes (2 << 11, 3 << 11, 4 << 11, ...)
+        // and pick the smallest one covering the input.
+        for prop in 0u8..40 {
+            let base = 2 | ((prop & 1) as u32);
+            let size = base << (prop / 2 + 11);
+
+            if size as u64 >= input_len {
+                candidate = size;
+                break;
+            }
+        }
+
+        let candidate = candidate.clamp(crate::DICT_SIZE_MIN, crate::DICT_SIZE_MAX);
+        self.dict_size = self.dict_size.min(candidate);
+    }
+
+    /// Sets the expected input size, capping the encoder's allocations.
+    ///
+    /// When the hint is much smaller than the dictionary size, the writers
+    /// reduce the dictionary they actually use (via
+    /// [`tune_dict_size_for`](Self::tune_dict_size_for)) when they are
+    /// constructed, shrinking the window, hash table and match finder tree
+    /// allocations proportionally. Inputs larger than the hint still
+    /// compress correctly; matches just cannot reach further back than the
+    /// reduced dictionary.
+    pub fn set_input_size_hint(&mut self, input_size_hint: Option<u64>) {
+        self.input_size_hint = input_size_hint;
+    }
+
     /// Returns the estimated memory usage in kilobytes for these options.
     pub fn get_memory_usage(&self) -> u32 {
         let dict_size = self.dict_size;
         let extra_size_before = get_extra_size_before(dict_size);
-        70 + LZMAEncoder::get_mem_usage(self.mode, dict_size, extra_size_before, self.mf)
+        70 + LZMAEncoder::get_mem_usage(
+            self.mode,
+            dict_size,
+            extra_size_before,
+            self.mf,
+            self.hash_bits_override,
+        )
     }
 
     /// Returns the LZMA properties byte for these options.
@@ -153,6 +255,26 @@ impl LzmaOptions {
     pub fn get_props(&self) -> u8 {
         ((self.pb * 5 + self.lp) * 9 + self.lc) as u8
     }
+
+    /// Decodes a properties byte into `(lc, lp, pb)`, the inverse of
+    /// [`get_props`](Self::get_props).
+    ///
+    /// Valid properties bytes are at most `(4 * 5 + 4) * 9 + 8 = 224`. Note
+    /// that LZMA2 additionally requires `lc + lp <= 4`, which is not enforced
+    /// here since plain LZMA allows the full range.
+    pub fn from_props(props: u8) -> crate::Result<(u32, u32, u32)> {
+        let props = props as u32;
+
+        if props > (4 * 5 + 4) * 9 + 8 {
+            return Err(crate::error_invalid_input("invalid LZMA properties byte"));
+        }
+
+        let lc = props % 9;
+        let lp = (props / 9) % 5;
+        let pb = props / (9 * 5);
+
+        Ok((lc, lp, pb))
+    }
 }
 
 /// Options for LZMA2 compression.
@@ -164,6 +286,18 @@ pub struct Lzma2Options {
     /// If not set, the whole data will be written as one chunk.
     /// Will get clamped to be at least the dict size to not waste memory.
     pub chunk_size: Option<NonZeroU64>,
+    /// Prefer uncompressed chunks for small flushed payloads.
+    pub low_latency: bool,
+    /// Memory budget in KiB for the multithreaded writer's workers.
+    pub max_total_memory: Option<u64>,
+    /// Always emit LZMA chunks, even when storing would be smaller.
+    pub force_compressed_chunks: bool,
+    /// Start an independent chunk after this many encoding chunks.
+    pub reset_every_n_chunks: Option<NonZeroU32>,
+    /// Abort with an error once the compressed output exceeds this size.
+    pub max_output: Option<u64>,
+    /// Proactively store data that samples as incompressible.
+    pub skip_incompressible: bool,
 }
 
 impl Lzma2Options {
@@ -172,23 +306,217 @@ impl Lzma2Options {
         Self {
             lzma_options: LzmaOptions::with_preset(preset),
             chunk_size: None,
+            low_latency: false,
+            max_total_memory: None,
+            force_compressed_chunks: false,
+            reset_every_n_chunks: None,
+            max_output: None,
+            skip_incompressible: false,
         }
     }
 
-    /// Set the chunk size (None means a single chunk, which is the default).
-    /// Chunk size will be clamped to be at least the dictionary size.
+    /// Sets the independent-chunk interval (None means a single independent
+    /// chunk, which is the default).
+    ///
+    /// Despite the name, this does not control the low-level LZMA2 chunk
+    /// framing: the encoder always splits its output into LZMA2 chunks of at
+    /// most 2 MiB of uncompressed data (64 KiB compressed), as the format
+    /// requires. This value instead controls how often a chunk with a
+    /// dictionary reset is emitted, which is the unit of parallelism for
+    /// [`Lzma2ReaderMt`](crate::Lzma2ReaderMt). It is clamped to at least
+    /// the dictionary size, so smaller values cannot waste the window.
     pub fn set_chunk_size(&mut self, chunk_size: Option<NonZeroU64>) {
         self.chunk_size = chunk_size;
     }
+
+    /// The effective independent-chunk interval: the configured value
+    /// clamped to at least the dictionary size, or `None` when the whole
+    /// stream is a single independent chunk.
+    ///
+    /// See [`set_chunk_size`](Self::set_chunk_size) for how this relates to
+    /// the format's internal 2 MiB chunk framing.
+    pub fn chunk_size(&self) -> Option<NonZeroU64> {
+        self.chunk_size.map(|chunk_size| {
+            chunk_size
+                .max(NonZeroU64::new(self.lzma_options.dict_size.max(1) as u64).expect("nonzero"))
+        })
+    }
+
+    /// Starts an independent chunk (dictionary reset) after this many
+    /// encoding chunks have been written, regardless of their byte count.
+    ///
+    /// Each encoding chunk holds at most 2 MiB of uncompressed data, so
+    /// this bounds the worst-case dictionary dependency chain length in
+    /// chunks rather than bytes, giving [`Lzma2ReaderMt`](crate::Lzma2ReaderMt)
+    /// a predictable parallel decode granularity. Works independently of
+    /// [`set_chunk_size`](Self::set_chunk_size); whichever limit is reached
+    /// first triggers the reset. A reset can only start where new input
+    /// follows, so data already buffered when the stream finishes drains
+    /// without further resets.
+    pub fn set_reset_every_n_chunks(&mut self, reset_every_n_chunks: Option<NonZeroU32>) {
+        self.reset_every_n_chunks = reset_every_n_chunks;
+    }
+
+    /// Proactively detects incompressible input and stores it as
+    /// uncompressed chunks without running the match finder.
+    ///
+    /// Writes are sampled with a cheap byte-distribution estimate; when a
+    /// write looks incompressible (already compressed, encrypted or random
+    /// data), up to one 64 KiB chunk is emitted uncompressed directly,
+    /// skipping the compression work the after-the-fact fallback would
+    /// still pay. The rest of the buffer is sampled again on the next
+    /// `write` call, so a stale sample costs at most one stored chunk. The
+    /// dictionary is reset around skipped data, so the output stays a
+    /// valid stream at the cost of matches never spanning the skipped
+    /// region. Detection needs writes of a few KiB to trigger.
+    pub fn set_skip_incompressible(&mut self, skip_incompressible: bool) {
+        self.skip_incompressible = skip_incompressible;
+    }
+
+    /// Sets a hard budget on the compressed output size.
+    ///
+    /// Once the bytes emitted by the writer exceed the budget, `write` and
+    /// `finish` fail with an error instead of compressing further, so a
+    /// caller filling a fixed-size slot can abort early and fall back (for
+    /// example to a higher preset or to storing the data). The check runs
+    /// at chunk granularity, after a chunk was already written.
+    pub fn set_max_output(&mut self, max_output: Option<u64>) {
+        self.max_output = max_output;
+    }
+
+    /// Prefer uncompressed chunks for small flushed payloads.
+    ///
+    /// A flush with at most 128 pending bytes is then always emitted as an
+    /// uncompressed chunk. For incompressible small messages this matches
+    /// the encoder's own fallback; for compressible ones it trades a few
+    /// bytes of ratio for predictable per-flush framing, which interactive
+    /// protocols may prefer. Leave this off for batch compression.
+    pub fn set_low_latency(&mut self, low_latency: bool) {
+        self.low_latency = low_latency;
+    }
+
+    /// Caps the total memory of the multithreaded writer's workers, in KiB.
+    ///
+    /// The effective worker count becomes the requested count clamped so
+    /// that `workers * per_worker_memory` stays within the budget (see
+    /// [`workers_for_memory_budget`]), but never less than one worker.
+    pub fn set_max_total_memory(&mut self, max_total_memory: Option<u64>) {
+        self.max_total_memory = max_total_memory;
+    }
+
+    /// Always emit LZMA chunks, even when the uncompressed chunk form would
+    /// be slightly smaller.
+    ///
+    /// Some downstream tools cannot handle uncompressed LZMA2 chunks; this
+    /// disables the stored-chunk fallback at the cost of a slightly larger
+    /// output for incompressible data. The `low_latency` flush preference
+    /// still takes precedence on explicit flushes.
+    pub fn set_force_compressed_chunks(&mut self, force_compressed_chunks: bool) {
+        self.force_compressed_chunks = force_compressed_chunks;
+    }
 }
 
 const COMPRESSED_SIZE_MAX: u32 = 64 << 10;
 
+/// Sample size for the incompressible-data detector.
+const SKIP_SAMPLE_SIZE: usize = 4096;
+
+/// A cheap byte-distribution test: computes the sum of squared symbol
+/// counts, which approximates collision entropy. Uniformly random bytes
+/// score close to `len^2 / 256`; compressible data concentrates symbols
+/// and scores much higher.
+fn looks_incompressible(sample: &[u8]) -> bool {
+    let mut counts = [0u32; 256];
+
+    for byte in sample {
+        counts[*byte as usize] += 1;
+    }
+
+    let sum_of_squares: u64 = counts
+        .iter()
+        .map(|count| u64::from(*count) * u64::from(*count))
+        .sum();
+    let uniform = (sample.len() as u64 * sample.len() as u64) / 256;
+
+    sum_of_squares < uniform * 2
+}
+
+/// Derives the number of encoder workers that fit into a memory budget.
+///
+/// Every multithreaded writer worker holds a full encoder whose size follows
+/// from the options ([`LzmaOptions::get_memory_usage`], in KiB). The result
+/// is `max_total_memory_kb / per_worker_kb`, clamped between one worker and
+/// the requested count.
+pub fn workers_for_memory_budget(
+    options: &LzmaOptions,
+    requested_workers: u32,
+    max_total_memory_kb: u64,
+) -> u32 {
+    let per_worker_kb = options.get_memory_usage().max(1) as u64;
+    let affordable = (max_total_memory_kb / per_worker_kb).min(u32::MAX as u64) as u32;
+
+    affordable.clamp(1, requested_workers.max(1))
+}
+
 /// Calculates the extra space needed before the dictionary for LZMA2 encoding.
 pub fn get_extra_size_before(dict_size: u32) -> u32 {
     COMPRESSED_SIZE_MAX.saturating_sub(dict_size)
 }
 
+/// Compresses `data` into a raw LZMA2 stream in one call.
+pub fn lzma2_compress(data: &[u8], options: &Lzma2Options) -> crate::Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = Lzma2Writer::new(&mut compressed, options.clone());
+        writer.write_all(data)?;
+        writer.finish()?;
+    }
+
+    Ok(compressed)
+}
+
+/// Totals reported by the writers' `finish_with_stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionStats {
+    /// Total uncompressed bytes written into the compressor.
+    pub uncompressed_in: u64,
+    /// Total compressed bytes produced, including framing.
+    pub compressed_out: u64,
+}
+
+/// Estimates the LZMA2 compressed size of `data` in bytes under the given options.
+///
+/// This performs a full compression pass - match finding and range coding
+/// included - into a discarding sink, so it costs as much CPU time as the
+/// real compression and only saves buffering the output. Useful to decide up
+/// front whether compressing is worthwhile (e.g. storing raw vs. compressing)
+/// without committing the output anywhere.
+///
+/// The returned value is an estimate, not a guarantee: it matches what
+/// [`Lzma2Writer`] produces for a single `write_all()` of `data` with these
+/// exact options, but a different write pattern or chunking configuration can
+/// shift chunk boundaries and change the size slightly.
+pub fn estimate_lzma2_size(data: &[u8], options: &Lzma2Options) -> crate::Result<u64> {
+    struct DiscardSink;
+
+    impl Write for DiscardSink {
+        fn write(&mut self, buf: &[u8]) -> crate::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> crate::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut writer = Lzma2Writer::new(CountingWriter::new(DiscardSink), options.clone());
+    writer.write_all(data)?;
+    let counting = writer.finish()?;
+
+    Ok(counting.bytes_written())
+}
+
 /// A single-threaded LZMA2 compressor.
 pub struct Lzma2Writer<W: Write> {
     inner: W,
@@ -202,12 +530,22 @@ pub struct Lzma2Writer<W: Write> {
     chunk_size: Option<u64>,
     uncompressed_size: u64,
     force_independent_chunk: bool,
+    chunks_since_reset: u32,
+    prefer_uncompressed_chunk: bool,
+    total_uncompressed: u64,
+    total_compressed: u64,
     options: Lzma2Options,
 }
 
 impl<W: Write> Lzma2Writer<W> {
     /// Creates a new LZMA2 writer that will write compressed data to the given writer.
     pub fn new(inner: W, options: Lzma2Options) -> Self {
+        let mut options = options;
+
+        if let Some(input_size_hint) = options.lzma_options.input_size_hint {
+            options.lzma_options.tune_dict_size_for(input_size_hint);
+        }
+
         let lzma_options = &options.lzma_options;
         let dict_size = lzma_options.dict_size;
 
@@ -221,6 +559,7 @@ impl<W: Write> Lzma2Writer<W> {
             lzma_options.depth_limit,
             lzma_options.dict_size,
             lzma_options.nice_len as usize,
+            lzma_options.hash_bits_override,
         );
 
         let mut dict_reset_needed = true;
@@ -244,16 +583,28 @@ impl<W: Write> Lzma2Writer<W> {
             chunk_size,
             uncompressed_size: 0,
             force_independent_chunk: false,
+            chunks_since_reset: 0,
+            prefer_uncompressed_chunk: false,
+            total_uncompressed: 0,
+            total_compressed: 0,
             options,
         }
     }
 
     fn should_start_independent_chunk(&self) -> bool {
         if let Some(chunk_size) = self.chunk_size {
-            self.uncompressed_size >= chunk_size
-        } else {
-            false
+            if self.uncompressed_size >= chunk_size {
+                return true;
+            }
         }
+
+        if let Some(reset_every_n_chunks) = self.options.reset_every_n_chunks {
+            if self.chunks_since_reset >= reset_every_n_chunks.get() {
+                return true;
+            }
+        }
+
+        false
     }
 
     fn start_independent_chunk(&mut self) -> crate::Result<()> {
@@ -269,23 +620,13 @@ impl<W: Write> Lzma2Writer<W> {
         self.state_reset_needed = true;
         self.props_needed = true;
         self.uncompressed_size = 0;
+        self.chunks_since_reset = 0;
 
-        let lzma_options = &self.options.lzma_options;
-
-        let (new_lzma, new_mode) = LZMAEncoder::new(
-            lzma_options.mode,
-            lzma_options.lc,
-            lzma_options.lp,
-            lzma_options.pb,
-            lzma_options.mf,
-            lzma_options.depth_limit,
-            lzma_options.dict_size,
-            lzma_options.nice_len as usize,
-        );
-
-        self.lzma = new_lzma;
-        self.mode = new_mode;
-        self.rc = RangeEncoder::new_buffer(COMPRESSED_SIZE_MAX as usize);
+        // Reuse the existing allocations: recreating the encoder and the
+        // range encoder buffer here costs an allocation storm when small
+        // chunk sizes cause frequent resets.
+        self.lzma.full_reset(&mut self.mode);
+        self.rc.reset_buffer();
 
         Ok(())
     }
@@ -313,11 +654,14 @@ impl<W: Write> Lzma2Writer<W> {
         if self.props_needed {
             chunk_header[5] = self.options.lzma_options.get_props();
             self.inner.write_all(&chunk_header)?;
+            self.total_compressed += chunk_header.len() as u64;
         } else {
             self.inner.write_all(&chunk_header[..5])?;
+            self.total_compressed += 5;
         }
 
         self.rc.write_to(&mut self.inner)?;
+        self.total_compressed += compressed_size as u64;
         self.props_needed = false;
         self.state_reset_needed = false;
         self.dict_reset_needed = false;
@@ -338,6 +682,7 @@ impl<W: Write> Lzma2Writer<W> {
                 uncompressed_size as i32,
                 chunk_size as usize,
             )?;
+            self.total_compressed += chunk_header.len() as u64 + chunk_size as u64;
             uncompressed_size -= chunk_size;
             self.dict_reset_needed = false;
         }
@@ -354,7 +699,11 @@ impl<W: Write> Lzma2Writer<W> {
             "uncompressed_size is 0, read_pos={}",
             self.lzma.lz.read_pos,
         );
-        if compressed_size + 2 < uncompressed_size {
+        let use_lzma_chunk = (compressed_size + 2 < uncompressed_size
+            || self.options.force_compressed_chunks)
+            && !self.prefer_uncompressed_chunk;
+
+        if use_lzma_chunk {
             self.write_lzma(uncompressed_size, compressed_size)?;
         } else {
             self.lzma.reset(&mut self.mode);
@@ -365,11 +714,69 @@ impl<W: Write> Lzma2Writer<W> {
         self.pending_size -= uncompressed_size;
         self.uncompressed_size += uncompressed_size as u64;
 
+        self.chunks_since_reset += 1;
+
         self.lzma.reset_uncompressed_size();
         self.rc.reset_buffer();
+
+        self.check_output_budget()?;
+
+        Ok(())
+    }
+
+    /// Emits `buf` (at most one chunk) uncompressed without running the
+    /// match finder, isolating it with dictionary resets on both sides.
+    fn write_skipped(&mut self, buf: &[u8]) -> crate::Result<usize> {
+        debug_assert!(buf.len() <= COMPRESSED_SIZE_MAX as usize);
+
+        let mut chunk_header = [0u8; 3];
+        chunk_header[0] = 0x01;
+        chunk_header[1] = ((buf.len() - 1) >> 8) as u8;
+        chunk_header[2] = (buf.len() - 1) as u8;
+        self.inner.write_all(&chunk_header)?;
+        self.inner.write_all(buf)?;
+        self.total_compressed += chunk_header.len() as u64 + buf.len() as u64;
+
+        self.total_uncompressed += buf.len() as u64;
+
+        // The encoder never saw the skipped bytes, so everything after them
+        // must restart with a fresh dictionary on both sides.
+        self.lzma.full_reset(&mut self.mode);
+        self.rc.reset_buffer();
+        self.dict_reset_needed = true;
+        self.state_reset_needed = true;
+        self.props_needed = true;
+        self.uncompressed_size = 0;
+        self.chunks_since_reset = 0;
+
+        self.check_output_budget()?;
+
+        Ok(buf.len())
+    }
+
+    /// Fails once the emitted output exceeds the configured budget.
+    fn check_output_budget(&self) -> crate::Result<()> {
+        if let Some(max_output) = self.options.max_output {
+            if self.total_compressed > max_output {
+                return Err(crate::error_other("output budget exceeded"));
+            }
+        }
+
         Ok(())
     }
 
+    /// Returns a snapshot of the trailing dictionary window.
+    ///
+    /// The returned bytes are the last up-to-`dict_size` bytes of input
+    /// processed so far. Compressing a series of related files, they can be
+    /// passed as `preset_dict` to the next writer so it starts with the
+    /// previous file's history instead of an empty dictionary.
+    pub fn take_dict_window(&self) -> Vec<u8> {
+        self.lzma
+            .lz
+            .dict_window(self.options.lzma_options.dict_size as usize)
+    }
+
     /// Unwraps the writer, returning the underlying writer.
     pub fn into_inner(self) -> W {
         self.inner
@@ -386,7 +793,13 @@ impl<W: Write> Lzma2Writer<W> {
     }
 
     /// Finishes the compression and returns the underlying writer.
-    pub fn finish(mut self) -> crate::Result<W> {
+    pub fn finish(self) -> crate::Result<W> {
+        Ok(self.finish_with_stats()?.0)
+    }
+
+    /// Finishes the compression, returning the underlying writer along with
+    /// the total uncompressed and compressed byte counts.
+    pub fn finish_with_stats(mut self) -> crate::Result<(W, CompressionStats)> {
         self.lzma.lz.set_finishing();
 
         while self.pending_size > 0 {
@@ -395,13 +808,34 @@ impl<W: Write> Lzma2Writer<W> {
         }
 
         self.inner.write_u8(0x00)?;
+        self.total_compressed += 1;
+
+        self.check_output_budget()?;
 
-        Ok(self.inner)
+        let stats = CompressionStats {
+            uncompressed_in: self.total_uncompressed,
+            compressed_out: self.total_compressed,
+        };
+
+        Ok((self.inner, stats))
     }
 }
 
 impl<W: Write> Write for Lzma2Writer<W> {
     fn write(&mut self, buf: &[u8]) -> crate::Result<usize> {
+        if self.options.skip_incompressible
+            && self.pending_size == 0
+            && buf.len() >= SKIP_SAMPLE_SIZE
+            && looks_incompressible(&buf[..SKIP_SAMPLE_SIZE])
+        {
+            // Store at most one chunk per sample as a partial write, so the
+            // remainder of a large buffer is sampled again on the caller's
+            // next call and one stale sample can only cost one chunk.
+            let skip_len = buf.len().min(COMPRESSED_SIZE_MAX as usize);
+
+            return self.write_skipped(&buf[..skip_len]);
+        }
+
         let mut len = buf.len();
 
         let mut off = 0;
@@ -414,6 +848,7 @@ impl<W: Write> Write for Lzma2Writer<W> {
             off += used;
             len -= used;
             self.pending_size += used as u32;
+            self.total_uncompressed += used as u64;
             if self.lzma.encode_for_lzma2(&mut self.rc, &mut self.mode)? {
                 self.write_chunk()?;
             }
@@ -421,7 +856,32 @@ impl<W: Write> Write for Lzma2Writer<W> {
         Ok(off)
     }
 
+    /// Feeds every buffer into the encoder instead of only the first one,
+    /// sparing callers from coalescing small buffers themselves.
+    #[cfg(feature = "std")]
+    fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> crate::Result<usize> {
+        let mut total_written = 0;
+
+        for buf in bufs {
+            let bytes_written = self.write(buf)?;
+            total_written += bytes_written;
+
+            if bytes_written < buf.len() {
+                break;
+            }
+        }
+
+        Ok(total_written)
+    }
+
     fn flush(&mut self) -> crate::Result<()> {
+        // For small flushed payloads, the LZMA chunk framing costs more than
+        // the payload itself: emit them as uncompressed chunks instead.
+        const LOW_LATENCY_MAX_PENDING: u32 = 128;
+
+        self.prefer_uncompressed_chunk =
+            self.options.low_latency && self.pending_size <= LOW_LATENCY_MAX_PENDING;
+
         self.lzma.lz.set_flushing();
 
         while self.pending_size > 0 {
@@ -429,6 +889,149 @@ impl<W: Write> Write for Lzma2Writer<W> {
             self.write_chunk()?;
         }
 
+        self.prefer_uncompressed_chunk = false;
+
         self.inner.flush()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_size_accessor_clamps_to_dict() {
+        let mut options = Lzma2Options::with_preset(6);
+        assert_eq!(options.chunk_size(), None);
+
+        let dict_size = options.lzma_options.dict_size as u64;
+
+        // Below the dictionary size: clamped up.
+        options.set_chunk_size(NonZeroU64::new(1));
+        assert_eq!(options.chunk_size(), NonZeroU64::new(dict_size));
+
+        // Above: taken as-is.
+        options.set_chunk_size(NonZeroU64::new(dict_size * 4));
+        assert_eq!(options.chunk_size(), NonZeroU64::new(dict_size * 4));
+    }
+
+    #[test]
+    fn workers_for_memory_budget_derivation() {
+        let options = LzmaOptions::with_preset(1);
+        let per_worker = options.get_memory_usage().max(1) as u64;
+
+        // The budget buys exactly as many workers as fit.
+        assert_eq!(workers_for_memory_budget(&options, 16, per_worker * 4), 4);
+        // But never more than requested.
+        assert_eq!(workers_for_memory_budget(&options, 2, per_worker * 4), 2);
+        // And never less than one, even for a zero budget.
+        assert_eq!(workers_for_memory_budget(&options, 16, 0), 1);
+    }
+
+    #[test]
+    fn props_byte_round_trip() {
+        for lc in 0..=8 {
+            for lp in 0..=4 {
+                for pb in 0..=4 {
+                    let mut options = LzmaOptions::with_preset(6);
+                    options.lc = lc;
+                    options.lp = lp;
+                    options.pb = pb;
+
+                    let props = options.get_props();
+                    assert_eq!(LzmaOptions::from_props(props).unwrap(), (lc, lp, pb));
+                }
+            }
+        }
+
+        // 224 is the largest valid properties byte.
+        assert_eq!(LzmaOptions::from_props(224).unwrap(), (8, 4, 4));
+        assert!(LzmaOptions::from_props(225).is_err());
+        assert!(LzmaOptions::from_props(255).is_err());
+    }
+
+    #[test]
+    fn nice_len_and_depth_setters_clamp() {
+        let mut options = LzmaOptions::with_preset(6);
+
+        options.set_nice_len(0);
+        assert_eq!(options.nice_len, LzmaOptions::NICE_LEN_MIN);
+
+        options.set_nice_len(100_000);
+        assert_eq!(options.nice_len, LzmaOptions::NICE_LEN_MAX);
+
+        options.set_nice_len(64);
+        assert_eq!(options.nice_len, 64);
+
+        options.set_depth_limit(-5);
+        assert_eq!(options.depth_limit, 0);
+
+        options.set_depth_limit(48);
+        assert_eq!(options.depth_limit, 48);
+    }
+
+    #[test]
+    fn tune_dict_size_boundaries() {
+        let mut options = LzmaOptions::with_preset(9);
+        assert_eq!(options.dict_size, 1 << 26);
+
+        // Exactly a valid size is kept as-is.
+        options.tune_dict_size_for(1 << 20);
+        assert_eq!(options.dict_size, 1 << 20);
+
+        // One byte above a valid size selects the next one (3 << 19 = 1.5 MiB).
+        let mut options = LzmaOptions::with_preset(9);
+        options.tune_dict_size_for((1 << 20) + 1);
+        assert_eq!(options.dict_size, 3 << 19);
+
+        // One byte below stays within the same size.
+        let mut options = LzmaOptions::with_preset(9);
+        options.tune_dict_size_for((1 << 20) - 1);
+        assert_eq!(options.dict_size, 1 << 20);
+    }
+
+    #[test]
+    fn tune_dict_size_clamps_to_min() {
+        let mut options = LzmaOptions::with_preset(9);
+        options.tune_dict_size_for(0);
+        assert_eq!(options.dict_size, crate::DICT_SIZE_MIN);
+
+        let mut options = LzmaOptions::with_preset(9);
+        options.tune_dict_size_for(1024);
+        assert_eq!(options.dict_size, crate::DICT_SIZE_MIN);
+    }
+
+    #[test]
+    fn tune_dict_size_never_grows() {
+        let mut options = LzmaOptions::with_preset(0);
+        let dict_size = options.dict_size;
+        options.tune_dict_size_for(u64::from(u32::MAX) * 2);
+        assert_eq!(options.dict_size, dict_size);
+    }
+
+    #[test]
+    fn hash_bits_override_reduces_memory_usage() {
+        let mut options = LzmaOptions::with_preset(9);
+        let default_usage = options.get_memory_usage();
+
+        options.hash_bits_override = Some(16);
+        let reduced_usage = options.get_memory_usage();
+        assert!(reduced_usage < default_usage);
+
+        // Out-of-range values are clamped, not rejected.
+        options.hash_bits_override = Some(1);
+        assert_eq!(options.get_memory_usage(), reduced_usage);
+
+        options.hash_bits_override = Some(63);
+        let max_usage = options.get_memory_usage();
+        assert!(max_usage <= default_usage);
+    }
+
+    #[test]
+    fn tune_dict_size_clamps_to_max() {
+        let mut options = LzmaOptions::with_preset(9);
+        options.dict_size = crate::DICT_SIZE_MAX;
+        options.tune_dict_size_for(u64::from(u32::MAX) * 2);
+        assert_eq!(options.dict_size, crate::DICT_SIZE_MAX);
+    }
+}
@@ -37,9 +37,30 @@ impl<W: Write> Lzma2WriterMt<W> {
     /// - `inner`: The writer to write compressed data to.
     /// - `options`: The LZMA2 options used for compressing. Chunk size must be set when using the
     ///   multi-threaded encoder. If you need just one chunk, then use the single-threaded encoder.
-    /// - `num_workers`: The maximum number of worker threads for compression.
-    ///   Currently capped at 256 Threads.
+    /// - `num_workers`: The maximum number of worker threads for compression. Pass `0` to
+    ///   use all available cores. Currently capped at 256 Threads.
     pub fn new(inner: W, options: Lzma2Options, num_workers: u32) -> crate::Result<Self> {
+        Self::construct(inner, options, num_workers, None)
+    }
+
+    /// Like [`new`](Self::new), but submits work to a shared thread pool
+    /// instead of spawning per-instance worker threads. See
+    /// [`SharedWorkerPool`](crate::SharedWorkerPool) for how to size the pool.
+    pub fn new_with_pool(
+        inner: W,
+        options: Lzma2Options,
+        num_workers: u32,
+        shared_pool: std::sync::Arc<crate::SharedWorkerPool>,
+    ) -> crate::Result<Self> {
+        Self::construct(inner, options, num_workers, Some(shared_pool))
+    }
+
+    fn construct(
+        inner: W,
+        options: Lzma2Options,
+        num_workers: u32,
+        shared_pool: Option<std::sync::Arc<crate::SharedWorkerPool>>,
+    ) -> crate::Result<Self> {
         let chunk_size = match options.chunk_size {
             None => return Err(error_invalid_input("chunk size must be set")),
             Some(chunk_size) => chunk_size.get().max(options.lzma_options.dict_size as u64),
@@ -48,6 +69,16 @@ impl<W: Write> Lzma2WriterMt<W> {
         let chunk_size = usize::try_from(chunk_size)
             .map_err(|_| error_invalid_input("chunk size bigger than usize"))?;
 
+        // Apply the memory budget by capping the worker count.
+        let num_workers = match options.max_total_memory {
+            Some(budget_kb) => crate::enc::workers_for_memory_budget(
+                &options.lzma_options,
+                crate::resolve_workers(num_workers),
+                budget_kb,
+            ),
+            None => num_workers,
+        };
+
         // We don't know how many work units we'll have ahead of time.
         let num_work = u64::MAX;
 
@@ -57,7 +88,11 @@ impl<W: Write> Lzma2WriterMt<W> {
             chunk_size,
             current_work_unit: Vec::with_capacity(chunk_size),
             work_pool: WorkPool::new(
-                WorkPoolConfig::new(num_workers, num_work),
+                {
+                    let mut config = WorkPoolConfig::new(num_workers, num_work);
+                    config.shared_pool = shared_pool;
+                    config
+                },
                 worker_thread_logic,
             ),
         })
@@ -74,6 +109,8 @@ impl<W: Write> Lzma2WriterMt<W> {
         let work_data = core::mem::take(&mut self.current_work_unit);
         let mut single_chunk_options = self.options.clone();
         single_chunk_options.chunk_size = None;
+        single_chunk_options.reset_every_n_chunks = None;
+        single_chunk_options.max_output = None;
         single_chunk_options.lzma_options.preset_dict = None;
 
         let mut work_data_opt = Some(work_data);
@@ -82,10 +119,10 @@ impl<W: Write> Lzma2WriterMt<W> {
             let data = work_data_opt.take().ok_or_else(|| {
                 io::Error::new(io::ErrorKind::InvalidInput, "work already provided")
             })?;
-            Ok(WorkUnit {
+            Ok(Some(WorkUnit {
                 data,
                 options: single_chunk_options.clone(),
-            })
+            }))
         })?;
 
         self.drain_available_results()?;
@@ -124,12 +161,7 @@ impl<W: Write> Lzma2WriterMt<W> {
         self.work_pool.finish();
 
         // Wait for all remaining work to complete.
-        while let Some(compressed_data) = self.work_pool.get_result(|_| {
-            Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "no more work to dispatch",
-            ))
-        })? {
+        while let Some(compressed_data) = self.work_pool.get_result(|_| Ok(None))? {
             self.inner.write_all(&compressed_data)?;
         }
 
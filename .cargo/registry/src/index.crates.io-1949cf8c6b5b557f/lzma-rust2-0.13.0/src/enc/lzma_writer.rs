@@ -25,6 +25,13 @@ impl<W: Write> LzmaWriter<W> {
         use_end_marker: bool,
         expected_uncompressed_size: Option<u64>,
     ) -> crate::Result<LzmaWriter<W>> {
+        let mut options = options.clone();
+
+        if let Some(input_size_hint) = options.input_size_hint {
+            options.tune_dict_size_for(input_size_hint);
+        }
+        let options = &options;
+
         let (mut lzma, mode) = LZMAEncoder::new(
             options.mode,
             options.lc,
@@ -34,6 +41,7 @@ impl<W: Write> LzmaWriter<W> {
             options.depth_limit,
             options.dict_size,
             options.nice_len as usize,
+            options.hash_bits_override,
         );
         if let Some(preset_dict) = &options.preset_dict {
             if use_header {
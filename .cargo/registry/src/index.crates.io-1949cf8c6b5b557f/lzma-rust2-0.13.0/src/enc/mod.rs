@@ -1,3 +1,4 @@
+mod dict_trainer;
 mod encoder;
 mod encoder_fast;
 mod encoder_normal;
@@ -7,6 +8,7 @@ mod lzma2_writer_mt;
 mod lzma_writer;
 mod range_enc;
 
+pub use dict_trainer::*;
 pub use encoder::EncodeMode;
 pub use lzma2_writer::*;
 #[cfg(feature = "std")]
@@ -3,3 +3,4 @@
 pub mod bcj;
 pub mod bcj2;
 pub mod delta;
+pub mod multi_channel_delta;
@@ -13,6 +13,7 @@ use crate::Read;
 #[cfg(feature = "encoder")]
 use crate::Write;
 
+#[derive(Clone)]
 struct BCJFilter {
     is_encoder: bool,
     pos: usize,
@@ -75,6 +76,17 @@ impl<R> BcjReader<R> {
         &mut self.inner
     }
 
+    /// Sets the internal filter buffer size in bytes (default 4096).
+    ///
+    /// A larger buffer reduces the number of reads issued against the inner
+    /// reader in high-throughput pipelines. The size is clamped to at least
+    /// 32 bytes, so every filter can make progress across its alignment
+    /// window. Must be called before the first read.
+    pub fn with_buffer_size(mut self, size: usize) -> Self {
+        self.state.filter_buf = vec![0; size.max(32)];
+        self
+    }
+
     /// Creates a new BCJ reader for x86 instruction filtering.
     #[inline]
     pub fn new_x86(inner: R, start_pos: usize) -> Self {
@@ -152,7 +164,7 @@ impl<R: Read> Read for BcjReader<R> {
             // If end of filterBuf was reached, move the pending data to
             // the beginning of the buffer so that more data can be
             // copied into filterBuf on the next loop iteration.
-            if state.pos + state.filtered + state.unfiltered == FILTER_BUF_SIZE {
+            if state.pos + state.filtered + state.unfiltered == state.filter_buf.len() {
                 // state.filter_buf.copy_from_slice(src);
                 state.filter_buf.rotate_left(state.pos);
                 state.pos = 0;
@@ -165,7 +177,8 @@ impl<R: Read> Read for BcjReader<R> {
 
             assert_eq!(state.filtered, 0);
             // Get more data into the temporary buffer.
-            let mut in_size = FILTER_BUF_SIZE - (state.pos + state.filtered + state.unfiltered);
+            let mut in_size =
+                state.filter_buf.len() - (state.pos + state.filtered + state.unfiltered);
             let start = state.pos + state.filtered + state.unfiltered;
             let temp = &mut state.filter_buf[start..(start + in_size)];
             in_size = match self.inner.read(temp) {
@@ -200,6 +213,7 @@ impl<R: Read> Read for BcjReader<R> {
 pub struct BcjWriter<W> {
     inner: W,
     filter: BCJFilter,
+    initial_filter: BCJFilter,
     buffer: Vec<u8>,
 }
 
@@ -208,11 +222,37 @@ impl<W> BcjWriter<W> {
     fn new(inner: W, filter: BCJFilter) -> Self {
         Self {
             inner,
+            initial_filter: filter.clone(),
             filter,
             buffer: Vec::with_capacity(FILTER_BUF_SIZE),
         }
     }
 
+    /// Writes the retained unfilterable tail unfiltered, flushes the inner
+    /// writer and restarts the filter, ending the current logical record.
+    ///
+    /// The writer holds back up to one instruction's worth of bytes it
+    /// cannot filter yet. Emitting them unfiltered is safe because the
+    /// filter leaves a trailing incomplete instruction unmodified on both
+    /// sides - but only at a record boundary: the reading side must decode
+    /// everything up to this point as its own complete BCJ stream (with its
+    /// own [`BcjReader`]), as in framed protocols. Data written afterwards
+    /// starts a fresh filter context, exactly as if a new writer had been
+    /// created.
+    pub fn flush_partial(&mut self) -> crate::Result<()>
+    where
+        W: Write,
+    {
+        if !self.buffer.is_empty() {
+            self.inner.write_all(&self.buffer)?;
+            self.buffer.clear();
+        }
+
+        self.filter = self.initial_filter.clone();
+
+        self.inner.flush()
+    }
+
     /// Unwraps the writer, returning the underlying writer.
     pub fn into_inner(self) -> W {
         self.inner
@@ -470,4 +510,103 @@ mod tests {
 
         assert!(test_data == decoded_data);
     }
+
+    #[test]
+    fn test_bcj_ia64_byte_at_a_time() {
+        use crate::Write;
+
+        // IA64 processes 16-byte bundles. Writing one byte at a time forces
+        // the writer to retain partial bundles across write calls; the result
+        // must match a single bulk write.
+        let test_data = std::fs::read("tests/data/wget-ia64").unwrap();
+        let test_data = &test_data[..256 * 1024];
+
+        let mut bulk_buffer = Vec::new();
+        let mut writer = BcjWriter::new_ia64(Cursor::new(&mut bulk_buffer), 0);
+        copy(&mut &test_data[..], &mut writer).expect("Failed to encode data");
+        writer.finish().expect("Failed to finish encoding");
+
+        let mut trickle_buffer = Vec::new();
+        let mut writer = BcjWriter::new_ia64(Cursor::new(&mut trickle_buffer), 0);
+        for byte in test_data {
+            writer.write_all(core::slice::from_ref(byte)).unwrap();
+        }
+        writer.finish().expect("Failed to finish encoding");
+
+        assert!(bulk_buffer == trickle_buffer);
+
+        // Odd-sized batches straddle bundle boundaries in a different way.
+        let mut batch_buffer = Vec::new();
+        let mut writer = BcjWriter::new_ia64(Cursor::new(&mut batch_buffer), 0);
+        for batch in test_data.chunks(23) {
+            writer.write_all(batch).unwrap();
+        }
+        writer.finish().expect("Failed to finish encoding");
+
+        assert!(bulk_buffer == batch_buffer);
+    }
+
+    #[test]
+    fn test_bcj_tiny_buffer_roundtrip() {
+        // A 16 byte requested buffer (clamped to 32) forces constant buffer
+        // rotation and partial filter windows.
+        let test_data = std::fs::read("tests/data/wget-x86").unwrap();
+        let test_data = &test_data[..128 * 1024];
+
+        let mut encoded_buffer = Vec::new();
+        let mut writer = BcjWriter::new_x86(Cursor::new(&mut encoded_buffer), 0);
+        copy(&mut &test_data[..], &mut writer).expect("Failed to encode data");
+        writer.finish().expect("Failed to finish encoding");
+
+        let mut decoded_data = Vec::new();
+        let mut reader = BcjReader::new_x86(Cursor::new(&encoded_buffer), 0).with_buffer_size(16);
+        copy(&mut reader, &mut decoded_data).expect("Failed to decode data");
+
+        assert!(test_data == decoded_data);
+
+        // A large buffer produces identical output as well.
+        let mut decoded_data = Vec::new();
+        let mut reader =
+            BcjReader::new_x86(Cursor::new(&encoded_buffer), 0).with_buffer_size(1 << 20);
+        copy(&mut reader, &mut decoded_data).expect("Failed to decode data");
+
+        assert!(test_data == decoded_data);
+    }
+
+    #[test]
+    fn partial_flush_round_trips_per_record() {
+        let data = std::fs::read("tests/data/wget-x86").unwrap();
+
+        // Record sizes chosen so the filter retains a tail at the boundary.
+        let record1 = &data[..100_003];
+        let record2 = &data[100_003..200_001];
+
+        let mut writer = BcjWriter::new_x86(Vec::new(), 0);
+        writer.write_all(record1).unwrap();
+        writer.flush_partial().unwrap();
+        let boundary = writer.inner().len();
+        writer.write_all(record2).unwrap();
+        let filtered = writer.finish().unwrap();
+
+        // Each record decodes as its own complete BCJ stream.
+        let mut reader = BcjReader::new_x86(&filtered[..boundary], 0);
+        let mut first = Vec::new();
+        copy(&mut reader, &mut first).unwrap();
+        assert!(first == record1);
+
+        let mut reader = BcjReader::new_x86(&filtered[boundary..], 0);
+        let mut second = Vec::new();
+        copy(&mut reader, &mut second).unwrap();
+        assert!(second == record2);
+
+        // A flush with nothing retained is a no-op boundary.
+        let mut writer = BcjWriter::new_x86(Vec::new(), 0);
+        writer.flush_partial().unwrap();
+        writer.write_all(record1).unwrap();
+        let filtered = writer.finish().unwrap();
+        let mut reader = BcjReader::new_x86(filtered.as_slice(), 0);
+        let mut back = Vec::new();
+        copy(&mut reader, &mut back).unwrap();
+        assert!(back == record1);
+    }
 }
@@ -50,6 +50,9 @@ impl Delta {
 }
 
 /// Reader that applies delta filtering to decompress data.
+///
+/// The delta transform is applied in place on the caller's buffer, so this
+/// reader is unbuffered and there is no internal buffer size to tune.
 pub struct DeltaReader<R> {
     inner: R,
     delta: Delta,
@@ -0,0 +1,324 @@
+//! Detection of compressed stream formats from their leading bytes.
+
+use crate::Read;
+
+/// A compressed stream format recognized by [`detect_format`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    /// XZ container format.
+    Xz,
+    /// LZIP container format.
+    Lzip,
+    /// Raw LZMA2 chunk stream.
+    Lzma2,
+    /// Legacy "LZMA alone" (`.lzma`) stream.
+    LzmaAlone,
+    /// None of the known formats matched.
+    Unknown,
+}
+
+/// Detects the format of a compressed buffer from its leading bytes.
+///
+/// XZ and LZIP carry unambiguous magic numbers. Raw LZMA2 and LZMA alone
+/// streams have none, so they are recognized heuristically: LZMA2 by a
+/// valid first chunk control byte (the first chunk must reset the
+/// dictionary), LZMA alone by a plausible properties byte, dictionary size
+/// and uncompressed size field. The heuristics can misclassify crafted
+/// input; treat the result as a hint, not as validation.
+///
+/// At least 13 bytes should be provided to make all formats detectable.
+pub fn detect_format(data: &[u8]) -> Format {
+    // XZ magic: FD 37 7A 58 5A 00.
+    if data.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+        return Format::Xz;
+    }
+
+    // LZIP magic plus the version byte (only version 1 is in use).
+    if data.len() >= 5 && data.starts_with(b"LZIP") && data[4] <= 1 {
+        return Format::Lzip;
+    }
+
+    // LZMA2: the first chunk must reset the dictionary, so only an
+    // uncompressed chunk with dictionary reset (0x01) or a compressed chunk
+    // with properties and dictionary reset (0xE0..=0xFF) can start a
+    // stream. The buffer must also hold the chunk's header, which keeps a
+    // truncated XZ magic (starting with 0xFD) from matching.
+    if data.len() >= 3 && data[0] == 0x01 {
+        return Format::Lzma2;
+    }
+
+    if data.len() >= 6 && data[0] >= 0xE0 {
+        return Format::Lzma2;
+    }
+
+    // LZMA alone: a properties byte encoding lc/lp/pb (< 9 * 5 * 5), a
+    // 4-byte little-endian dictionary size and an 8-byte uncompressed size,
+    // which is either unknown (all 0xFF) or reasonably small.
+    if data.len() >= 13 && data[0] < 225 {
+        let dict_size = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+        let uncompressed_size = u64::from_le_bytes([
+            data[5], data[6], data[7], data[8], data[9], data[10], data[11], data[12],
+        ]);
+
+        if dict_size >= crate::DICT_SIZE_MIN
+            && (uncompressed_size == u64::MAX || uncompressed_size < 1 << 40)
+        {
+            return Format::LzmaAlone;
+        }
+    }
+
+    Format::Unknown
+}
+
+/// A reader that detects the format of the compressed stream from its
+/// magic bytes and delegates to the matching decompressor.
+///
+/// The peeked bytes are buffered, so the chosen reader sees the complete
+/// stream. XZ streams are read with multiple concatenated streams allowed,
+/// matching the `xz` command line tool. Raw LZMA2 streams are rejected:
+/// they do not carry their dictionary size, which
+/// [`Lzma2Reader`](crate::Lzma2Reader) needs up front.
+pub struct AnyReader<R: Read> {
+    kind: AnyReaderKind<R>,
+    format: Format,
+    continue_streams: bool,
+}
+
+#[allow(clippy::large_enum_variant)]
+enum AnyReaderKind<R: Read> {
+    #[cfg(feature = "xz")]
+    Xz(crate::XzReader<PrefixReader<R>>),
+    #[cfg(feature = "lzip")]
+    Lzip(crate::LzipReader<PrefixReader<R>>),
+    LzmaAlone(crate::LzmaReader<PrefixReader<R>>),
+    Finished,
+}
+
+impl<R: Read> AnyReader<R> {
+    /// Creates a reader by peeking at the stream's magic bytes.
+    ///
+    /// Reports an unsupported error when the format is not recognized, or
+    /// when the crate was built without the feature for the detected
+    /// format.
+    pub fn new(inner: R) -> crate::Result<Self> {
+        Self::construct(inner, false)
+    }
+
+    /// Like [`new`](Self::new), but when one stream ends the next stream's
+    /// format is detected again, so heterogeneous concatenations like an XZ
+    /// stream followed by an LZIP stream decode as one contiguous output.
+    /// Zero padding between streams is skipped. Trailing data that is not a
+    /// recognized stream reports an error, and no stream can follow a raw
+    /// LZMA stream, since its decoder buffers past its own end.
+    pub fn new_multi(inner: R) -> crate::Result<Self> {
+        Self::construct(inner, true)
+    }
+
+    fn construct(mut inner: R, continue_streams: bool) -> crate::Result<Self> {
+        let prefix = peek_prefix(&mut inner, alloc::vec::Vec::new())?;
+        let format = detect_format(&prefix);
+        let (kind, format) = dispatch(format, prefix, inner, continue_streams)?;
+
+        Ok(Self {
+            kind,
+            format,
+            continue_streams,
+        })
+    }
+
+    /// The format that was detected for the (current) stream.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+}
+
+impl<R: Read> Read for AnyReader<R> {
+    // Without the xz and lzip features only raw LZMA streams remain, whose
+    // arm diverges, making the re-detection below unreachable.
+    #[cfg_attr(not(any(feature = "xz", feature = "lzip")), allow(unreachable_code))]
+    fn read(&mut self, buf: &mut [u8]) -> crate::Result<usize> {
+        loop {
+            let read = match &mut self.kind {
+                #[cfg(feature = "xz")]
+                AnyReaderKind::Xz(reader) => reader.read(buf)?,
+                #[cfg(feature = "lzip")]
+                AnyReaderKind::Lzip(reader) => reader.read(buf)?,
+                AnyReaderKind::LzmaAlone(reader) => reader.read(buf)?,
+                AnyReaderKind::Finished => return Ok(0),
+            };
+
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+
+            if !self.continue_streams {
+                self.kind = AnyReaderKind::Finished;
+                return Ok(0);
+            }
+
+            // The active stream ended: recover the inner reader along with
+            // any bytes its reader probed past the stream end, and detect
+            // the next stream's format.
+            let (mut inner, leftover) =
+                match core::mem::replace(&mut self.kind, AnyReaderKind::Finished) {
+                    #[cfg(feature = "xz")]
+                    AnyReaderKind::Xz(reader) => {
+                        (reader.into_inner().inner, alloc::vec::Vec::new())
+                    }
+                    #[cfg(feature = "lzip")]
+                    AnyReaderKind::Lzip(reader) => {
+                        let (prefix_reader, leftover) = reader.into_inner_with_leftover();
+                        (prefix_reader.inner, leftover)
+                    }
+                    AnyReaderKind::LzmaAlone(reader) => {
+                        // The range decoder buffers input past the stream end,
+                        // so the position of the inner reader is not the stream
+                        // boundary. Re-detection would misattribute bytes.
+                        let mut probe = [0u8; 1];
+                        return match reader.into_inner().inner.read(&mut probe)? {
+                            0 => Ok(0),
+                            _ => Err(crate::error_unsupported(
+                                "cannot detect a following stream after a raw LZMA stream",
+                            )),
+                        };
+                    }
+                    AnyReaderKind::Finished => return Ok(0),
+                };
+
+            let mut prefix = peek_prefix(&mut inner, leftover)?;
+
+            // Skip zero padding between streams.
+            while prefix.first() == Some(&0) {
+                let zeros = prefix.iter().take_while(|byte| **byte == 0).count();
+                prefix.drain(..zeros);
+                prefix = peek_prefix(&mut inner, prefix)?;
+
+                if prefix.is_empty() {
+                    break;
+                }
+            }
+
+            if prefix.is_empty() {
+                return Ok(0);
+            }
+
+            let format = detect_format(&prefix);
+
+            if format == Format::Unknown {
+                return Err(crate::error_invalid_data(
+                    "trailing data is not a recognized stream",
+                ));
+            }
+
+            let (kind, format) = dispatch(format, prefix, inner, true)?;
+            self.kind = kind;
+            self.format = format;
+        }
+    }
+}
+
+/// Reads up to 13 bytes (on top of `leftover`) for format detection.
+fn peek_prefix<R: Read>(
+    inner: &mut R,
+    leftover: alloc::vec::Vec<u8>,
+) -> crate::Result<alloc::vec::Vec<u8>> {
+    let mut prefix = leftover;
+    let target = 13;
+
+    while prefix.len() < target {
+        let mut chunk = [0u8; 13];
+        let wanted = target - prefix.len();
+
+        match inner.read(&mut chunk[..wanted])? {
+            0 => break,
+            read => prefix.extend_from_slice(&chunk[..read]),
+        }
+    }
+
+    Ok(prefix)
+}
+
+#[cfg_attr(not(feature = "xz"), allow(unused_variables))]
+fn dispatch<R: Read>(
+    format: Format,
+    prefix: alloc::vec::Vec<u8>,
+    inner: R,
+    continue_streams: bool,
+) -> crate::Result<(AnyReaderKind<R>, Format)> {
+    let reader = PrefixReader {
+        prefix,
+        position: 0,
+        inner,
+    };
+
+    let kind = match format {
+        // In multi mode the XZ reader handles a single stream and this
+        // module takes over at the boundary; otherwise concatenated XZ
+        // streams stay the XZ reader's job, as in the xz tool.
+        #[cfg(feature = "xz")]
+        Format::Xz => AnyReaderKind::Xz(crate::XzReader::new(reader, !continue_streams)),
+        #[cfg(feature = "lzip")]
+        Format::Lzip => AnyReaderKind::Lzip(crate::LzipReader::new(reader)?),
+        Format::LzmaAlone => {
+            AnyReaderKind::LzmaAlone(crate::LzmaReader::new_mem_limit(reader, u32::MAX, None)?)
+        }
+        Format::Lzma2 => {
+            return Err(crate::error_unsupported(
+                "raw LZMA2 carries no dictionary size, use Lzma2Reader",
+            ))
+        }
+        _ => return Err(crate::error_unsupported("unknown compression format")),
+    };
+
+    Ok((kind, format))
+}
+
+/// Serves the peeked prefix before continuing with the inner reader.
+struct PrefixReader<R> {
+    prefix: alloc::vec::Vec<u8>,
+    position: usize,
+    inner: R,
+}
+
+impl<R: Read> Read for PrefixReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> crate::Result<usize> {
+        if self.position < self.prefix.len() {
+            let available = &self.prefix[self.position..];
+            let read = available.len().min(buf.len());
+            buf[..read].copy_from_slice(&available[..read]);
+            self.position += read;
+            return Ok(read);
+        }
+
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_each_format() {
+        assert_eq!(
+            detect_format(&[0xFD, b'7', b'z', b'X', b'Z', 0x00, 0x00, 0x04]),
+            Format::Xz
+        );
+        assert_eq!(detect_format(b"LZIP\x01\x14"), Format::Lzip);
+        assert_eq!(detect_format(&[0x01, 0x00, 0x0F]), Format::Lzma2);
+        assert_eq!(
+            detect_format(&[0xE0, 0x00, 0x1F, 0x00, 0x20, 0x5D]),
+            Format::Lzma2
+        );
+
+        // Typical LZMA alone header: props 0x5D, 1 MiB dict, unknown size.
+        let mut lzma_alone = [0xFF; 13];
+        lzma_alone[0] = 0x5D;
+        lzma_alone[1..5].copy_from_slice(&(1u32 << 20).to_le_bytes());
+        assert_eq!(detect_format(&lzma_alone), Format::LzmaAlone);
+
+        assert_eq!(detect_format(b"not compressed data"), Format::Unknown);
+        assert_eq!(detect_format(&[]), Format::Unknown);
+        assert_eq!(detect_format(&[0xFD, b'7']), Format::Unknown);
+    }
+}
@@ -42,6 +42,13 @@
 //! Note that multithreaded features are not available in `no_std` mode as they require
 //! standard library threading primitives.
 //!
+//! ## Binary Size
+//!
+//! The CRC32 / CRC64 checksums used by the `lzip` and `xz` features embed 16-slice
+//! lookup tables (48 KiB combined) for maximal throughput. On flash-constrained
+//! targets the `small-crc-tables` feature switches to byte-at-a-time tables (3 KiB
+//! combined) at the cost of roughly 4-8x slower checksum throughput.
+//!
 //! ## License
 //!
 //! Licensed under the [Apache License, Version 2.0](https://www.apache.org/licenses/LICENSE-2.0).
@@ -54,10 +61,14 @@
 
 extern crate alloc;
 
+mod buffer_provider;
 mod decoder;
+mod format;
 mod lz;
 #[cfg(feature = "lzip")]
 mod lzip;
+mod lzma2_chunk_reader;
+mod lzma2_push_decoder;
 mod lzma2_reader;
 mod lzma_reader;
 mod range_dec;
@@ -76,6 +87,8 @@ pub mod filter;
 mod lzma2_reader_mt;
 #[cfg(not(feature = "std"))]
 mod no_std;
+#[cfg(all(feature = "std", feature = "typed-errors"))]
+mod typed_error;
 #[cfg(feature = "std")]
 mod work_pool;
 
@@ -86,18 +99,22 @@ pub(crate) use std::io::Read;
 #[cfg(feature = "std")]
 pub(crate) use std::io::Write;
 
+pub use buffer_provider::{BufferProvider, GlobalBufferProvider};
 #[cfg(feature = "encoder")]
 pub use enc::*;
+pub use format::{detect_format, AnyReader, Format};
 pub use lz::MfType;
-#[cfg(feature = "lzip")]
-pub use lzip::LzipReader;
 #[cfg(all(feature = "lzip", feature = "std"))]
 pub use lzip::LzipReaderMt;
 #[cfg(all(feature = "lzip", feature = "encoder"))]
-pub use lzip::{AutoFinishLzipWriter, LzipOptions, LzipWriter};
+pub use lzip::{lzip_compress, AutoFinishLzipWriter, LzipOptions, LzipWriter};
+#[cfg(feature = "lzip")]
+pub use lzip::{lzip_decompress, LzipReader};
 #[cfg(all(feature = "lzip", feature = "encoder", feature = "std"))]
 pub use lzip::{AutoFinishLzipWriterMt, LzipWriterMt};
-pub use lzma2_reader::{get_memory_usage as lzma2_get_memory_usage, Lzma2Reader};
+pub use lzma2_chunk_reader::{Lzma2Chunk, Lzma2ChunkReader};
+pub use lzma2_push_decoder::Lzma2PushDecoder;
+pub use lzma2_reader::{get_memory_usage as lzma2_get_memory_usage, lzma2_decompress, Lzma2Reader};
 #[cfg(feature = "std")]
 pub use lzma2_reader_mt::Lzma2ReaderMt;
 pub use lzma_reader::{
@@ -111,14 +128,26 @@ pub use no_std::Read;
 #[cfg(not(feature = "std"))]
 pub use no_std::Write;
 use state::*;
-#[cfg(all(feature = "xz", feature = "std"))]
-pub use xz::XzReaderMt;
+#[cfg(all(feature = "std", feature = "typed-errors"))]
+pub use typed_error::LzmaError;
+#[cfg(feature = "std")]
+pub use work_pool::SharedWorkerPool;
+#[cfg(feature = "xz")]
+pub use xz::{
+    try_decode_xz, xz_crc32, xz_crc64, xz_decompress, BlockHeaderCallback, BlockInfo, BlockLayout,
+    CheckType, Crc32Hasher, Crc64Hasher, Filter, FilterConfig, FilterType, XzReader,
+};
 #[cfg(all(feature = "xz", feature = "encoder"))]
-pub use xz::{AutoFinishXzWriter, XzOptions, XzWriter};
+pub use xz::{
+    xz_compress, AutoFinishXzWriter, XzFilterChainBuilder, XzMultiStreamWriter, XzOptions, XzWriter,
+};
+#[cfg(all(feature = "xz", feature = "std"))]
+pub use xz::{
+    xz_list, IndexedXzReader, SharedSlice, XzListing, XzReaderMt, XzReaderMtStream, XzRecord,
+    XzRecordReader, XzStreamInfo,
+};
 #[cfg(all(feature = "xz", feature = "encoder", feature = "std"))]
-pub use xz::{AutoFinishXzWriterMt, XzWriterMt};
-#[cfg(feature = "xz")]
-pub use xz::{CheckType, XzReader};
+pub use xz::{AutoFinishXzWriterMt, IndexedXzWriter, XzWriterMt};
 
 /// Result type of the crate.
 #[cfg(feature = "std")]
@@ -128,12 +157,71 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[cfg(not(feature = "std"))]
 pub type Result<T> = core::result::Result<T, Error>;
 
+/// CRC lookup table width used for the CRC32 / CRC64 checksums.
+///
+/// By default a 16-slice table is used for maximal throughput. Enabling the
+/// `small-crc-tables` feature switches to a byte-at-a-time table, which shrinks
+/// the embedded tables considerably (16 KiB -> 1 KiB for CRC32, 32 KiB -> 2 KiB
+/// for CRC64) at the cost of roughly 4-8x slower checksum throughput.
+#[cfg(all(feature = "crc", not(feature = "small-crc-tables")))]
+pub(crate) type CrcTable = crc::Table<16>;
+
+/// CRC lookup table width used for the CRC32 / CRC64 checksums.
+///
+/// Byte-at-a-time variant selected by the `small-crc-tables` feature.
+#[cfg(all(feature = "crc", feature = "small-crc-tables"))]
+pub(crate) type CrcTable = crc::Table<1>;
+
 /// The minimal size of a dictionary.
 pub const DICT_SIZE_MIN: u32 = 4096;
 
 /// The maximal size of a dictionary.
 pub const DICT_SIZE_MAX: u32 = u32::MAX & !15_u32;
 
+/// Converts an LZMA2 dictionary size in bytes to the one-byte property
+/// value used by the XZ LZMA2 filter and the `.lzma`-style headers.
+///
+/// The encoding only represents sizes of the form `2^n` and `3 * 2^n`;
+/// other sizes round up to the next representable value. Sizes below
+/// [`DICT_SIZE_MIN`] are rejected.
+pub fn lzma2_dict_size_to_prop(dict_size: u32) -> Result<u8> {
+    if dict_size < DICT_SIZE_MIN {
+        return Err(error_invalid_dict_size("LZMA2 dictionary size too small"));
+    }
+
+    if dict_size == 0xFFFFFFFF {
+        return Ok(40);
+    }
+
+    for prop in 0u8..40 {
+        let base = 2 | ((prop & 1) as u32);
+        let size = base << (prop / 2 + 11);
+
+        if size >= dict_size {
+            return Ok(prop);
+        }
+    }
+
+    Ok(40)
+}
+
+/// Converts an LZMA2 dictionary size property byte back to the size in
+/// bytes. Only the 41 values `0..=40` are valid.
+pub fn lzma2_prop_to_dict_size(prop: u8) -> Result<u32> {
+    if prop > 40 {
+        return Err(error_invalid_input(
+            "invalid LZMA2 dictionary size property",
+        ));
+    }
+
+    if prop == 40 {
+        return Ok(0xFFFFFFFF);
+    }
+
+    let base = 2 | ((prop & 1) as u32);
+    Ok(base << (prop / 2 + 11))
+}
+
 const LOW_SYMBOLS: usize = 1 << 3;
 const MID_SYMBOLS: usize = 1 << 3;
 const HIGH_SYMBOLS: usize = 1 << 8;
@@ -167,6 +255,80 @@ const RC_BIT_MODEL_OFFSET: u32 = (1u32 << MOVE_BITS)
     .wrapping_sub(1)
     .wrapping_sub(BIT_MODEL_TOTAL);
 
+/// Returns the number of worker threads used when a multithreaded reader or
+/// writer is given `0` as its worker count ("auto").
+///
+/// This is [`std::thread::available_parallelism`] clamped to the 256 thread
+/// cap of the multithreaded APIs, falling back to `1` when the parallelism
+/// cannot be determined.
+#[cfg(feature = "std")]
+pub fn auto_workers() -> u32 {
+    std::thread::available_parallelism()
+        .map(|workers| workers.get() as u32)
+        .unwrap_or(1)
+        .min(256)
+}
+
+/// Resolves a user-provided worker count: `0` means "auto", everything else is
+/// clamped to the 256 thread cap.
+#[cfg(feature = "std")]
+fn resolve_workers(num_workers: u32) -> u32 {
+    if num_workers == 0 {
+        auto_workers()
+    } else {
+        num_workers.min(256)
+    }
+}
+
+/// Reads `reader` to the end, erroring once the output would exceed
+/// `max_output_size` bytes. Shared by the one-shot decompress helpers to
+/// guard against decompression bombs.
+fn read_to_end_capped<R: Read>(
+    reader: &mut R,
+    max_output_size: usize,
+) -> Result<alloc::vec::Vec<u8>> {
+    let mut output = alloc::vec::Vec::new();
+    let mut chunk = [0u8; 8192];
+
+    loop {
+        let bytes_read = reader.read(&mut chunk)?;
+
+        if bytes_read == 0 {
+            return Ok(output);
+        }
+
+        if output.len() + bytes_read > max_output_size {
+            return Err(error_other("decompressed output exceeds the size cap"));
+        }
+
+        output.extend_from_slice(&chunk[..bytes_read]);
+    }
+}
+
+/// Wraps a worker error with the failing block's sequence number, so
+/// multithreaded decode errors identify the block or chunk that caused
+/// them. With the `typed-errors` feature the index stays available as
+/// [`LzmaError::InBlock`].
+#[cfg(feature = "std")]
+fn error_with_block_context(error: Error, block_index: u64) -> Error {
+    #[cfg(feature = "typed-errors")]
+    if error
+        .get_ref()
+        .is_some_and(|source| source.is::<LzmaError>())
+    {
+        let kind = error.kind();
+        let source = error
+            .into_inner()
+            .expect("source checked")
+            .downcast::<LzmaError>()
+            .expect("type checked");
+
+        return Error::new(kind, LzmaError::InBlock(block_index, source));
+    }
+
+    Error::new(error.kind(), std::format!("block {block_index}: {error}"))
+}
+
 /// Helper to set the shared error state and trigger shutdown.
 #[cfg(feature = "std")]
 fn set_error(
@@ -422,42 +584,125 @@ impl<T: Write> ByteWriter for T {
 #[cfg(feature = "std")]
 #[inline(always)]
 fn error_eof() -> Error {
+    #[cfg(feature = "typed-errors")]
+    return Error::new(std::io::ErrorKind::UnexpectedEof, LzmaError::Truncated);
+    #[cfg(not(feature = "typed-errors"))]
     Error::new(std::io::ErrorKind::UnexpectedEof, "unexpected EOF")
 }
 
+#[cfg(feature = "std")]
+#[inline(always)]
+fn is_error_eof(error: &Error) -> bool {
+    error.kind() == std::io::ErrorKind::UnexpectedEof
+}
+
 #[cfg(feature = "std")]
 #[inline(always)]
 fn error_other(msg: &'static str) -> Error {
+    #[cfg(feature = "typed-errors")]
+    return Error::other(LzmaError::Other(msg));
+    #[cfg(not(feature = "typed-errors"))]
     Error::other(msg)
 }
 
 #[cfg(feature = "std")]
 #[inline(always)]
 fn error_invalid_input(msg: &'static str) -> Error {
+    #[cfg(feature = "typed-errors")]
+    return Error::new(
+        std::io::ErrorKind::InvalidInput,
+        LzmaError::InvalidInput(msg),
+    );
+    #[cfg(not(feature = "typed-errors"))]
     Error::new(std::io::ErrorKind::InvalidInput, msg)
 }
 
 #[cfg(feature = "std")]
 #[inline(always)]
 fn error_invalid_data(msg: &'static str) -> Error {
+    #[cfg(feature = "typed-errors")]
+    return Error::new(std::io::ErrorKind::InvalidData, LzmaError::Corrupted(msg));
+    #[cfg(not(feature = "typed-errors"))]
     Error::new(std::io::ErrorKind::InvalidData, msg)
 }
 
 #[cfg(feature = "std")]
 #[inline(always)]
 fn error_out_of_memory(msg: &'static str) -> Error {
+    #[cfg(feature = "typed-errors")]
+    return Error::new(std::io::ErrorKind::OutOfMemory, LzmaError::OutOfMemory(msg));
+    #[cfg(not(feature = "typed-errors"))]
     Error::new(std::io::ErrorKind::OutOfMemory, msg)
 }
 
 #[cfg(feature = "std")]
 #[inline(always)]
 fn error_unsupported(msg: &'static str) -> Error {
+    #[cfg(feature = "typed-errors")]
+    return Error::new(std::io::ErrorKind::Unsupported, LzmaError::Unsupported(msg));
+    #[cfg(not(feature = "typed-errors"))]
     Error::new(std::io::ErrorKind::Unsupported, msg)
 }
 
+/// An invalid-data error for a stored checksum that does not match,
+/// carrying [`LzmaError::ChecksumMismatch`] with the `typed-errors` feature.
+#[cfg(feature = "std")]
+#[inline(always)]
+fn error_checksum_mismatch(msg: &'static str) -> Error {
+    #[cfg(feature = "typed-errors")]
+    return {
+        let _ = msg;
+        Error::new(std::io::ErrorKind::InvalidData, LzmaError::ChecksumMismatch)
+    };
+    #[cfg(not(feature = "typed-errors"))]
+    Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+/// An invalid-input error for a dictionary size outside the valid range,
+/// carrying [`LzmaError::InvalidDictSize`] with the `typed-errors` feature.
+#[cfg(feature = "std")]
+#[inline(always)]
+fn error_invalid_dict_size(msg: &'static str) -> Error {
+    #[cfg(feature = "typed-errors")]
+    return {
+        let _ = msg;
+        Error::new(std::io::ErrorKind::InvalidInput, LzmaError::InvalidDictSize)
+    };
+    #[cfg(not(feature = "typed-errors"))]
+    Error::new(std::io::ErrorKind::InvalidInput, msg)
+}
+
+/// An unsupported error for a filter rejected by name, carrying
+/// [`LzmaError::UnsupportedFilter`] with the `typed-errors` feature.
+#[cfg(all(feature = "std", feature = "xz"))]
+#[inline(always)]
+fn error_unsupported_filter(filter: FilterType, msg: &'static str) -> Error {
+    #[cfg(feature = "typed-errors")]
+    return {
+        let _ = msg;
+        Error::new(
+            std::io::ErrorKind::Unsupported,
+            LzmaError::UnsupportedFilter(filter),
+        )
+    };
+    #[cfg(not(feature = "typed-errors"))]
+    {
+        let _ = filter;
+        Error::new(std::io::ErrorKind::Unsupported, msg)
+    }
+}
+
 #[cfg(feature = "std")]
 #[inline(always)]
 fn copy_error(error: &Error) -> Error {
+    #[cfg(feature = "typed-errors")]
+    if let Some(typed) = error
+        .get_ref()
+        .and_then(|source| source.downcast_ref::<LzmaError>())
+    {
+        return Error::new(error.kind(), typed.clone());
+    }
+
     Error::new(error.kind(), error.to_string())
 }
 
@@ -467,6 +712,12 @@ fn error_eof() -> Error {
     Error::EOF
 }
 
+#[cfg(not(feature = "std"))]
+#[inline(always)]
+fn is_error_eof(error: &Error) -> bool {
+    matches!(error, Error::EOF)
+}
+
 #[cfg(not(feature = "std"))]
 #[inline(always)]
 fn error_other(msg: &'static str) -> Error {
@@ -497,6 +748,25 @@ fn error_unsupported(msg: &'static str) -> Error {
     Error::Unsupported(msg)
 }
 
+#[cfg(not(feature = "std"))]
+#[inline(always)]
+fn error_checksum_mismatch(msg: &'static str) -> Error {
+    Error::InvalidData(msg)
+}
+
+#[cfg(not(feature = "std"))]
+#[inline(always)]
+fn error_invalid_dict_size(msg: &'static str) -> Error {
+    Error::InvalidInput(msg)
+}
+
+#[cfg(all(not(feature = "std"), feature = "xz"))]
+#[inline(always)]
+fn error_unsupported_filter(filter: FilterType, msg: &'static str) -> Error {
+    let _ = filter;
+    Error::Unsupported(msg)
+}
+
 #[cfg(not(feature = "std"))]
 #[inline(always)]
 fn copy_error(error: &Error) -> Error {
@@ -506,6 +776,7 @@ fn copy_error(error: &Error) -> Error {
 struct CountingReader<R> {
     inner: R,
     bytes_read: u64,
+    limit: Option<u64>,
 }
 
 impl<R> CountingReader<R> {
@@ -513,11 +784,21 @@ impl<R> CountingReader<R> {
         Self {
             inner,
             bytes_read: 0,
+            limit: None,
         }
     }
 
     fn with_count(inner: R, bytes_read: u64) -> Self {
-        Self { inner, bytes_read }
+        Self {
+            inner,
+            bytes_read,
+            limit: None,
+        }
+    }
+
+    /// Caps `bytes_read` at `limit`: once reached, reads report EOF.
+    fn set_limit(&mut self, limit: Option<u64>) {
+        self.limit = limit;
     }
 
     fn bytes_read(&self) -> u64 {
@@ -539,12 +820,74 @@ impl<R> CountingReader<R> {
 
 impl<R: Read> Read for CountingReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let read_size = self.inner.read(buf)?;
+        let max_read = match self.limit {
+            Some(limit) => (limit.saturating_sub(self.bytes_read) as usize).min(buf.len()),
+            None => buf.len(),
+        };
+
+        if max_read == 0 {
+            return Ok(0);
+        }
+
+        let read_size = self.inner.read(&mut buf[..max_read])?;
         self.bytes_read += read_size as u64;
         Ok(read_size)
     }
 }
 
+/// Forwards writes to the inner writer and also hands every successfully
+/// written byte to a callback.
+///
+/// This lets callers checksum the uncompressed data while compressing in a
+/// single pass, without reading the input twice:
+///
+/// ```
+/// use std::io::Write;
+///
+/// use lzma_rust2::{TeeWriter, XzOptions, XzWriter};
+///
+/// let mut uncompressed_len = 0;
+/// let mut writer = TeeWriter::new(
+///     XzWriter::new(Vec::new(), XzOptions::with_preset(1)).unwrap(),
+///     |bytes: &[u8]| uncompressed_len += bytes.len(),
+/// );
+/// writer.write_all(b"tee example").unwrap();
+/// writer.into_inner().finish().unwrap();
+/// assert_eq!(uncompressed_len, 11);
+/// ```
+#[cfg(feature = "encoder")]
+pub struct TeeWriter<W, F> {
+    inner: W,
+    callback: F,
+}
+
+#[cfg(feature = "encoder")]
+impl<W, F> TeeWriter<W, F> {
+    /// Creates a new tee writer forwarding to `inner` and reporting written
+    /// bytes to `callback`.
+    pub fn new(inner: W, callback: F) -> Self {
+        Self { inner, callback }
+    }
+
+    /// Consumes the tee writer and returns the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(feature = "encoder")]
+impl<W: Write, F: FnMut(&[u8])> Write for TeeWriter<W, F> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let bytes_written = self.inner.write(buf)?;
+        (self.callback)(&buf[..bytes_written]);
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
 #[cfg(feature = "encoder")]
 struct CountingWriter<W> {
     inner: W,
@@ -20,13 +20,18 @@ fn sh_left(i: i32) -> i32 {
 }
 
 impl Bt4 {
-    pub(crate) fn new(dict_size: u32, nice_len: u32, depth_limit: i32) -> Self {
+    pub(crate) fn new(
+        dict_size: u32,
+        nice_len: u32,
+        depth_limit: i32,
+        hash_bits: Option<u32>,
+    ) -> Self {
         let cyclic_size = dict_size as i32 + 1;
 
         let tree = vec![0; cyclic_size as usize * 2];
 
         Self {
-            hash: Hash234::new(dict_size),
+            hash: Hash234::new(dict_size, hash_bits),
             tree,
             depth_limit: if depth_limit > 0 {
                 depth_limit
@@ -39,8 +44,17 @@ impl Bt4 {
         }
     }
 
-    pub(crate) fn get_mem_usage(dict_size: u32) -> u32 {
-        Hash234::get_mem_usage(dict_size) + dict_size / (1024 / 8) + 10
+    /// Clears the match finder back to its freshly constructed state,
+    /// reusing the existing allocations.
+    pub(crate) fn reset(&mut self) {
+        self.hash.reset();
+        self.tree.fill(0);
+        self.cyclic_pos = -1;
+        self.lz_pos = self.cyclic_size;
+    }
+
+    pub(crate) fn get_mem_usage(dict_size: u32, hash_bits: Option<u32>) -> u32 {
+        Hash234::get_mem_usage(dict_size, hash_bits) + dict_size / (1024 / 8) + 10
     }
 
     fn move_pos(&mut self, encoder: &mut super::LZEncoderData) -> i32 {
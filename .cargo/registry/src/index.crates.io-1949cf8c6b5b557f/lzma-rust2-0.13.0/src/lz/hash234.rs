@@ -7,6 +7,13 @@ const HASH2_MASK: u32 = HASH2_SIZE - 1;
 const HASH3_SIZE: u32 = 1 << 16;
 const HASH3_MASK: u32 = HASH3_SIZE - 1;
 
+/// Smallest allowed hash4 table size override (matches the `h |= 0xFFFF`
+/// floor of the default sizing).
+pub(crate) const HASH4_BITS_MIN: u32 = 16;
+/// Largest allowed hash4 table size override (matches the `1 << 24` ceiling
+/// of the default sizing).
+pub(crate) const HASH4_BITS_MAX: u32 = 24;
+
 pub struct Hash234 {
     hash2_table: Vec<i32>,
     hash3_table: Vec<i32>,
@@ -19,7 +26,22 @@ pub struct Hash234 {
 }
 
 impl Hash234 {
-    fn get_hash4_size(dict_size: u32) -> u32 {
+    /// Clears the tables back to their freshly allocated state, reusing the
+    /// existing allocations.
+    pub(crate) fn reset(&mut self) {
+        self.hash2_table.fill(0);
+        self.hash3_table.fill(0);
+        self.hash4_table.fill(0);
+        self.hash2_value = 0;
+        self.hash3_value = 0;
+        self.hash4_value = 0;
+    }
+
+    fn get_hash4_size(dict_size: u32, hash_bits: Option<u32>) -> u32 {
+        if let Some(bits) = hash_bits {
+            return 1 << bits.clamp(HASH4_BITS_MIN, HASH4_BITS_MAX);
+        }
+
         let mut h = dict_size - 1;
         h |= h >> 1;
         h |= h >> 2;
@@ -33,12 +55,12 @@ impl Hash234 {
         h + 1
     }
 
-    pub(crate) fn get_mem_usage(dict_size: u32) -> u32 {
-        (HASH2_MASK + HASH2_SIZE + Self::get_hash4_size(dict_size)) / (1024 / 4) + 4
+    pub(crate) fn get_mem_usage(dict_size: u32, hash_bits: Option<u32>) -> u32 {
+        (HASH2_MASK + HASH2_SIZE + Self::get_hash4_size(dict_size, hash_bits)) / (1024 / 4) + 4
     }
 
-    pub(crate) fn new(dict_size: u32) -> Self {
-        let hash4_size = Self::get_hash4_size(dict_size);
+    pub(crate) fn new(dict_size: u32, hash_bits: Option<u32>) -> Self {
+        let hash4_size = Self::get_hash4_size(dict_size, hash_bits);
         let hash4_mask = hash4_size - 1;
 
         let hash2_table = vec![0; HASH2_SIZE as usize];
@@ -18,15 +18,29 @@ pub(crate) struct Hc4 {
 }
 
 impl Hc4 {
-    pub(crate) fn get_mem_usage(dict_size: u32) -> u32 {
-        Hash234::get_mem_usage(dict_size) + dict_size / (1024 / 4) + 10
+    /// Clears the match finder back to its freshly constructed state,
+    /// reusing the existing allocations.
+    pub(crate) fn reset(&mut self) {
+        self.hash.reset();
+        self.chain.fill(0);
+        self.cyclic_pos = -1;
+        self.lz_pos = self.cyclic_size;
     }
 
-    pub(crate) fn new(dict_size: u32, nice_len: u32, depth_limit: i32) -> Self {
+    pub(crate) fn get_mem_usage(dict_size: u32, hash_bits: Option<u32>) -> u32 {
+        Hash234::get_mem_usage(dict_size, hash_bits) + dict_size / (1024 / 4) + 10
+    }
+
+    pub(crate) fn new(
+        dict_size: u32,
+        nice_len: u32,
+        depth_limit: i32,
+        hash_bits: Option<u32>,
+    ) -> Self {
         let chain = vec![0; dict_size as usize + 1];
 
         Self {
-            hash: Hash234::new(dict_size),
+            hash: Hash234::new(dict_size, hash_bits),
             chain,
             depth_limit: if depth_limit > 0 {
                 depth_limit
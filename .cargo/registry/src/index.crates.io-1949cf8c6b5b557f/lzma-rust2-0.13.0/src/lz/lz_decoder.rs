@@ -1,6 +1,9 @@
 use alloc::{vec, vec::Vec};
 
-use crate::{error_other, Read};
+use crate::{error_invalid_data, Read};
+
+/// How many recovered overflow positions lenient mode records at most.
+const RECOVERED_POSITIONS_MAX: usize = 64;
 
 #[derive(Default)]
 pub(crate) struct LZDecoder {
@@ -12,11 +15,23 @@ pub(crate) struct LZDecoder {
     limit: usize,
     pending_len: usize,
     pending_dist: usize,
+    max_buf_size: usize,
+    lenient: bool,
+    recovered_errors: u64,
+    recovered_positions: Vec<u64>,
+    total_flushed: u64,
 }
 
 impl LZDecoder {
     pub(crate) fn new(dict_size: usize, preset_dict: Option<&[u8]>) -> Self {
-        let mut buf = vec![0; dict_size];
+        Self::from_buffer(vec![0; dict_size], preset_dict)
+    }
+
+    /// Creates a decoder over a caller-provided zeroed dictionary buffer,
+    /// whose length is the dictionary size.
+    pub(crate) fn from_buffer(buf: Vec<u8>, preset_dict: Option<&[u8]>) -> Self {
+        let dict_size = buf.len();
+        let mut buf = buf;
         let mut pos = 0;
         let mut full = 0;
         let mut start = 0;
@@ -30,6 +45,7 @@ impl LZDecoder {
         Self {
             buf,
             buf_size: dict_size,
+            max_buf_size: dict_size,
             pos,
             full,
             start,
@@ -37,6 +53,27 @@ impl LZDecoder {
         }
     }
 
+    /// Creates a decoder whose dictionary buffer starts small and grows
+    /// lazily towards `max_dict_size` as the stream actually uses it.
+    ///
+    /// The buffer only grows while it has never wrapped: at that point all
+    /// references are linear, so enlarging it is transparent. Once the
+    /// maximum is reached the buffer wraps cyclically as usual.
+    pub(crate) fn new_lazy(max_dict_size: usize, preset_dict: Option<&[u8]>) -> Self {
+        const INITIAL_DICT_BUF: usize = 64 * 1024;
+
+        let initial = max_dict_size.min(INITIAL_DICT_BUF.max(preset_dict.map_or(0, <[u8]>::len)));
+
+        let mut decoder = Self::new(initial, preset_dict);
+        decoder.max_buf_size = max_dict_size;
+        decoder
+    }
+
+    /// Consumes the decoder, returning its dictionary buffer for reuse.
+    pub(crate) fn into_buffer(self) -> Vec<u8> {
+        self.buf
+    }
+
     pub(crate) fn reset(&mut self) {
         self.start = 0;
         self.pos = 0;
@@ -63,7 +100,10 @@ impl LZDecoder {
 
     pub(crate) fn get_byte(&self, dist: usize) -> u8 {
         let offset = if dist >= self.pos {
-            self.buf_size + self.pos - dist - 1
+            // A corrupt distance can exceed the window; clamp it into the
+            // buffer instead of overflowing. The distance check in
+            // [`repeat`](Self::repeat) reports the corruption.
+            (self.buf_size + self.pos).saturating_sub(dist + 1) % self.buf_size
         } else {
             self.pos - dist - 1
         };
@@ -78,9 +118,58 @@ impl LZDecoder {
         }
     }
 
+    /// Switches distance overflows from hard errors to zero-filled recovery.
+    pub(crate) fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// The count of distance overflows recovered in lenient mode.
+    pub(crate) fn recovered_errors(&self) -> u64 {
+        self.recovered_errors
+    }
+
+    /// The uncompressed output offsets of the recovered overflows, capped
+    /// at the first [`RECOVERED_POSITIONS_MAX`] occurrences.
+    pub(crate) fn recovered_positions(&self) -> &[u64] {
+        &self.recovered_positions
+    }
+
+    /// Whether lenient recovery is enabled.
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
     pub(crate) fn repeat(&mut self, dist: usize, len: usize) -> crate::Result<()> {
         if dist >= self.full {
-            return Err(error_other("dist overflow"));
+            if !self.lenient {
+                // A match distance pointing outside the written dictionary
+                // means the input is corrupt.
+                return Err(error_invalid_data("dist overflow"));
+            }
+
+            // Diagnostic mode: emit zeros for the unreachable reference and
+            // keep decoding the surrounding data.
+            self.recovered_errors += 1;
+
+            if self.recovered_positions.len() < RECOVERED_POSITIONS_MAX {
+                let offset = self.total_flushed + (self.pos - self.start) as u64;
+                self.recovered_positions.push(offset);
+            }
+
+            let left = usize::min(self.limit - self.pos, len);
+            self.pending_len = len - left;
+            // The zeros are in the window now, so a distance-1 reference
+            // keeps emitting them for any pending remainder.
+            self.pending_dist = 0;
+
+            self.buf[self.pos..self.pos + left].fill(0);
+            self.pos += left;
+
+            if self.full < self.pos {
+                self.full = self.pos;
+            }
+
+            return Ok(());
         }
         let mut left = usize::min(self.limit - self.pos, len);
         self.pending_len = len - left;
@@ -157,6 +246,16 @@ impl LZDecoder {
 
     pub(crate) fn flush(&mut self, out: &mut [u8], out_off: usize) -> usize {
         let copy_size = self.pos - self.start;
+        self.total_flushed += copy_size as u64;
+
+        if self.pos == self.buf_size && self.buf_size < self.max_buf_size {
+            // Lazily grow instead of wrapping. The buffer has never wrapped
+            // here, so every reachable reference stays linear and valid.
+            let new_size = self.buf_size.saturating_mul(2).min(self.max_buf_size);
+            self.buf.resize(new_size, 0);
+            self.buf_size = new_size;
+        }
+
         if self.pos == self.buf_size {
             self.pos = 0;
         }
@@ -18,6 +18,15 @@ pub(crate) enum MatchFinders {
     Bt4(Bt4),
 }
 
+impl MatchFinders {
+    fn reset(&mut self) {
+        match self {
+            MatchFinders::Hc4(m) => m.reset(),
+            MatchFinders::Bt4(m) => m.reset(),
+        }
+    }
+}
+
 impl MatchFind for MatchFinders {
     fn find_matches(&mut self, encoder: &mut LZEncoderData, matches: &mut Matches) {
         match self {
@@ -51,10 +60,10 @@ impl Default for MfType {
 
 impl MfType {
     #[inline]
-    fn get_memory_usage(self, dict_size: u32) -> u32 {
+    fn get_memory_usage(self, dict_size: u32, hash_bits: Option<u32>) -> u32 {
         match self {
-            MfType::Hc4 => Hc4::get_mem_usage(dict_size),
-            MfType::Bt4 => Bt4::get_mem_usage(dict_size),
+            MfType::Hc4 => Hc4::get_mem_usage(dict_size, hash_bits),
+            MfType::Bt4 => Bt4::get_mem_usage(dict_size, hash_bits),
         }
     }
 }
@@ -103,13 +112,14 @@ impl LZEncoder {
         extra_size_after: u32,
         match_len_max: u32,
         mf: MfType,
+        hash_bits: Option<u32>,
     ) -> u32 {
         get_buf_size(
             dict_size,
             extra_size_before,
             extra_size_after,
             match_len_max,
-        ) + mf.get_memory_usage(dict_size)
+        ) + mf.get_memory_usage(dict_size, hash_bits)
     }
 
     pub(crate) fn new_hc4(
@@ -119,6 +129,7 @@ impl LZEncoder {
         nice_len: u32,
         match_len_max: u32,
         depth_limit: i32,
+        hash_bits: Option<u32>,
     ) -> Self {
         Self::new(
             dict_size,
@@ -126,7 +137,7 @@ impl LZEncoder {
             extra_size_after,
             nice_len,
             match_len_max,
-            MatchFinders::Hc4(Hc4::new(dict_size, nice_len, depth_limit)),
+            MatchFinders::Hc4(Hc4::new(dict_size, nice_len, depth_limit, hash_bits)),
         )
     }
 
@@ -137,6 +148,7 @@ impl LZEncoder {
         nice_len: u32,
         match_len_max: u32,
         depth_limit: i32,
+        hash_bits: Option<u32>,
     ) -> Self {
         Self::new(
             dict_size,
@@ -144,7 +156,7 @@ impl LZEncoder {
             extra_size_after,
             nice_len,
             match_len_max,
-            MatchFinders::Bt4(Bt4::new(dict_size, nice_len, depth_limit)),
+            MatchFinders::Bt4(Bt4::new(dict_size, nice_len, depth_limit, hash_bits)),
         )
     }
 
@@ -189,6 +201,19 @@ impl LZEncoder {
         }
     }
 
+    /// Clears the window and match finder back to their freshly constructed
+    /// state, reusing all allocations. The window contents need no clearing:
+    /// no valid match position references them afterwards.
+    pub(crate) fn reset(&mut self) {
+        self.data.read_pos = -1;
+        self.data.read_limit = -1;
+        self.data.finishing = false;
+        self.data.write_pos = 0;
+        self.data.pending_size = 0;
+        self.matches.count = 0;
+        self.match_finder.reset();
+    }
+
     pub(crate) fn normalize(positions: &mut [i32], norm_offset: i32) {
         #[cfg(all(feature = "std", target_arch = "x86_64"))]
         {
@@ -243,6 +268,15 @@ impl LZEncoder {
         self.data.set_flushing(&mut self.match_finder)
     }
 
+    /// Returns a copy of the trailing `max_size` bytes of input that went
+    /// through the window, for use as a preset dictionary.
+    pub(crate) fn dict_window(&self, max_size: usize) -> Vec<u8> {
+        let end = self.data.write_pos.max(0) as usize;
+        let start = end.saturating_sub(max_size);
+
+        self.data.buf[start..end].to_vec()
+    }
+
     pub(crate) fn verify_matches(&self) -> bool {
         self.data.verify_matches(&self.matches)
     }
@@ -317,7 +351,10 @@ impl LZEncoderData {
             let old_pending = self.pending_size;
             self.pending_size = 0;
             match_finder.skip(self, old_pending as _);
-            debug_assert!(self.pending_size < old_pending)
+            // Right after a flush there may be too little lookahead in the
+            // window to make progress; the bytes stay pending and are
+            // retried once more data arrives.
+            debug_assert!(self.pending_size <= old_pending)
         }
     }
 
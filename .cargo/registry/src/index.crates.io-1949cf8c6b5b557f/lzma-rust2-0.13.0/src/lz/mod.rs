@@ -85,10 +85,58 @@ fn extend_match_safe(s1: &[u8], s2: &[u8]) -> usize {
     matched
 }
 
+/// Extends a match between two slices to its maximum possible length.
+///
+/// SIMD variant comparing 16 bytes at a time via `simd128`.
+#[cfg(all(
+    feature = "optimization",
+    target_arch = "wasm32",
+    target_feature = "simd128"
+))]
+#[inline(always)]
+fn extend_match_safe(s1: &[u8], s2: &[u8]) -> usize {
+    use core::arch::wasm32::{u8x16_bitmask, u8x16_eq, v128, v128_load};
+
+    const VECTOR_SIZE: usize = 16;
+
+    let len = s1.len().min(s2.len());
+
+    // SAFETY: This is safe because all pointer accesses are bounded by
+    // `len`, which is calculated from the lengths of the input slices,
+    // ensuring no out-of-bounds reads. Wasm `v128_load` has no alignment
+    // requirement.
+    unsafe {
+        let mut matched = 0;
+
+        while matched + VECTOR_SIZE <= len {
+            let vector1 = v128_load(s1.as_ptr().add(matched) as *const v128);
+            let vector2 = v128_load(s2.as_ptr().add(matched) as *const v128);
+
+            // One bit per byte lane, set where the bytes are equal.
+            let equal_mask = u8x16_bitmask(u8x16_eq(vector1, vector2)) as u32;
+
+            if equal_mask == 0xFFFF {
+                matched += VECTOR_SIZE;
+            } else {
+                return matched + (!equal_mask).trailing_zeros() as usize;
+            }
+        }
+
+        while matched < len && *s1.get_unchecked(matched) == *s2.get_unchecked(matched) {
+            matched += 1;
+        }
+
+        matched
+    }
+}
+
 /// Extends a match between two slices to its maximum possible length.
 ///
 /// This function is optimized using native word-at-a-time comparisons.
-#[cfg(feature = "optimization")]
+#[cfg(all(
+    feature = "optimization",
+    not(all(target_arch = "wasm32", target_feature = "simd128"))
+))]
 #[inline(always)]
 fn extend_match_safe(s1: &[u8], s2: &[u8]) -> usize {
     const WORD_SIZE: usize = size_of::<usize>();
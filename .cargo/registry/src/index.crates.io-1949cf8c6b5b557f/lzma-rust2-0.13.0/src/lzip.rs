@@ -14,18 +14,18 @@ mod writer_mt;
 #[cfg(feature = "std")]
 use std::io::{Seek, SeekFrom};
 
-pub use reader::LzipReader;
+pub use reader::{lzip_decompress, LzipReader};
 #[cfg(feature = "std")]
 pub use reader_mt::LzipReaderMt;
 #[cfg(feature = "encoder")]
-pub use writer::{AutoFinishLzipWriter, LzipOptions, LzipWriter};
+pub use writer::{lzip_compress, AutoFinishLzipWriter, LzipOptions, LzipWriter};
 #[cfg(all(feature = "encoder", feature = "std"))]
 pub use writer_mt::{AutoFinishLzipWriterMt, LzipWriterMt};
 
 use crate::{error_invalid_data, error_invalid_input, ByteReader, Read, Result};
 
-const CRC32: crc::Crc<u32, crc::Table<16>> =
-    crc::Crc::<u32, crc::Table<16>>::new(&crc::CRC_32_ISO_HDLC);
+const CRC32: crc::Crc<u32, crate::CrcTable> =
+    crc::Crc::<u32, crate::CrcTable>::new(&crc::CRC_32_ISO_HDLC);
 
 const LZIP_MAGIC: [u8; 4] = [b'L', b'Z', b'I', b'P'];
 
@@ -147,7 +147,7 @@ fn encode_dict_size(dict_size: u32) -> Result<u8> {
     }
 
     if base_log2 > 29 {
-        return Err(error_invalid_input("dictionary size too large"));
+        return Err(crate::error_invalid_dict_size("dictionary size too large"));
     }
 
     let base_size = 1u32 << base_log2;
@@ -158,17 +158,10 @@ fn encode_dict_size(dict_size: u32) -> Result<u8> {
         let diff = base_size - dict_size;
         let fraction_unit = base_size >> 4; // 1/16 of base_size
 
-        if fraction_unit > 0 {
-            // Round up.
-            fraction_num = diff.div_ceil(fraction_unit);
-            if fraction_num > 7 {
-                // Need to use a larger base.
-                base_log2 += 1;
-                if base_log2 > 29 {
-                    return Err(error_invalid_input("dictionary size too large"));
-                }
-                fraction_num = 0;
-            }
+        // Round the fraction down, so the encoded size never undercuts the
+        // requested size.
+        if let Some(fraction) = diff.checked_div(fraction_unit) {
+            fraction_num = fraction.min(7);
         }
     }
 
@@ -10,11 +10,40 @@ pub struct LzipReader<R> {
     current_header: Option<LZIPHeader>,
     finished: bool,
     trailer_buf: Vec<u8>,
-    crc_digest: Option<crc::Digest<'static, u32, crc::Table<16>>>,
+    crc_digest: Option<crc::Digest<'static, u32, crate::CrcTable>>,
     data_size: u64,
+    allow_trailing_data: bool,
+    members_read: u64,
+    leftover: Vec<u8>,
 }
 
 impl<R> LzipReader<R> {
+    /// The count of members read to completion so far.
+    ///
+    /// The member CRC32, data size and member size are verified against the
+    /// trailer as each member is finished (the CRC is computed incrementally
+    /// while decompressing). When `read` reports an error, this identifies
+    /// the failing member: it is the zero-based index of the member that was
+    /// being read.
+    pub fn members_read(&self) -> u64 {
+        self.members_read
+    }
+
+    /// Consumes the LzipReader, returning the inner reader along with any
+    /// bytes that were probed past the final member while looking for
+    /// another member header. The leftover is only non-empty after the
+    /// reader reported end of stream over trailing non-LZIP data.
+    pub fn into_inner_with_leftover(mut self) -> (R, Vec<u8>) {
+        if let Some(lzma_reader) = self.lzma_reader.take() {
+            return (lzma_reader.into_inner().inner, Vec::new());
+        }
+
+        (
+            self.inner.take().expect("inner reader not set"),
+            core::mem::take(&mut self.leftover),
+        )
+    }
+
     /// Consume the LzipReader and return the inner reader.
     pub fn into_inner(mut self) -> R {
         if let Some(lzma_reader) = self.lzma_reader.take() {
@@ -51,21 +80,54 @@ impl<R: Read> LzipReader<R> {
             finished: false,
             trailer_buf: Vec::with_capacity(TRAILER_SIZE),
             crc_digest: None,
+            members_read: 0,
             data_size: 0,
+            allow_trailing_data: true,
+            leftover: Vec::new(),
         })
     }
 
+    /// Sets whether data after the final LZIP member is allowed.
+    ///
+    /// By default trailing data is ignored, mirroring how the XZ reader treats
+    /// stream padding. When set to `false`, any bytes after the final member
+    /// that do not form a valid member header cause an error instead of a
+    /// clean EOF.
+    pub fn with_allow_trailing_data(mut self, allow_trailing_data: bool) -> Self {
+        self.allow_trailing_data = allow_trailing_data;
+        self
+    }
+
     /// Start processing the next LZIP member.
     /// Returns Ok(true) if a new member was started, Ok(false) if EOF was reached.
     fn start_next_member(&mut self) -> Result<bool> {
         let mut reader = self.inner.take().expect("inner reader not set");
 
-        let header = match LZIPHeader::parse(&mut reader) {
+        // Probe a full header into a buffer first. A clean EOF at the member
+        // boundary can then be told apart from trailing garbage, and
+        // whatever was probed but not consumed stays available through
+        // [`into_inner_with_leftover`](Self::into_inner_with_leftover).
+        let mut probe = [0u8; HEADER_SIZE];
+        let mut filled = 0;
+
+        while filled < probe.len() {
+            match reader.read(&mut probe[filled..])? {
+                0 => break,
+                read => filled += read,
+            }
+        }
+
+        let header = match LZIPHeader::parse(&mut &probe[..filled]) {
             Ok(header) => header,
             Err(_) => {
-                // If header parsing fails, we've probably reached EOF:
-                // Put the reader back and indicate we're done:
                 self.inner = Some(reader);
+
+                if filled > 0 && !self.allow_trailing_data {
+                    return Err(error_invalid_data("trailing data after last LZIP member"));
+                }
+
+                self.leftover = probe[..filled].to_vec();
+
                 return Ok(false);
             }
         };
@@ -106,7 +168,7 @@ impl<R: Read> LzipReader<R> {
 
         if computed_crc != trailer.crc32 {
             self.inner = Some(inner_reader);
-            return Err(error_invalid_data("LZIP CRC32 mismatch"));
+            return Err(crate::error_checksum_mismatch("LZIP CRC32 mismatch"));
         }
 
         if self.data_size != trailer.data_size {
@@ -122,6 +184,7 @@ impl<R: Read> LzipReader<R> {
 
         // Store the reader for potential next member.
         self.inner = Some(inner_reader);
+        self.members_read += 1;
 
         Ok(())
     }
@@ -176,3 +239,12 @@ impl<R: Read> Read for LzipReader<R> {
         }
     }
 }
+
+/// Decompresses a complete LZIP stream in one call.
+///
+/// `max_output_size` caps the decompressed size to guard against
+/// decompression bombs. Concatenated members are accepted.
+pub fn lzip_decompress(data: &[u8], max_output_size: usize) -> Result<Vec<u8>> {
+    let mut reader = LzipReader::new(data)?;
+    crate::read_to_end_capped(&mut reader, max_output_size)
+}
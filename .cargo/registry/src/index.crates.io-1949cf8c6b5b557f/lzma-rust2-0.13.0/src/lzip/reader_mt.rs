@@ -22,6 +22,10 @@ struct WorkUnit {
 }
 
 /// A multi-threaded LZIP decompressor.
+///
+/// Dropping the reader before the end of the stream is cheap: the work
+/// queue is closed and the worker threads shut down promptly without
+/// decoding the remaining members.
 pub struct LzipReaderMt<R: Read + Seek> {
     inner: R,
     members: Vec<LZIPMember>,
@@ -33,8 +37,28 @@ impl<R: Read + Seek> LzipReaderMt<R> {
     /// Creates a new multi-threaded LZIP reader.
     ///
     /// - `inner`: The reader to read compressed data from. Must implement Seek.
-    /// - `num_workers`: The maximum number of worker threads for decompression. Currently capped at 256 threads.
+    /// - `num_workers`: The maximum number of worker threads for decompression. Pass `0` to
+    ///   use all available cores. Currently capped at 256 threads.
     pub fn new(inner: R, num_workers: u32) -> io::Result<Self> {
+        Self::construct(inner, num_workers, None)
+    }
+
+    /// Like [`new`](Self::new), but decompresses on threads borrowed from
+    /// `shared_pool` instead of spawning its own. See
+    /// [`SharedWorkerPool`](crate::SharedWorkerPool) for pool sizing.
+    pub fn new_with_pool(
+        inner: R,
+        num_workers: u32,
+        shared_pool: std::sync::Arc<crate::SharedWorkerPool>,
+    ) -> io::Result<Self> {
+        Self::construct(inner, num_workers, Some(shared_pool))
+    }
+
+    fn construct(
+        inner: R,
+        num_workers: u32,
+        shared_pool: Option<std::sync::Arc<crate::SharedWorkerPool>>,
+    ) -> io::Result<Self> {
         let (inner, members) = scan_members(inner)?;
         let num_members = members.len() as u64;
 
@@ -42,7 +66,11 @@ impl<R: Read + Seek> LzipReaderMt<R> {
             inner,
             members,
             work_pool: WorkPool::new(
-                WorkPoolConfig::new(num_workers, num_members),
+                {
+                    let mut config = WorkPoolConfig::new(num_workers, num_members);
+                    config.shared_pool = shared_pool;
+                    config
+                },
                 worker_thread_logic,
             ),
             current_chunk: Cursor::new(Vec::new()),
@@ -65,7 +93,7 @@ impl<R: Read + Seek> LzipReaderMt<R> {
             self.inner.seek(SeekFrom::Start(member.start_pos)).unwrap();
             let mut member_data = vec![0u8; member.compressed_size as usize];
             self.inner.read_exact(&mut member_data).unwrap();
-            Ok(WorkUnit { member_data })
+            Ok(Some(WorkUnit { member_data }))
         })
     }
 }
@@ -1,8 +1,8 @@
 use core::num::NonZeroU64;
 
 use super::{
-    encode_dict_size, CRC32, HEADER_SIZE, LZIP_MAGIC, LZIP_VERSION, MAX_DICT_SIZE, MIN_DICT_SIZE,
-    TRAILER_SIZE,
+    decode_dict_size, encode_dict_size, CRC32, HEADER_SIZE, LZIP_MAGIC, LZIP_VERSION,
+    MAX_DICT_SIZE, MIN_DICT_SIZE, TRAILER_SIZE,
 };
 use crate::{
     enc::{LzmaOptions, LzmaWriter},
@@ -17,6 +17,8 @@ pub struct LzipOptions {
     /// The maximal size of a member. If not set, the whole data will be written in one member.
     /// Will get clamped to be at least the dict size to not waste memory.
     pub member_size: Option<NonZeroU64>,
+    /// Memory budget in KiB for the multithreaded writer's workers.
+    pub max_total_memory: Option<u64>,
 }
 
 impl LzipOptions {
@@ -25,6 +27,7 @@ impl LzipOptions {
         Self {
             lzma_options: LzmaOptions::with_preset(preset),
             member_size: None,
+            max_total_memory: None,
         }
     }
 
@@ -32,6 +35,28 @@ impl LzipOptions {
     pub fn set_member_size(&mut self, member_size: Option<NonZeroU64>) {
         self.member_size = member_size;
     }
+
+    /// Caps the total memory of [`LzipWriterMt`](crate::LzipWriterMt)'s
+    /// workers, in KiB. See
+    /// [`Lzma2Options::set_max_total_memory`](crate::Lzma2Options::set_max_total_memory).
+    pub fn set_max_total_memory(&mut self, max_total_memory: Option<u64>) {
+        self.max_total_memory = max_total_memory;
+    }
+
+    /// Returns the nearest lzip-legal dictionary size for `requested`.
+    ///
+    /// Lzip only supports dictionary sizes of the form `2^n - k * 2^(n-4)`
+    /// with `n` in `[12, 29]` and `k` in `[0, 7]`, i.e. between 4 KiB and
+    /// 512 MiB. The requested size is clamped to that range and rounded up to
+    /// the next representable value, so the returned size is always at least
+    /// as large as the clamped request.
+    pub fn nearest_legal_dict_size(requested: u32) -> u32 {
+        let clamped = requested.clamp(MIN_DICT_SIZE, MAX_DICT_SIZE);
+
+        // encode/decode round-trips to the smallest legal size >= clamped.
+        let encoded = encode_dict_size(clamped).expect("clamped size is encodable");
+        decode_dict_size(encoded).expect("encoded size is decodable")
+    }
 }
 
 /// A single-threaded LZIP compressor.
@@ -41,7 +66,7 @@ pub struct LzipWriter<W: Write> {
     options: LzipOptions,
     header_written: bool,
     finished: bool,
-    crc_digest: crc::Digest<'static, u32, crc::Table<16>>,
+    crc_digest: crc::Digest<'static, u32, crate::CrcTable>,
     uncompressed_size: u64,
     member_start_pos: u64,
     current_member_uncompressed_size: u64,
@@ -56,10 +81,8 @@ impl<W: Write> LzipWriter<W> {
         options.lzma_options.lc = 3;
         options.lzma_options.lp = 0;
         options.lzma_options.pb = 2;
-        options.lzma_options.dict_size = options
-            .lzma_options
-            .dict_size
-            .clamp(MIN_DICT_SIZE, MAX_DICT_SIZE);
+        options.lzma_options.dict_size =
+            LzipOptions::nearest_legal_dict_size(options.lzma_options.dict_size);
 
         if let Some(member_size) = options.member_size.as_mut() {
             *member_size =
@@ -260,6 +283,15 @@ impl<W: Write> Write for LzipWriter<W> {
 /// This can be created by the [`LzipWriter::auto_finish`] method.
 pub struct AutoFinishLzipWriter<W: Write>(Option<LzipWriter<W>>);
 
+impl<W: Write> AutoFinishLzipWriter<W> {
+    /// Finishes the stream explicitly, returning the inner writer and
+    /// making finish errors observable. The drop-based finish, which
+    /// swallows errors, only runs when this was not called.
+    pub fn finish(mut self) -> Result<W> {
+        self.0.take().expect("writer not set").finish()
+    }
+}
+
 impl<W: Write> Drop for AutoFinishLzipWriter<W> {
     fn drop(&mut self) {
         if let Some(writer) = self.0.take() {
@@ -277,3 +309,16 @@ impl<W: Write> Write for AutoFinishLzipWriter<W> {
         self.0.as_mut().unwrap().flush()
     }
 }
+
+/// Compresses `data` into a complete LZIP stream in one call.
+pub fn lzip_compress(data: &[u8], options: &LzipOptions) -> crate::Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = LzipWriter::new(&mut compressed, options.clone());
+        writer.write_all(data)?;
+        writer.finish()?;
+    }
+
+    Ok(compressed)
+}
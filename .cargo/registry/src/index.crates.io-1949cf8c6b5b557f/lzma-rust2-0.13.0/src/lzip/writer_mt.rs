@@ -38,9 +38,30 @@ impl<W: Write> LzipWriterMt<W> {
     /// - `inner`: The writer to write compressed data to.
     /// - `options`: The LZIP options used for compressing. Member size must be set when using the
     ///   multi-threaded encoder. If you need just one member, then use the single-threaded encoder.
-    /// - `num_workers`: The maximum number of worker threads for compression.
-    ///   Currently capped at 256 threads.
+    /// - `num_workers`: The maximum number of worker threads for compression. Pass `0` to
+    ///   use all available cores. Currently capped at 256 threads.
     pub fn new(inner: W, options: LzipOptions, num_workers: u32) -> io::Result<Self> {
+        Self::construct(inner, options, num_workers, None)
+    }
+
+    /// Like [`new`](Self::new), but borrows its worker threads from
+    /// `shared_pool` instead of spawning them per instance. See
+    /// [`SharedWorkerPool`](crate::SharedWorkerPool) for pool sizing.
+    pub fn new_with_pool(
+        inner: W,
+        options: LzipOptions,
+        num_workers: u32,
+        shared_pool: std::sync::Arc<crate::SharedWorkerPool>,
+    ) -> io::Result<Self> {
+        Self::construct(inner, options, num_workers, Some(shared_pool))
+    }
+
+    fn construct(
+        inner: W,
+        options: LzipOptions,
+        num_workers: u32,
+        shared_pool: Option<std::sync::Arc<crate::SharedWorkerPool>>,
+    ) -> io::Result<Self> {
         let member_size = match options.member_size {
             None => return Err(error_invalid_input("member size must be set")),
             Some(member_size) => member_size.get().max(options.lzma_options.dict_size as u64),
@@ -49,6 +70,16 @@ impl<W: Write> LzipWriterMt<W> {
         let member_size = usize::try_from(member_size)
             .map_err(|_| error_invalid_input("member size bigger than usize"))?;
 
+        // Apply the memory budget by capping the worker count.
+        let num_workers = match options.max_total_memory {
+            Some(budget_kb) => crate::enc::workers_for_memory_budget(
+                &options.lzma_options,
+                crate::resolve_workers(num_workers),
+                budget_kb,
+            ),
+            None => num_workers,
+        };
+
         // We don't know how many work units we'll have ahead of time.
         let num_work = u64::MAX;
 
@@ -58,7 +89,11 @@ impl<W: Write> LzipWriterMt<W> {
             current_work_unit: Vec::with_capacity(member_size.min(1024 * 1024)),
             member_size,
             work_pool: WorkPool::new(
-                WorkPoolConfig::new(num_workers, num_work),
+                {
+                    let mut config = WorkPoolConfig::new(num_workers, num_work);
+                    config.shared_pool = shared_pool;
+                    config
+                },
                 worker_thread_logic,
             ),
             current_chunk: Cursor::new(Vec::new()),
@@ -84,10 +119,10 @@ impl<W: Write> LzipWriterMt<W> {
             let data = work_data_opt.take().ok_or_else(|| {
                 io::Error::new(io::ErrorKind::InvalidInput, "work already provided")
             })?;
-            Ok(WorkUnit {
+            Ok(Some(WorkUnit {
                 data,
                 options: single_member_options.clone(),
-            })
+            }))
         })?;
 
         self.drain_available_results()?;
@@ -136,12 +171,7 @@ impl<W: Write> LzipWriterMt<W> {
         self.work_pool.finish();
 
         // Wait for all remaining work to complete.
-        while let Some(compressed_data) = self.work_pool.get_result(|_| {
-            Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "no more work to dispatch",
-            ))
-        })? {
+        while let Some(compressed_data) = self.work_pool.get_result(|_| Ok(None))? {
             self.inner.write_all(&compressed_data)?;
         }
 
@@ -250,6 +280,15 @@ impl<W: Write> Write for LzipWriterMt<W> {
 /// This can be created by the [`LzipWriterMt::auto_finish`] method.
 pub struct AutoFinishLzipWriterMt<W: Write>(Option<LzipWriterMt<W>>);
 
+impl<W: Write> AutoFinishLzipWriterMt<W> {
+    /// Finishes the stream explicitly, returning the inner writer and
+    /// making finish errors observable. The drop-based finish, which
+    /// swallows errors, only runs when this was not called.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.0.take().expect("writer not set").finish()
+    }
+}
+
 impl<W: Write> Drop for AutoFinishLzipWriterMt<W> {
     fn drop(&mut self) {
         if let Some(writer) = self.0.take() {
@@ -0,0 +1,156 @@
+use alloc::vec::Vec;
+
+use crate::{error_invalid_data, is_error_eof, Read, Result};
+
+/// A single LZMA2 chunk as found in the stream, without decompressing it.
+#[derive(Debug, Clone)]
+pub struct Lzma2Chunk {
+    /// The raw LZMA2 control byte of this chunk.
+    pub control: u8,
+    /// The chunk payload as stored in the stream: range-coded data for
+    /// compressed chunks, the raw bytes for uncompressed chunks.
+    pub compressed: Vec<u8>,
+    /// The size of the chunk after decompression in bytes.
+    pub uncompressed_size: usize,
+    /// Whether this chunk starts with a dictionary reset and can therefore be
+    /// decompressed independently of all preceding chunks.
+    pub is_independent: bool,
+    /// The LZMA properties byte, present when the chunk carries new properties
+    /// (control byte `>= 0xC0`).
+    pub props: Option<u8>,
+}
+
+impl Lzma2Chunk {
+    /// Whether the chunk payload is stored uncompressed.
+    pub fn is_uncompressed(&self) -> bool {
+        self.control == 0x01 || self.control == 0x02
+    }
+}
+
+/// A low-level reader that yields the individual chunks of an LZMA2 stream
+/// without decompressing them.
+///
+/// Useful for diagnostics and repacking: since every chunk reports whether it
+/// is independent (starts with a dictionary reset), an LZMA2 stream can be
+/// split at independent-chunk boundaries for custom parallel pipelines. The
+/// multithreaded [`Lzma2ReaderMt`](crate::Lzma2ReaderMt) uses the same
+/// boundaries to distribute work.
+///
+/// # Examples
+/// ```
+/// use lzma_rust2::Lzma2ChunkReader;
+///
+/// let compressed: Vec<u8> = vec![
+///     1, 0, 12, 72, 101, 108, 108, 111, 44, 32, 119, 111, 114, 108, 100, 33, 0,
+/// ];
+/// let mut reader = Lzma2ChunkReader::new(compressed.as_slice());
+/// let chunk = reader.next_chunk().unwrap().unwrap();
+/// assert_eq!(chunk.uncompressed_size, 13);
+/// assert!(chunk.is_independent);
+/// assert!(reader.next_chunk().unwrap().is_none());
+/// ```
+pub struct Lzma2ChunkReader<R> {
+    inner: R,
+    finished: bool,
+}
+
+impl<R: Read> Lzma2ChunkReader<R> {
+    /// Creates a new [`Lzma2ChunkReader`].
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            finished: false,
+        }
+    }
+
+    /// Reads the next chunk from the stream.
+    ///
+    /// Returns `Ok(None)` once the end-of-stream marker or a clean EOF is
+    /// reached. Returns an error on an invalid control byte or truncated
+    /// chunk.
+    pub fn next_chunk(&mut self) -> Result<Option<Lzma2Chunk>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let mut control_buf = [0u8; 1];
+        match self.inner.read_exact(&mut control_buf) {
+            Ok(()) => (),
+            Err(error) if is_error_eof(&error) => {
+                // Clean end of stream without an end marker.
+                self.finished = true;
+                return Ok(None);
+            }
+            Err(error) => {
+                self.finished = true;
+                return Err(error);
+            }
+        }
+
+        let control = control_buf[0];
+
+        if control == 0x00 {
+            // End of stream marker.
+            self.finished = true;
+            return Ok(None);
+        }
+
+        let (uncompressed_size, compressed_size, props) = if control >= 0x80 {
+            // Compressed chunk.
+            let header_len = if control >= 0xC0 { 5 } else { 4 };
+            let mut header_buf = [0; 5];
+            self.inner.read_exact(&mut header_buf[..header_len])?;
+
+            let uncompressed_size = (((control & 0x1F) as usize) << 16)
+                | u16::from_be_bytes([header_buf[0], header_buf[1]]) as usize;
+            let compressed_size = u16::from_be_bytes([header_buf[2], header_buf[3]]) as usize + 1;
+            let props = (header_len == 5).then(|| header_buf[4]);
+
+            (uncompressed_size + 1, compressed_size, props)
+        } else if control == 0x01 || control == 0x02 {
+            // Uncompressed chunk.
+            let mut size_buf = [0u8; 2];
+            self.inner.read_exact(&mut size_buf)?;
+            let size = u16::from_be_bytes(size_buf) as usize + 1;
+
+            (size, size, None)
+        } else {
+            self.finished = true;
+            return Err(error_invalid_data("invalid LZMA2 control byte"));
+        };
+
+        let mut compressed = alloc::vec![0u8; compressed_size];
+        self.inner.read_exact(&mut compressed)?;
+
+        Ok(Some(Lzma2Chunk {
+            control,
+            compressed,
+            uncompressed_size,
+            is_independent: control >= 0xE0 || control == 0x01,
+            props,
+        }))
+    }
+
+    /// Consume the Lzma2ChunkReader and return the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns a reference to the inner reader.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner reader.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+impl<R: Read> Iterator for Lzma2ChunkReader<R> {
+    type Item = Result<Lzma2Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk().transpose()
+    }
+}
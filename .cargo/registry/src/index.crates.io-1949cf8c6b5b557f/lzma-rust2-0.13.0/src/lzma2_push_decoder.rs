@@ -0,0 +1,186 @@
+use alloc::{collections::VecDeque, vec::Vec};
+
+use crate::{error_invalid_data, Lzma2Reader, Read, Result};
+
+/// Owned input queue the internal [`Lzma2Reader`] pulls from. Only ever holds
+/// complete chunks, so the reader never observes a mid-chunk EOF.
+#[derive(Default)]
+struct ChunkQueue {
+    data: VecDeque<u8>,
+}
+
+impl Read for ChunkQueue {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let (front, _) = self.data.as_slices();
+        let bytes_read = front.len().min(buf.len());
+        buf[..bytes_read].copy_from_slice(&front[..bytes_read]);
+        self.data.drain(..bytes_read);
+        Ok(bytes_read)
+    }
+}
+
+/// A sans-io push decoder for raw LZMA2 streams.
+///
+/// Unlike [`Lzma2Reader`], this decoder does not own an input `Read`: the
+/// caller pushes compressed bytes in whatever sizes its event loop produces
+/// and pulls decompressed bytes back out. Input is buffered internally until
+/// a complete LZMA2 chunk is available, which is then decoded eagerly.
+///
+/// ```
+/// use lzma_rust2::{Lzma2PushDecoder, LzmaOptions};
+///
+/// let compressed: Vec<u8> = vec![
+///     1, 0, 12, 72, 101, 108, 108, 111, 44, 32, 119, 111, 114, 108, 100, 33, 0,
+/// ];
+///
+/// let mut decoder = Lzma2PushDecoder::new(LzmaOptions::DICT_SIZE_DEFAULT, None);
+/// let mut output = Vec::new();
+///
+/// for byte in compressed {
+///     decoder.push(&[byte]).unwrap();
+///     let mut buf = [0u8; 64];
+///     loop {
+///         let produced = decoder.pull(&mut buf).unwrap();
+///         if produced == 0 {
+///             break;
+///         }
+///         output.extend_from_slice(&buf[..produced]);
+///     }
+/// }
+///
+/// assert!(decoder.is_finished());
+/// assert_eq!(&output[..], b"Hello, world!");
+/// ```
+pub struct Lzma2PushDecoder {
+    staging: Vec<u8>,
+    reader: Lzma2Reader<ChunkQueue>,
+    output: VecDeque<u8>,
+    finished: bool,
+}
+
+impl Lzma2PushDecoder {
+    /// Creates a new push decoder.
+    ///
+    /// - `dict_size`: The dictionary size in bytes, as specified in the stream properties.
+    /// - `preset_dict`: An optional preset dictionary.
+    pub fn new(dict_size: u32, preset_dict: Option<&[u8]>) -> Self {
+        Self {
+            staging: Vec::new(),
+            reader: Lzma2Reader::new(ChunkQueue::default(), dict_size, preset_dict),
+            output: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Pushes compressed input into the decoder.
+    ///
+    /// Always consumes the whole input (buffering incomplete chunks
+    /// internally) and returns the number of bytes consumed. Complete chunks
+    /// are decoded immediately and their output becomes available via
+    /// [`pull`](Self::pull). Input past the end-of-stream marker is rejected.
+    pub fn push(&mut self, input: &[u8]) -> Result<usize> {
+        if self.finished && !input.is_empty() {
+            return Err(error_invalid_data("input past the LZMA2 end marker"));
+        }
+
+        self.staging.extend_from_slice(input);
+
+        while let Some((chunk_len, uncompressed_size)) = self.peek_complete_chunk()? {
+            let chunk: Vec<u8> = self.staging.drain(..chunk_len).collect();
+            self.reader.inner_mut().data.extend(chunk);
+
+            if uncompressed_size == 0 {
+                // End of stream marker.
+                self.finished = true;
+
+                if !self.staging.is_empty() {
+                    return Err(error_invalid_data("input past the LZMA2 end marker"));
+                }
+
+                break;
+            }
+
+            let start = self.output.len();
+            self.output.resize(start + uncompressed_size, 0);
+            let output = self.output.make_contiguous();
+            self.reader.read_exact(&mut output[start..])?;
+        }
+
+        Ok(input.len())
+    }
+
+    /// Pulls decompressed output from the decoder.
+    ///
+    /// Returns the number of bytes produced. `0` means more input is needed
+    /// (or, when [`is_finished`](Self::is_finished) reports `true`, that the
+    /// stream is complete).
+    pub fn pull(&mut self, out: &mut [u8]) -> Result<usize> {
+        let (front, _) = self.output.as_slices();
+        let bytes_read = front.len().min(out.len());
+        out[..bytes_read].copy_from_slice(&front[..bytes_read]);
+        self.output.drain(..bytes_read);
+        Ok(bytes_read)
+    }
+
+    /// Whether the end-of-stream marker has been decoded.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Whether the decoder needs more input before [`pull`](Self::pull) can
+    /// produce further output.
+    pub fn needs_input(&self) -> bool {
+        !self.finished && self.output.is_empty()
+    }
+
+    /// Parses the staged input for one complete chunk. Returns the total
+    /// chunk length in the staging buffer and its uncompressed size (zero for
+    /// the end-of-stream marker).
+    fn peek_complete_chunk(&self) -> Result<Option<(usize, usize)>> {
+        let staging = &self.staging;
+
+        let Some(&control) = staging.first() else {
+            return Ok(None);
+        };
+
+        if control == 0x00 {
+            return Ok(Some((1, 0)));
+        }
+
+        if control >= 0x80 {
+            // Compressed chunk.
+            let header_len = if control >= 0xC0 { 6 } else { 5 };
+
+            if staging.len() < header_len {
+                return Ok(None);
+            }
+
+            let uncompressed_size = (((control & 0x1F) as usize) << 16)
+                | (u16::from_be_bytes([staging[1], staging[2]]) as usize);
+            let compressed_size = u16::from_be_bytes([staging[3], staging[4]]) as usize + 1;
+            let chunk_len = header_len + compressed_size;
+
+            if staging.len() < chunk_len {
+                return Ok(None);
+            }
+
+            Ok(Some((chunk_len, uncompressed_size + 1)))
+        } else if control == 0x01 || control == 0x02 {
+            // Uncompressed chunk.
+            if staging.len() < 3 {
+                return Ok(None);
+            }
+
+            let size = u16::from_be_bytes([staging[1], staging[2]]) as usize + 1;
+            let chunk_len = 3 + size;
+
+            if staging.len() < chunk_len {
+                return Ok(None);
+            }
+
+            Ok(Some((chunk_len, size)))
+        } else {
+            Err(error_invalid_data("invalid LZMA2 control byte"))
+        }
+    }
+}
@@ -9,6 +9,20 @@ use crate::ByteReader;
 
 pub const COMPRESSED_SIZE_MAX: u32 = 1 << 16;
 
+/// Decompresses a raw LZMA2 stream in one call.
+///
+/// `dict_size` is the dictionary size the stream was compressed with and
+/// `max_output_size` caps the decompressed size to guard against
+/// decompression bombs.
+pub fn lzma2_decompress(
+    data: &[u8],
+    dict_size: u32,
+    max_output_size: usize,
+) -> crate::Result<alloc::vec::Vec<u8>> {
+    let mut reader = Lzma2Reader::new(data, dict_size, None);
+    crate::read_to_end_capped(&mut reader, max_output_size)
+}
+
 /// A single-threaded LZMA2 decompressor.
 ///
 /// # Examples
@@ -66,13 +80,110 @@ impl<R> Lzma2Reader<R> {
 }
 
 impl<R: Read> Lzma2Reader<R> {
+    /// Creates a new LZMA2 reader in lenient diagnostic mode.
+    ///
+    /// Corrupt streams sometimes reference match distances beyond the
+    /// declared dictionary. Instead of aborting with a "dist overflow"
+    /// error, this mode emits zero bytes for such references and keeps
+    /// decoding, so the surrounding data can be recovered for analysis.
+    /// [`recovered_errors`](Self::recovered_errors) reports how many
+    /// references were patched; any nonzero count means the output is not
+    /// faithful. Use [`new`](Self::new) for strict decoding.
+    pub fn new_lenient(inner: R, dict_size: u32, preset_dict: Option<&[u8]>) -> Self {
+        let mut reader = Self::new(inner, dict_size, preset_dict);
+        reader.lz.set_lenient(true);
+        reader
+    }
+
+    /// The uncompressed output offsets where lenient mode recovered a
+    /// distance overflow, identifying the corrupt references in the output.
+    /// At most the first 64 positions are recorded.
+    pub fn recovered_positions(&self) -> &[u64] {
+        self.lz.recovered_positions()
+    }
+
+    /// The count of distance overflows recovered in lenient mode.
+    pub fn recovered_errors(&self) -> u64 {
+        self.lz.recovered_errors()
+    }
+
+    /// Decodes just enough of the stream to fill `out` and stops early,
+    /// without decoding the rest of the stream. Returns the number of bytes
+    /// written, which is only smaller than `out.len()` when the stream ends
+    /// first. Input is consumed at the decoder's chunk granularity: only the
+    /// compressed chunks covering the prefix are read from the inner reader,
+    /// making this suitable for previewing large archives.
+    pub fn decode_prefix(&mut self, out: &mut [u8]) -> crate::Result<usize> {
+        let mut filled = 0;
+
+        while filled < out.len() {
+            match self.read(&mut out[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+
+        Ok(filled)
+    }
+
+    /// Creates a reader for a raw LZMA2 stream whose dictionary size is
+    /// unknown, tolerating distances up to `max_dict_size` (or
+    /// [`DICT_SIZE_MAX`](crate::DICT_SIZE_MAX) when `None`).
+    ///
+    /// The dictionary buffer starts small and grows lazily as the stream's
+    /// distances actually require, so a large cap does not allocate
+    /// gigabytes up front; memory use ends up proportional to what the
+    /// stream touches.
+    pub fn new_auto_dict(inner: R, max_dict_size: Option<u32>, preset_dict: Option<&[u8]>) -> Self {
+        let max_dict_size = max_dict_size.unwrap_or(crate::DICT_SIZE_MAX);
+        let has_preset = preset_dict.as_ref().map(|a| !a.is_empty()).unwrap_or(false);
+        let lz = LZDecoder::new_lazy(get_dict_size(max_dict_size) as _, preset_dict);
+        let rc = RangeDecoder::new_buffer(COMPRESSED_SIZE_MAX as _);
+        Self {
+            inner,
+            lz,
+            rc,
+            lzma: None,
+            uncompressed_size: 0,
+            is_lzma_chunk: false,
+            need_dict_reset: !has_preset,
+            need_props: true,
+            end_reached: false,
+        }
+    }
+
     /// Create a new LZMA2 reader.
     /// `inner` is the reader to read compressed data from.
     /// `dict_size` is the dictionary size in bytes.
     pub fn new(inner: R, dict_size: u32, preset_dict: Option<&[u8]>) -> Self {
+        Self::new_with_provider(
+            inner,
+            dict_size,
+            preset_dict,
+            &mut crate::GlobalBufferProvider,
+        )
+    }
+
+    /// Like [`new`](Self::new), but the dictionary and range decoder
+    /// scratch buffers come from `provider` instead of fresh allocations.
+    ///
+    /// The provider is asked for two zeroed buffers: one of the dictionary
+    /// size and one of the fixed range decoder size. Both can be reclaimed
+    /// with [`into_buffers`](Self::into_buffers) when decoding is done, so
+    /// a pool can recycle them across readers.
+    pub fn new_with_provider(
+        inner: R,
+        dict_size: u32,
+        preset_dict: Option<&[u8]>,
+        provider: &mut dyn crate::BufferProvider,
+    ) -> Self {
         let has_preset = preset_dict.as_ref().map(|a| !a.is_empty()).unwrap_or(false);
-        let lz = LZDecoder::new(get_dict_size(dict_size) as _, preset_dict);
-        let rc = RangeDecoder::new_buffer(COMPRESSED_SIZE_MAX as _);
+        let dict_buf = provider.take_buffer(get_dict_size(dict_size) as usize);
+        let rc_buf = provider.take_buffer(COMPRESSED_SIZE_MAX as usize - 5);
+        debug_assert_eq!(dict_buf.len(), get_dict_size(dict_size) as usize);
+        debug_assert_eq!(rc_buf.len(), COMPRESSED_SIZE_MAX as usize - 5);
+        let lz = LZDecoder::from_buffer(dict_buf, preset_dict);
+        let rc = RangeDecoder::from_provided_buffer(rc_buf);
         Self {
             inner,
             lz,
@@ -86,6 +197,13 @@ impl<R: Read> Lzma2Reader<R> {
         }
     }
 
+    /// Consumes the reader, returning the dictionary and range decoder
+    /// buffers for reuse through a [`BufferProvider`](crate::BufferProvider)
+    /// pool.
+    pub fn into_buffers(self) -> (alloc::vec::Vec<u8>, alloc::vec::Vec<u8>) {
+        (self.lz.into_buffer(), self.rc.into_buffer())
+    }
+
     // ### LZMA2 Control Byte Meaning
     //
     //  Control Byte    | Chunk Type      | Formal Action
@@ -144,7 +262,7 @@ impl<R: Read> Lzma2Reader<R> {
             return Err(error_invalid_input("corrupted input data (LZMA2:2)"));
         } else {
             self.is_lzma_chunk = false;
-            self.uncompressed_size = (self.inner.read_u16_be()? + 1) as _;
+            self.uncompressed_size = self.inner.read_u16_be()? as usize + 1;
         }
         Ok(())
     }
@@ -206,7 +324,12 @@ impl<R: Read> Read for Lzma2Reader<R> {
                 self.uncompressed_size -= copied_size;
                 if self.uncompressed_size == 0 && (!self.rc.is_finished() || self.lz.has_pending())
                 {
-                    return Err(error_invalid_input("rc not finished or lz has pending"));
+                    // In lenient mode a recovered overflow desynchronizes the
+                    // adaptive literal contexts, so this consistency check
+                    // cannot hold; keep going with the next chunk instead.
+                    if !self.lz.is_lenient() {
+                        return Err(error_invalid_input("rc not finished or lz has pending"));
+                    }
                 }
             }
         }
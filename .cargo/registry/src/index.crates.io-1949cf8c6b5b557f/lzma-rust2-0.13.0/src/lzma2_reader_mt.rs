@@ -11,9 +11,9 @@ use std::{
 };
 
 use crate::{
-    set_error,
+    error_invalid_data, set_error,
     work_queue::{WorkStealingQueue, WorkerHandle},
-    Lzma2Reader,
+    Lzma2Reader, SharedWorkerPool,
 };
 
 /// A work unit for a worker thread.
@@ -37,6 +37,10 @@ enum State {
 }
 
 /// A multi-threaded LZMA2 decompressor.
+///
+/// Dropping the reader before the end of the stream is cheap: the work
+/// queue is closed and the worker threads shut down promptly without
+/// decoding the remaining chunks.
 pub struct Lzma2ReaderMt<R: Read> {
     inner: R,
     result_rx: Receiver<ResultUnit>,
@@ -56,6 +60,9 @@ pub struct Lzma2ReaderMt<R: Read> {
     dict_size: u32,
     preset_dict: Option<Arc<Vec<u8>>>,
     worker_handles: Vec<thread::JoinHandle<()>>,
+    shared_pool: Option<Arc<SharedWorkerPool>>,
+    spawned_workers: u32,
+    max_buffered_results: Option<usize>,
 }
 
 impl<R: Read> Lzma2ReaderMt<R> {
@@ -64,9 +71,39 @@ impl<R: Read> Lzma2ReaderMt<R> {
     /// - `inner`: The reader to read compressed data from.
     /// - `dict_size`: The dictionary size in bytes, as specified in the stream properties.
     /// - `preset_dict`: An optional preset dictionary.
-    /// - `num_workers`: The maximum number of worker threads for decompression. Currently capped at 256 Threads.
+    /// - `num_workers`: The maximum number of worker threads for decompression. Pass `0` to
+    ///   use all available cores. Currently capped at 256 Threads.
     pub fn new(inner: R, dict_size: u32, preset_dict: Option<&[u8]>, num_workers: u32) -> Self {
-        let max_workers = num_workers.clamp(1, 256);
+        Self::construct(inner, dict_size, preset_dict, num_workers, None)
+    }
+
+    /// Like [`new`](Self::new), but decompresses on threads borrowed from
+    /// `shared_pool` instead of spawning its own. See
+    /// [`SharedWorkerPool`] for pool sizing.
+    pub fn new_with_pool(
+        inner: R,
+        dict_size: u32,
+        preset_dict: Option<&[u8]>,
+        num_workers: u32,
+        shared_pool: Arc<SharedWorkerPool>,
+    ) -> Self {
+        Self::construct(
+            inner,
+            dict_size,
+            preset_dict,
+            num_workers,
+            Some(shared_pool),
+        )
+    }
+
+    fn construct(
+        inner: R,
+        dict_size: u32,
+        preset_dict: Option<&[u8]>,
+        num_workers: u32,
+        shared_pool: Option<Arc<SharedWorkerPool>>,
+    ) -> Self {
+        let max_workers = crate::resolve_workers(num_workers);
 
         let work_queue = WorkStealingQueue::new();
         let (result_tx, result_rx) = mpsc::sync_channel::<ResultUnit>(1);
@@ -94,6 +131,9 @@ impl<R: Read> Lzma2ReaderMt<R> {
             dict_size,
             preset_dict,
             worker_handles: Vec::new(),
+            shared_pool,
+            spawned_workers: 0,
+            max_buffered_results: None,
         };
 
         reader.spawn_worker_thread();
@@ -101,6 +141,22 @@ impl<R: Read> Lzma2ReaderMt<R> {
         reader
     }
 
+    /// Caps how many completed-but-unconsumed result chunks are buffered.
+    ///
+    /// When the cap is reached, no further input is read or dispatched
+    /// until the consumer drains results, bounding peak memory for a fast
+    /// producer feeding a slow consumer. The default is unbounded.
+    pub fn with_max_buffered_results(mut self, max_buffered_results: Option<usize>) -> Self {
+        self.max_buffered_results = max_buffered_results;
+        self
+    }
+
+    /// Whether the out-of-order result buffer has reached its cap.
+    fn results_buffer_full(&self) -> bool {
+        self.max_buffered_results
+            .is_some_and(|limit| self.out_of_order_chunks.len() >= limit)
+    }
+
     fn spawn_worker_thread(&mut self) {
         let worker_handle = self.work_queue.worker();
         let result_tx = self.result_tx.clone();
@@ -110,7 +166,7 @@ impl<R: Read> Lzma2ReaderMt<R> {
         let preset_dict = self.preset_dict.clone();
         let dict_size = self.dict_size;
 
-        let handle = thread::spawn(move || {
+        let worker = move || {
             worker_thread_logic(
                 worker_handle,
                 result_tx,
@@ -120,9 +176,19 @@ impl<R: Read> Lzma2ReaderMt<R> {
                 error_store,
                 active_workers,
             );
-        });
+        };
 
-        self.worker_handles.push(handle);
+        match &self.shared_pool {
+            Some(shared_pool) => {
+                if shared_pool.execute(Box::new(worker)) {
+                    self.spawned_workers += 1;
+                }
+            }
+            None => {
+                self.worker_handles.push(thread::spawn(worker));
+                self.spawned_workers += 1;
+            }
+        }
     }
 
     /// The count of independent chunks found inside the compressed file.
@@ -131,6 +197,26 @@ impl<R: Read> Lzma2ReaderMt<R> {
         self.next_sequence_to_return
     }
 
+    /// Waits for the next result, waking up periodically to notice worker
+    /// errors. A worker that hits a decode error stores it and exits without
+    /// sending a result, so a plain blocking `recv` would deadlock.
+    fn recv_result(&self) -> Result<ResultUnit, mpsc::RecvTimeoutError> {
+        loop {
+            match self
+                .result_rx
+                .recv_timeout(core::time::Duration::from_millis(50))
+            {
+                Ok(unit) => return Ok(unit),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if self.shutdown_flag.load(Ordering::Acquire) {
+                        return Err(mpsc::RecvTimeoutError::Timeout);
+                    }
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
     /// Reads one LZMA2 chunk from the inner reader and appends it to the current work unit.
     /// If the chunk is an independent block, it dispatches the current work unit.
     ///
@@ -180,10 +266,7 @@ impl<R: Read> Lzma2ReaderMt<R> {
             self.current_work_unit.extend_from_slice(&size_buf);
             u16::from_be_bytes(size_buf) as usize + 1
         } else {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("invalid LZMA2 control byte: {control:X}"),
-            ));
+            return Err(error_invalid_data("invalid LZMA2 control byte"));
         };
 
         // Read the chunk data itself.
@@ -222,7 +305,7 @@ impl<R: Read> Lzma2ReaderMt<R> {
 
         // We spawn a new thread if we have work queued, no available workers, and haven't reached
         // the maximal allowed parallelism yet.
-        let spawned_workers = self.worker_handles.len() as u32;
+        let spawned_workers = self.spawned_workers;
         let active_workers = self.active_workers.load(Ordering::Acquire);
         let queue_len = self.work_queue.len();
 
@@ -275,8 +358,9 @@ impl<R: Read> Lzma2ReaderMt<R> {
                         }
                     }
 
-                    // If the work queue has capacity, try to read more from the source.
-                    if self.work_queue.is_empty() {
+                    // If the work queue has capacity and the consumer is
+                    // keeping up, try to read more from the source.
+                    if self.work_queue.is_empty() && !self.results_buffer_full() {
                         match self.read_and_dispatch_chunk() {
                             Ok(true) => {
                                 // Successfully read and dispatched a chunk, loop to continue.
@@ -300,7 +384,7 @@ impl<R: Read> Lzma2ReaderMt<R> {
                     }
 
                     // Now we MUST wait for a result to make progress.
-                    match self.result_rx.recv() {
+                    match self.recv_result() {
                         Ok((seq, result)) => {
                             if seq == self.next_sequence_to_return {
                                 self.next_sequence_to_return += 1;
@@ -311,6 +395,10 @@ impl<R: Read> Lzma2ReaderMt<R> {
                                 continue;
                             }
                         }
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            // Shutdown was signaled: loop to pick up the stored error.
+                            continue;
+                        }
                         Err(_) => {
                             // All workers are done.
                             self.state = State::Draining;
@@ -326,7 +414,7 @@ impl<R: Read> Lzma2ReaderMt<R> {
                     }
 
                     // In Draining state, we only wait for results.
-                    match self.result_rx.recv() {
+                    match self.recv_result() {
                         Ok((seq, result)) => {
                             if seq == self.next_sequence_to_return {
                                 self.next_sequence_to_return += 1;
@@ -335,6 +423,10 @@ impl<R: Read> Lzma2ReaderMt<R> {
                                 self.out_of_order_chunks.insert(seq, result);
                             }
                         }
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            // Shutdown was signaled: loop to pick up the stored error.
+                            continue;
+                        }
                         Err(_) => {
                             // All workers finished, and channel is empty. We are done.
                             self.state = State::Finished;
@@ -388,7 +480,11 @@ fn worker_thread_logic(
             Ok(_) => decompressed_data,
             Err(error) => {
                 active_workers.fetch_sub(1, Ordering::Release);
-                set_error(error, &error_store, &shutdown_flag);
+                set_error(
+                    crate::error_with_block_context(error, seq),
+                    &error_store,
+                    &shutdown_flag,
+                );
                 return;
             }
         };
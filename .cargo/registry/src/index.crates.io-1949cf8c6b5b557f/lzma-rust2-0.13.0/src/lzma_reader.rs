@@ -6,7 +6,7 @@ use super::{
 /// Calculates the memory usage in KiB required for LZMA decompression from properties byte.
 pub fn get_memory_usage_by_props(dict_size: u32, props_byte: u8) -> crate::Result<u32> {
     if dict_size > DICT_SIZE_MAX {
-        return Err(error_invalid_input("dict size too large"));
+        return Err(crate::error_invalid_dict_size("dict size too large"));
     }
     if props_byte > (4 * 5 + 4) * 9 + 8 {
         return Err(error_invalid_input("invalid props byte"));
@@ -27,7 +27,7 @@ pub fn get_memory_usage(dict_size: u32, lc: u32, lp: u32) -> crate::Result<u32>
 
 fn get_dict_size(dict_size: u32) -> crate::Result<u32> {
     if dict_size > DICT_SIZE_MAX {
-        return Err(error_invalid_input("dict size too large"));
+        return Err(crate::error_invalid_dict_size("dict size too large"));
     }
     let dict_size = dict_size.max(4096);
     Ok((dict_size + 15) & !15)
@@ -64,6 +64,7 @@ pub struct LzmaReader<R> {
     end_reached: bool,
     relaxed_end_cond: bool,
     remaining_size: u64,
+    total_out: u64,
 }
 
 impl<R> LzmaReader<R> {
@@ -99,7 +100,7 @@ impl<R: Read> LzmaReader<R> {
         let lp = props / 9;
         let lc = props - lp * 9;
         if dict_size > DICT_SIZE_MAX {
-            return Err(error_invalid_input("dict size too large"));
+            return Err(crate::error_invalid_dict_size("dict size too large"));
         }
         Self::construct2(
             reader,
@@ -120,6 +121,29 @@ impl<R: Read> LzmaReader<R> {
         pb: u32,
         dict_size: u32,
         preset_dict: Option<&[u8]>,
+    ) -> crate::Result<Self> {
+        Self::construct3(
+            reader,
+            uncomp_size,
+            lc,
+            lp,
+            pb,
+            dict_size,
+            preset_dict,
+            true,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn construct3(
+        reader: R,
+        uncomp_size: u64,
+        lc: u32,
+        lp: u32,
+        pb: u32,
+        dict_size: u32,
+        preset_dict: Option<&[u8]>,
+        leading_zero: bool,
     ) -> crate::Result<Self> {
         if lc > 8 || lp > 4 || pb > 4 {
             return Err(error_invalid_input("invalid lc or lp or pb"));
@@ -128,7 +152,11 @@ impl<R: Read> LzmaReader<R> {
         if uncomp_size <= u64::MAX / 2 && dict_size as u64 > uncomp_size {
             dict_size = get_dict_size(uncomp_size as u32)?;
         }
-        let rc = RangeDecoder::new_stream(reader);
+        let rc = if leading_zero {
+            RangeDecoder::new_stream(reader)
+        } else {
+            RangeDecoder::new_stream_no_leading_zero(reader)
+        };
         let rc = match rc {
             Ok(r) => r,
             Err(e) => {
@@ -145,6 +173,7 @@ impl<R: Read> LzmaReader<R> {
             end_reached: false,
             relaxed_end_cond: true,
             remaining_size: uncomp_size,
+            total_out: 0,
         })
     }
 
@@ -205,6 +234,44 @@ impl<R: Read> LzmaReader<R> {
         Self::construct2(reader, uncomp_size, lc, lp, pb, dict_size, preset_dict)
     }
 
+    /// Creates a new input stream that decompresses raw LZMA data which was
+    /// embedded without the leading zero byte of the range coder.
+    ///
+    /// Some container formats store raw LZMA1 without the first (always zero)
+    /// byte of the range-coded stream. This constructor skips the first-byte
+    /// check and reads the initial range coder state directly. Only use it for
+    /// such non-standard streams; standard raw LZMA belongs to [`Self::new`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_no_leading_zero(
+        reader: R,
+        uncomp_size: u64,
+        lc: u32,
+        lp: u32,
+        pb: u32,
+        dict_size: u32,
+        preset_dict: Option<&[u8]>,
+    ) -> crate::Result<Self> {
+        Self::construct3(
+            reader,
+            uncomp_size,
+            lc,
+            lp,
+            pb,
+            dict_size,
+            preset_dict,
+            false,
+        )
+    }
+
+    /// The number of uncompressed bytes produced so far.
+    ///
+    /// For streams terminated by the end marker (created with an unknown
+    /// uncompressed size of `u64::MAX`), this reports the actual size once
+    /// the end of the stream has been reached.
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
     fn read_decode(&mut self, buf: &mut [u8]) -> crate::Result<usize> {
         if buf.is_empty() {
             return Ok(0);
@@ -237,6 +304,7 @@ impl<R: Read> LzmaReader<R> {
             off += copied_size;
             len -= copied_size;
             size += copied_size;
+            self.total_out += copied_size;
             if self.remaining_size <= u64::MAX / 2 {
                 self.remaining_size -= copied_size;
                 if self.remaining_size == 0 {
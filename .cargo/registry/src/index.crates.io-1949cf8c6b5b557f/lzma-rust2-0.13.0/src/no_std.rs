@@ -23,6 +23,23 @@ pub enum Error {
     WriteZero(&'static str),
 }
 
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::EOF => write!(f, "end of file reached unexpectedly"),
+            Error::Interrupted => write!(f, "operation was interrupted"),
+            Error::InvalidData(message) => write!(f, "invalid data: {message}"),
+            Error::InvalidInput(message) => write!(f, "invalid input: {message}"),
+            Error::OutOfMemory(message) => write!(f, "out of memory: {message}"),
+            Error::Other(message) => write!(f, "{message}"),
+            Error::Unsupported(message) => write!(f, "unsupported: {message}"),
+            Error::WriteZero(message) => write!(f, "could not write any bytes: {message}"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
 /// `no_std` compatible `std::io::Read` trait
 ///
 /// Will get removed once there is a standard way in either `core` or `alloc`.
@@ -178,3 +195,46 @@ impl<W: Write + ?Sized> Write for alloc::boxed::Box<W> {
         (**self).flush()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::*;
+
+    #[test]
+    fn display_each_variant() {
+        assert_eq!(
+            format!("{}", Error::EOF),
+            "end of file reached unexpectedly"
+        );
+        assert_eq!(
+            format!("{}", Error::Interrupted),
+            "operation was interrupted"
+        );
+        assert_eq!(
+            format!("{}", Error::InvalidData("bad header")),
+            "invalid data: bad header"
+        );
+        assert_eq!(
+            format!("{}", Error::InvalidInput("bad option")),
+            "invalid input: bad option"
+        );
+        assert_eq!(
+            format!("{}", Error::OutOfMemory("dict too large")),
+            "out of memory: dict too large"
+        );
+        assert_eq!(
+            format!("{}", Error::Other("something failed")),
+            "something failed"
+        );
+        assert_eq!(
+            format!("{}", Error::Unsupported("no seeking")),
+            "unsupported: no seeking"
+        );
+        assert_eq!(
+            format!("{}", Error::WriteZero("sink full")),
+            "could not write any bytes: sink full"
+        );
+    }
+}
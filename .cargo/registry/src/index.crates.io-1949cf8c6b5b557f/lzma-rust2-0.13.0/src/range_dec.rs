@@ -33,6 +33,22 @@ impl RangeDecoder<RangeDecoderBuffer> {
             range: 0,
         }
     }
+
+    /// Creates a buffered range decoder over a caller-provided buffer. The
+    /// buffer's length takes the role of `size - 5` in
+    /// [`new_buffer`](Self::new_buffer).
+    pub(crate) fn from_provided_buffer(buf: Vec<u8>) -> Self {
+        Self {
+            inner: RangeDecoderBuffer::from_buffer(buf),
+            code: 0,
+            range: 0,
+        }
+    }
+
+    /// Consumes the decoder, returning its buffer for reuse.
+    pub(crate) fn into_buffer(self) -> Vec<u8> {
+        self.inner.into_buffer()
+    }
 }
 
 impl<R: RangeReader> RangeDecoder<R> {
@@ -49,6 +65,21 @@ impl<R: RangeReader> RangeDecoder<R> {
         })
     }
 
+    /// Creates a range decoder for non-standard raw LZMA streams that omit
+    /// the leading zero byte.
+    ///
+    /// The initial code is read directly from the first four bytes instead of
+    /// skipping a zero byte first; normalization and everything else behaves
+    /// like [`Self::new_stream`].
+    pub(crate) fn new_stream_no_leading_zero(mut inner: R) -> crate::Result<Self> {
+        let code = inner.read_u32_be()?;
+        Ok(Self {
+            inner,
+            code,
+            range: 0xFFFFFFFFu32,
+        })
+    }
+
     pub(crate) fn is_stream_finished(&self) -> bool {
         self.code == 0
     }
@@ -377,11 +408,22 @@ impl RangeDecoder<RangeDecoderBuffer> {
 
 impl RangeDecoderBuffer {
     pub(crate) fn new(len: usize) -> Self {
+        Self::from_buffer(vec![0; len])
+    }
+
+    /// Creates the range decoder buffer over a caller-provided zeroed
+    /// buffer.
+    pub(crate) fn from_buffer(buf: Vec<u8>) -> Self {
         Self {
-            buf: vec![0; len],
-            pos: len,
+            pos: buf.len(),
+            buf,
         }
     }
+
+    /// Consumes the buffer wrapper, returning its storage for reuse.
+    pub(crate) fn into_buffer(self) -> Vec<u8> {
+        self.buf
+    }
 }
 
 pub(crate) trait RangeReader {
@@ -460,7 +502,11 @@ impl RangeReader for RangeDecoderBuffer {
 
     #[inline(always)]
     fn read_u32_be(&mut self) -> crate::Result<u32> {
-        let b = u32::from_be_bytes(self.buf[self.pos..self.pos + 4].try_into().unwrap());
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(crate::error_eof)?;
+        let b = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
         self.pos += 4;
         Ok(b)
     }
@@ -485,3 +531,96 @@ impl RangeReader for RangeDecoderBuffer {
         self.buf.as_slice()
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::PROB_INIT;
+
+    /// A plain reference implementation the production bit-tree decoding must
+    /// match exactly, including the final decoder and probability state. This
+    /// pins the behavior for future optimization attempts on the literal
+    /// decoder's hot loop.
+    fn reference_bit_tree<R: crate::Read>(rc: &mut RangeDecoder<R>, probs: &mut [u16]) -> i32 {
+        let mut symbol = 1;
+        loop {
+            symbol = (symbol << 1) | rc.decode_bit(&mut probs[symbol as usize]);
+            if symbol >= probs.len() as i32 {
+                break;
+            }
+        }
+        symbol - probs.len() as i32
+    }
+
+    #[test]
+    fn bit_tree_matches_reference() {
+        let mut bytes = vec![0u8];
+        let mut seed = 0xBADC0FFEu64;
+        for _ in 0..4096 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            bytes.push((seed >> 32) as u8);
+        }
+
+        let mut rc1 = RangeDecoder::new_stream(&bytes[..]).unwrap();
+        let mut rc2 = RangeDecoder::new_stream(&bytes[..]).unwrap();
+        let mut probs1 = [PROB_INIT; 256];
+        let mut probs2 = [PROB_INIT; 256];
+
+        for _ in 0..1024 {
+            let lhs = rc1.decode_bit_tree(&mut probs1);
+            let rhs = reference_bit_tree(&mut rc2, &mut probs2);
+            assert_eq!(lhs, rhs);
+        }
+
+        assert_eq!(probs1, probs2);
+    }
+
+    /// Decodes the same range-coded bytes through a buffer-backed decoder
+    /// (which takes the inline-assembly path on x86_64 and aarch64 with the
+    /// `optimization` feature) and through a stream-backed decoder (always
+    /// the pure-Rust loop), asserting identical results and decoder state.
+    /// This guards the unsafe fast paths against diverging from the
+    /// reference after a refactor.
+    #[test]
+    fn direct_bits_asm_matches_pure_rust() {
+        let mut seed = 0x1234_5678_9ABC_DEF0u64;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for _ in 0..256 {
+            let mut bytes = vec![0u8];
+            for _ in 0..512 {
+                bytes.push((random() >> 32) as u8);
+            }
+
+            let mut buffered = RangeDecoder::new_buffer(bytes.len());
+            buffered.prepare(&bytes[..], bytes.len()).unwrap();
+            let mut streamed = RangeDecoder::new_stream(&bytes[..]).unwrap();
+
+            // Interleave direct-bits reads of random widths with single
+            // bits, so the fast path sees many different entry states.
+            let mut probs1 = [PROB_INIT; 2];
+            let mut probs2 = [PROB_INIT; 2];
+
+            for _ in 0..96 {
+                let count = (random() % 30 + 1) as u32;
+                let lhs = buffered.decode_direct_bits(count);
+                let rhs = streamed.decode_direct_bits(count);
+                assert_eq!(lhs, rhs, "direct bits diverged for count {count}");
+
+                assert_eq!(
+                    buffered.decode_bit(&mut probs1[1]),
+                    streamed.decode_bit(&mut probs2[1])
+                );
+            }
+
+            assert_eq!(probs1, probs2);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::{error::Error as StdError, fmt, io};
+
+/// A typed error payload for programmatic error handling.
+///
+/// With the `typed-errors` feature enabled, the errors produced by this crate
+/// carry an `LzmaError` as their source, so callers can match on the failure
+/// class instead of parsing message strings. Errors that originate in the
+/// underlying reader or writer (for example an `UnexpectedEof` from
+/// `read_exact` on a truncated source) pass through unchanged and carry no
+/// `LzmaError` payload:
+///
+/// ```
+/// use lzma_rust2::LzmaError;
+///
+/// fn is_checksum_failure(error: &std::io::Error) -> bool {
+///     error
+///         .get_ref()
+///         .and_then(|source| source.downcast_ref::<LzmaError>())
+///         .is_some_and(|error| matches!(error, LzmaError::ChecksumMismatch))
+/// }
+/// ```
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LzmaError {
+    /// A stored checksum does not match the decompressed data.
+    ChecksumMismatch,
+    /// The named filter was rejected, for example by the decode allowlist.
+    #[cfg(feature = "xz")]
+    UnsupportedFilter(crate::FilterType),
+    /// A dictionary size is outside the valid range.
+    InvalidDictSize,
+    /// The stream ended before the decoder was finished.
+    Truncated,
+    /// The compressed data is corrupt.
+    Corrupted(&'static str),
+    /// The operation or stream feature is unsupported.
+    Unsupported(&'static str),
+    /// An argument or option is invalid.
+    InvalidInput(&'static str),
+    /// An allocation limit was exceeded.
+    OutOfMemory(&'static str),
+    /// Any other error.
+    Other(&'static str),
+    /// An error in a specific block or chunk of a multithreaded decode,
+    /// identified by its zero-based index.
+    InBlock(u64, Box<LzmaError>),
+}
+
+impl LzmaError {
+    fn kind(&self) -> io::ErrorKind {
+        match self {
+            LzmaError::ChecksumMismatch | LzmaError::Corrupted(_) => io::ErrorKind::InvalidData,
+            #[cfg(feature = "xz")]
+            LzmaError::UnsupportedFilter(_) => io::ErrorKind::Unsupported,
+            LzmaError::Unsupported(_) => io::ErrorKind::Unsupported,
+            LzmaError::InvalidDictSize | LzmaError::InvalidInput(_) => io::ErrorKind::InvalidInput,
+            LzmaError::Truncated => io::ErrorKind::UnexpectedEof,
+            LzmaError::OutOfMemory(_) => io::ErrorKind::OutOfMemory,
+            LzmaError::Other(_) => io::ErrorKind::Other,
+            LzmaError::InBlock(_, source) => source.kind(),
+        }
+    }
+}
+
+impl fmt::Display for LzmaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LzmaError::ChecksumMismatch => f.write_str("checksum mismatch"),
+            #[cfg(feature = "xz")]
+            LzmaError::UnsupportedFilter(filter) => {
+                write!(f, "unsupported filter {filter:?}")
+            }
+            LzmaError::InvalidDictSize => f.write_str("invalid dictionary size"),
+            LzmaError::Truncated => f.write_str("unexpected end of stream"),
+            LzmaError::Corrupted(msg)
+            | LzmaError::Unsupported(msg)
+            | LzmaError::InvalidInput(msg)
+            | LzmaError::OutOfMemory(msg)
+            | LzmaError::Other(msg) => f.write_str(msg),
+            LzmaError::InBlock(block_index, source) => {
+                write!(f, "block {block_index}: {source}")
+            }
+        }
+    }
+}
+
+impl StdError for LzmaError {}
+
+impl From<LzmaError> for io::Error {
+    fn from(error: LzmaError) -> Self {
+        io::Error::new(error.kind(), error)
+    }
+}
@@ -19,6 +19,9 @@ use crate::{
 pub(crate) struct WorkPoolConfig {
     pub(crate) num_workers: u32,
     pub(crate) num_work: u64,
+    pub(crate) shared_pool: Option<Arc<SharedWorkerPool>>,
+    #[cfg(feature = "spill-to-disk")]
+    pub(crate) spill_threshold: Option<u64>,
 }
 
 impl WorkPoolConfig {
@@ -26,6 +29,9 @@ impl WorkPoolConfig {
         Self {
             num_workers,
             num_work,
+            shared_pool: None,
+            #[cfg(feature = "spill-to-disk")]
+            spill_threshold: None,
         }
     }
 }
@@ -51,6 +57,112 @@ pub(crate) type WorkerFunction<W, R> = fn(
     Arc<AtomicU32>,
 );
 
+/// A long-lived pool of worker threads shared across multithreaded readers
+/// and writers.
+///
+/// Every [`WorkPool`] normally spawns its own threads and joins them when it
+/// is dropped. An application compressing many files sequentially pays that
+/// spawn/teardown for each file; with a shared pool, the threads are spawned
+/// once and the per-file work pools borrow them instead.
+///
+/// A reader or writer borrows up to `num_workers` threads for its entire
+/// lifetime, even while it is idle. When several instances run concurrently,
+/// the pool must be large enough for all of them combined; an undersized
+/// pool can deadlock, because a borrowed thread is only returned once the
+/// instance holding it is finished.
+pub struct SharedWorkerPool {
+    job_tx: mpsc::Sender<Box<dyn FnOnce() + Send>>,
+    handles: Vec<thread::JoinHandle<()>>,
+}
+
+impl core::fmt::Debug for SharedWorkerPool {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SharedWorkerPool")
+            .field("num_threads", &self.handles.len())
+            .finish()
+    }
+}
+
+impl SharedWorkerPool {
+    /// Creates a shared pool with `num_threads` long-lived worker threads.
+    /// Pass `0` to use all available cores.
+    pub fn new(num_threads: u32) -> Self {
+        let num_threads = crate::resolve_workers(num_threads);
+        let (job_tx, job_rx) = mpsc::channel::<Box<dyn FnOnce() + Send>>();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        let handles = (0..num_threads)
+            .map(|_| {
+                let job_rx = Arc::clone(&job_rx);
+                thread::spawn(move || loop {
+                    let job = job_rx.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                })
+            })
+            .collect();
+
+        Self { job_tx, handles }
+    }
+
+    /// The number of threads in the pool.
+    pub fn num_threads(&self) -> u32 {
+        self.handles.len() as u32
+    }
+
+    pub(crate) fn execute(&self, job: Box<dyn FnOnce() + Send>) -> bool {
+        self.job_tx.send(job).is_ok()
+    }
+}
+
+impl Drop for SharedWorkerPool {
+    fn drop(&mut self) {
+        // Closing the channel lets the threads exit once idle.
+        let (closed_tx, _) = mpsc::channel();
+        self.job_tx = closed_tx;
+
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// A result that can be written to and restored from a temporary file when
+/// the out-of-order buffer exceeds the spill threshold.
+#[cfg(feature = "spill-to-disk")]
+pub(crate) trait Spill: Sized {
+    fn spill_to(&self, writer: &mut dyn io::Write) -> io::Result<()>;
+    fn restore_from(reader: &mut dyn io::Read) -> io::Result<Self>;
+    fn spill_size(&self) -> usize;
+}
+
+#[cfg(feature = "spill-to-disk")]
+impl Spill for Vec<u8> {
+    fn spill_to(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        writer.write_all(self)
+    }
+
+    fn restore_from(reader: &mut dyn io::Read) -> io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn spill_size(&self) -> usize {
+        self.len()
+    }
+}
+
+/// One entry of the out-of-order buffer, either in memory or spilled to a
+/// temporary file.
+enum StoredResult<R> {
+    Memory(R),
+    #[cfg(feature = "spill-to-disk")]
+    Spilled(std::path::PathBuf),
+}
+
 /// A generic work pool for the multi threading reader and writer.
 pub(crate) struct WorkPool<W, R> {
     work_queue: WorkStealingQueue<(u64, W)>,
@@ -59,7 +171,11 @@ pub(crate) struct WorkPool<W, R> {
     next_index_to_dispatch: u64,
     next_index_to_return: u64,
     last_sequence_id: Option<u64>,
-    out_of_order_results: BTreeMap<u64, R>,
+    out_of_order_results: BTreeMap<u64, StoredResult<R>>,
+    #[cfg(feature = "spill-to-disk")]
+    spill_threshold: Option<u64>,
+    #[cfg(feature = "spill-to-disk")]
+    in_memory_bytes: u64,
     shutdown_flag: Arc<AtomicBool>,
     error_store: Arc<Mutex<Option<io::Error>>>,
     state: WorkPoolState,
@@ -67,13 +183,28 @@ pub(crate) struct WorkPool<W, R> {
     num_workers: u32,
     num_work: u64,
     worker_handles: Vec<thread::JoinHandle<()>>,
+    shared_pool: Option<Arc<SharedWorkerPool>>,
+    spawned_workers: u32,
     worker_fn: WorkerFunction<W, R>,
 }
 
+/// The bound on work pool results. With `spill-to-disk` enabled, results
+/// additionally have to be storable in temporary files.
+#[cfg(feature = "spill-to-disk")]
+pub(crate) trait ResultBound: Send + Spill + 'static {}
+#[cfg(feature = "spill-to-disk")]
+impl<T: Send + Spill + 'static> ResultBound for T {}
+
+/// The bound on work pool results.
+#[cfg(not(feature = "spill-to-disk"))]
+pub(crate) trait ResultBound: Send + 'static {}
+#[cfg(not(feature = "spill-to-disk"))]
+impl<T: Send + 'static> ResultBound for T {}
+
 impl<W, R> WorkPool<W, R>
 where
     W: Send + 'static,
-    R: Send + 'static,
+    R: ResultBound,
 {
     /// Create a new work pool that spawns workers using the provided worker function.
     pub(crate) fn new(config: WorkPoolConfig, worker_fn: WorkerFunction<W, R>) -> Self {
@@ -87,13 +218,19 @@ where
             next_index_to_return: 0,
             last_sequence_id: None,
             out_of_order_results: BTreeMap::new(),
+            #[cfg(feature = "spill-to-disk")]
+            spill_threshold: config.spill_threshold,
+            #[cfg(feature = "spill-to-disk")]
+            in_memory_bytes: 0,
             shutdown_flag: Arc::new(AtomicBool::new(false)),
             error_store: Arc::new(Mutex::new(None)),
             state: WorkPoolState::Dispatching,
             active_workers: Arc::new(AtomicU32::new(0)),
-            num_workers: config.num_workers.clamp(1, 256),
+            num_workers: crate::resolve_workers(config.num_workers),
             num_work: config.num_work,
             worker_handles: Vec::new(),
+            shared_pool: config.shared_pool,
+            spawned_workers: 0,
             worker_fn,
         };
 
@@ -106,10 +243,72 @@ where
         self.next_index_to_dispatch
     }
 
-    /// Submit work to the pool. Returns `false` if there is no more work to work on.
+    /// Stores an out-of-order result, spilling it to a temporary file when
+    /// the in-memory buffer would exceed the configured threshold.
+    fn stash_result(&mut self, seq: u64, result: R) -> io::Result<()> {
+        #[cfg(feature = "spill-to-disk")]
+        if let Some(threshold) = self.spill_threshold {
+            let size = result.spill_size() as u64;
+
+            if self.in_memory_bytes + size > threshold {
+                let path = std::env::temp_dir().join(format!(
+                    "lzma-rust2-spill-{}-{:p}-{seq}",
+                    std::process::id(),
+                    std::ptr::addr_of!(self.num_work),
+                ));
+                let mut file = std::fs::File::create(&path)?;
+                result.spill_to(&mut file)?;
+                self.out_of_order_results
+                    .insert(seq, StoredResult::Spilled(path));
+                return Ok(());
+            }
+
+            self.in_memory_bytes += size;
+        }
+
+        self.out_of_order_results
+            .insert(seq, StoredResult::Memory(result));
+
+        Ok(())
+    }
+
+    /// Takes the next in-order result out of the buffer, restoring it from
+    /// its temporary file when it was spilled.
+    fn take_next_result(&mut self) -> io::Result<Option<R>> {
+        let Some(stored) = self.out_of_order_results.remove(&self.next_index_to_return) else {
+            return Ok(None);
+        };
+
+        self.next_index_to_return += 1;
+
+        match stored {
+            StoredResult::Memory(result) => {
+                #[cfg(feature = "spill-to-disk")]
+                if self.spill_threshold.is_some() {
+                    self.in_memory_bytes = self
+                        .in_memory_bytes
+                        .saturating_sub(result.spill_size() as u64);
+                }
+
+                Ok(Some(result))
+            }
+            #[cfg(feature = "spill-to-disk")]
+            StoredResult::Spilled(path) => {
+                let mut file = std::fs::File::open(&path)?;
+                let result = R::restore_from(&mut file)?;
+                drop(file);
+                let _ = std::fs::remove_file(&path);
+
+                Ok(Some(result))
+            }
+        }
+    }
+
+    /// Submit work to the pool. The closure returns `Ok(None)` when the work
+    /// source is exhausted. Returns `false` if there is no more work to work on.
     pub(crate) fn dispatch_next_work<F>(&mut self, next_work_function: &mut F) -> io::Result<bool>
     where
-        F: FnMut(u64) -> io::Result<W>,
+        F: FnMut(u64) -> io::Result<Option<W>>,
     {
         let next_index = self.next_index_to_dispatch;
 
@@ -118,7 +317,10 @@ where
             return Ok(false);
         }
 
-        let work = next_work_function(next_index)?;
+        let Some(work) = next_work_function(next_index)? else {
+            // The work source is exhausted.
+            return Ok(false);
+        };
 
         if !self.work_queue.push((next_index, work)) {
             // Queue is closed, this indicates shutdown.
@@ -144,8 +346,7 @@ where
     /// Try to get the next result in sequence order. Returns None if no result is ready.
     pub(crate) fn try_get_result(&mut self) -> io::Result<Option<R>> {
         // Check if we have the next result in sequence.
-        if let Some(result) = self.out_of_order_results.remove(&self.next_index_to_return) {
-            self.next_index_to_return += 1;
+        if let Some(result) = self.take_next_result()? {
             return Ok(Some(result));
         }
 
@@ -162,7 +363,7 @@ where
                     self.next_index_to_return += 1;
                     Ok(Some(result))
                 } else {
-                    self.out_of_order_results.insert(seq, result);
+                    self.stash_result(seq, result)?;
                     Ok(None)
                 }
             }
@@ -176,15 +377,16 @@ where
         }
     }
 
-    /// Get the next result in sequence order, blocking until available.
+    /// Get the next result in sequence order, blocking until available. The
+    /// closure provides further work units and returns `Ok(None)` when the
+    /// work source is exhausted.
     pub(crate) fn get_result<F>(&mut self, mut next_work_function: F) -> io::Result<Option<R>>
     where
-        F: FnMut(u64) -> io::Result<W>,
+        F: FnMut(u64) -> io::Result<Option<W>>,
     {
         loop {
             // Always check for already-received results first.
-            if let Some(result) = self.out_of_order_results.remove(&self.next_index_to_return) {
-                self.next_index_to_return += 1;
+            if let Some(result) = self.take_next_result()? {
                 return Ok(Some(result));
             }
 
@@ -204,7 +406,7 @@ where
                                 self.next_index_to_return += 1;
                                 return Ok(Some(result));
                             } else {
-                                self.out_of_order_results.insert(seq, result);
+                                self.stash_result(seq, result)?;
                                 continue; // Loop again to check the out_of_order_results.
                             }
                         }
@@ -245,7 +447,7 @@ where
                                 self.next_index_to_return += 1;
                                 return Ok(Some(result));
                             } else {
-                                self.out_of_order_results.insert(seq, result);
+                                self.stash_result(seq, result)?;
                                 // We've made progress, loop to check the out_of_order_results.
                                 continue;
                             }
@@ -271,7 +473,7 @@ where
                                 self.next_index_to_return += 1;
                                 return Ok(Some(result));
                             } else {
-                                self.out_of_order_results.insert(seq, result);
+                                self.stash_result(seq, result)?;
                             }
                         }
                         Err(_) => {
@@ -318,7 +520,7 @@ where
         let active_workers = Arc::clone(&self.active_workers);
         let worker_fn = self.worker_fn;
 
-        let handle = thread::spawn(move || {
+        let worker = move || {
             worker_fn(
                 worker_handle,
                 result_tx,
@@ -326,13 +528,25 @@ where
                 error_store,
                 active_workers,
             );
-        });
+        };
 
-        self.worker_handles.push(handle);
+        match &self.shared_pool {
+            Some(shared_pool) => {
+                // The shared pool's thread runs this worker until the work
+                // queue closes, then returns to the pool.
+                if shared_pool.execute(Box::new(worker)) {
+                    self.spawned_workers += 1;
+                }
+            }
+            None => {
+                self.worker_handles.push(thread::spawn(worker));
+                self.spawned_workers += 1;
+            }
+        }
     }
 
     fn maybe_spawn_worker(&mut self) {
-        let spawned_workers = self.worker_handles.len() as u32;
+        let spawned_workers = self.spawned_workers;
         let active_workers = self.active_workers.load(Ordering::Acquire);
         let queue_len = self.work_queue.len();
 
@@ -353,5 +567,119 @@ impl<W, R> Drop for WorkPool<W, R> {
         self.work_queue.close();
         // Worker threads will exit when the work queue is closed
         // JoinHandles will be dropped, which is fine since we set the shutdown flag
+
+        // Remove any results still spilled to temporary files.
+        #[cfg(feature = "spill-to-disk")]
+        for stored in self.out_of_order_results.values() {
+            if let StoredResult::Spilled(path) = stored {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "spill-to-disk"))]
+mod tests {
+    use super::*;
+
+    /// Echoes work units back as results, holding back sequence 0 so the
+    /// later results pile up out of order and spill deterministically.
+    fn delaying_worker(
+        worker_handle: WorkerHandle<(u64, Vec<u8>)>,
+        result_tx: SyncSender<(u64, Vec<u8>)>,
+        shutdown_flag: Arc<AtomicBool>,
+        _error_store: Arc<Mutex<Option<io::Error>>>,
+        active_workers: Arc<AtomicU32>,
+    ) {
+        while !shutdown_flag.load(Ordering::Acquire) {
+            let Some((seq, data)) = worker_handle.steal() else {
+                break;
+            };
+            active_workers.fetch_add(1, Ordering::Release);
+
+            if seq == 0 {
+                std::thread::sleep(core::time::Duration::from_millis(500));
+            }
+
+            let disconnected = result_tx.send((seq, data)).is_err();
+            active_workers.fetch_sub(1, Ordering::Release);
+
+            if disconnected {
+                return;
+            }
+        }
+    }
+
+    /// Counts this process's spill files still present in the temp
+    /// directory.
+    fn spill_files() -> usize {
+        let prefix = format!("lzma-rust2-spill-{}-", std::process::id());
+        std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(&prefix)
+            })
+            .count()
+    }
+
+    #[test]
+    fn results_spill_to_disk_and_restore_in_order() {
+        const UNITS: u64 = 6;
+
+        let mut config = WorkPoolConfig::new(4, UNITS);
+        // Any out-of-order result exceeds a zero threshold and spills.
+        config.spill_threshold = Some(0);
+
+        let mut pool: WorkPool<Vec<u8>, Vec<u8>> = WorkPool::new(config, delaying_worker);
+
+        let mut dispatched = 0u64;
+        let mut results = Vec::new();
+
+        while results.len() < UNITS as usize {
+            let result = pool
+                .get_result(|seq| {
+                    if seq < UNITS {
+                        // Pace the dispatch so the worker holding back
+                        // sequence 0 has registered before the next unit is
+                        // queued, which makes a second worker spawn and the
+                        // later results overtake sequence 0 deterministically.
+                        std::thread::sleep(core::time::Duration::from_millis(20));
+                        dispatched += 1;
+                        Ok(Some(alloc::vec![seq as u8; 64 * (seq as usize + 1)]))
+                    } else {
+                        Ok(None)
+                    }
+                })
+                .unwrap();
+
+            if let Some(result) = result {
+                if results.is_empty() {
+                    // Sequence 0 was held back for 200ms, so by the time it
+                    // arrives the later results have been stashed - and
+                    // with a zero threshold, spilled to disk.
+                    assert!(spill_files() > 0, "later results were spilled");
+                }
+
+                results.push(result);
+            }
+        }
+
+        // Results come back in order with their contents intact, even the
+        // ones that took the temporary-file round trip while sequence 0
+        // was held back.
+        assert_eq!(dispatched, UNITS);
+        for (seq, result) in results.iter().enumerate() {
+            assert_eq!(result.len(), 64 * (seq + 1));
+            assert!(result.iter().all(|byte| *byte == seq as u8));
+        }
+
+        // Every spilled temporary file was cleaned up again.
+        drop(pool);
+        assert_eq!(spill_files(), 0);
     }
 }
@@ -1,8 +1,16 @@
 //! XZ format decoder and encoder implementation.
 
+#[cfg(feature = "std")]
+mod indexed;
+#[cfg(feature = "std")]
+mod list;
 mod reader;
 #[cfg(feature = "std")]
 mod reader_mt;
+#[cfg(feature = "std")]
+mod reader_mt_stream;
+#[cfg(feature = "std")]
+mod record_reader;
 #[cfg(feature = "encoder")]
 mod writer;
 #[cfg(all(feature = "encoder", feature = "std"))]
@@ -12,12 +20,25 @@ use alloc::{boxed::Box, vec, vec::Vec};
 #[cfg(feature = "std")]
 use std::io::{self, Seek, SeekFrom};
 
-pub use reader::XzReader;
 #[cfg(feature = "std")]
-pub use reader_mt::XzReaderMt;
+pub use indexed::IndexedXzReader;
+#[cfg(all(feature = "std", feature = "encoder"))]
+pub use indexed::IndexedXzWriter;
+#[cfg(feature = "std")]
+pub use list::{xz_list, XzListing, XzStreamInfo};
+pub use reader::{try_decode_xz, xz_decompress, BlockHeaderCallback, BlockLayout, XzReader};
+#[cfg(feature = "std")]
+pub use reader_mt::{SharedSlice, XzReaderMt};
+#[cfg(feature = "std")]
+pub use reader_mt_stream::XzReaderMtStream;
+#[cfg(feature = "std")]
+pub use record_reader::{XzRecord, XzRecordReader};
+#[cfg(feature = "xz-sha256")]
 use sha2::Digest;
 #[cfg(feature = "encoder")]
-pub use writer::{AutoFinishXzWriter, XzOptions, XzWriter};
+pub use writer::{
+    xz_compress, AutoFinishXzWriter, XzFilterChainBuilder, XzMultiStreamWriter, XzOptions, XzWriter,
+};
 #[cfg(all(feature = "encoder", feature = "std"))]
 pub use writer_mt::{AutoFinishXzWriterMt, XzWriterMt};
 
@@ -28,9 +49,82 @@ use crate::{
     Lzma2Reader,
 };
 
-const CRC32: crc::Crc<u32, crc::Table<16>> =
-    crc::Crc::<u32, crc::Table<16>>::new(&crc::CRC_32_ISO_HDLC);
-const CRC64: crc::Crc<u64, crc::Table<16>> = crc::Crc::<u64, crc::Table<16>>::new(&crc::CRC_64_XZ);
+const CRC32: crc::Crc<u32, crate::CrcTable> =
+    crc::Crc::<u32, crate::CrcTable>::new(&crc::CRC_32_ISO_HDLC);
+const CRC64: crc::Crc<u64, crate::CrcTable> =
+    crc::Crc::<u64, crate::CrcTable>::new(&crc::CRC_64_XZ);
+
+/// Computes the CRC32 checksum of `data` with the parameters used by the XZ
+/// format (`CRC_32_ISO_HDLC`).
+pub fn xz_crc32(data: &[u8]) -> u32 {
+    CRC32.checksum(data)
+}
+
+/// Computes the CRC64 checksum of `data` with the parameters used by the XZ
+/// format (`CRC_64_XZ`).
+pub fn xz_crc64(data: &[u8]) -> u64 {
+    CRC64.checksum(data)
+}
+
+/// Incremental variant of [`xz_crc32`] for streaming pipelines.
+pub struct Crc32Hasher {
+    digest: crc::Digest<'static, u32, crate::CrcTable>,
+}
+
+impl Crc32Hasher {
+    /// Creates a new hasher with the XZ CRC32 parameters.
+    pub fn new() -> Self {
+        Self {
+            digest: CRC32.digest(),
+        }
+    }
+
+    /// Feeds `data` into the checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        self.digest.update(data);
+    }
+
+    /// Consumes the hasher and returns the checksum.
+    pub fn finalize(self) -> u32 {
+        self.digest.finalize()
+    }
+}
+
+impl Default for Crc32Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Incremental variant of [`xz_crc64`] for streaming pipelines.
+pub struct Crc64Hasher {
+    digest: crc::Digest<'static, u64, crate::CrcTable>,
+}
+
+impl Crc64Hasher {
+    /// Creates a new hasher with the XZ CRC64 parameters.
+    pub fn new() -> Self {
+        Self {
+            digest: CRC64.digest(),
+        }
+    }
+
+    /// Feeds `data` into the checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        self.digest.update(data);
+    }
+
+    /// Consumes the hasher and returns the checksum.
+    pub fn finalize(self) -> u64 {
+        self.digest.finalize()
+    }
+}
+
+impl Default for Crc64Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 const XZ_MAGIC: [u8; 6] = [0xFD, b'7', b'z', b'X', b'Z', 0x00];
 
@@ -46,6 +140,9 @@ struct IndexRecord {
 struct Index {
     pub number_of_records: u64,
     pub records: Vec<IndexRecord>,
+    /// Total on-disk size of the index in bytes, including the indicator,
+    /// padding and CRC32.
+    pub size: u64,
 }
 
 #[derive(Debug)]
@@ -75,10 +172,62 @@ struct Block {
     uncompressed_size: u64,
 }
 
+/// Read-only information about one XZ block header, as passed to the
+/// [`XzReader::on_block_header`](crate::XzReader::on_block_header) callback.
+#[derive(Debug, Clone)]
+pub struct BlockInfo {
+    /// The filter chain of the block with the raw property of each filter,
+    /// outermost first, LZMA2 last.
+    pub filters: alloc::vec::Vec<(FilterType, u32)>,
+    /// The compressed size of the block, when present in the header.
+    pub compressed_size: Option<u64>,
+    /// The uncompressed size of the block, when present in the header.
+    pub uncompressed_size: Option<u64>,
+    /// The zero-based index of the block within its stream.
+    pub index: u64,
+}
+
+impl BlockInfo {
+    /// The block's LZMA2 dictionary size in bytes.
+    pub fn lzma2_dict_size(&self) -> Option<u32> {
+        self.property_of(FilterType::LZMA2)
+    }
+
+    /// The delta filter distance in bytes, when the block uses delta.
+    pub fn delta_distance(&self) -> Option<u32> {
+        self.property_of(FilterType::Delta)
+    }
+
+    /// The BCJ filter of the block along with its start offset, when one is
+    /// used.
+    pub fn bcj_filter(&self) -> Option<(FilterType, u32)> {
+        self.filters
+            .iter()
+            .find(|(filter_type, _)| !matches!(filter_type, FilterType::Delta | FilterType::LZMA2))
+            .copied()
+    }
+
+    /// The start offset of the block's BCJ filter, when one is used.
+    pub fn bcj_start_offset(&self) -> Option<u32> {
+        self.bcj_filter().map(|(_, start_offset)| start_offset)
+    }
+
+    fn property_of(&self, filter_type: FilterType) -> Option<u32> {
+        self.filters
+            .iter()
+            .find(|(candidate, _)| *candidate == filter_type)
+            .map(|(_, property)| *property)
+    }
+}
+
 /// Configuration for a filter in the XZ filter chain.
 #[derive(Debug, Clone)]
 pub struct FilterConfig {
+    /// The type of the filter.
     pub filter_type: FilterType,
+    /// The raw filter property: the distance for the delta filter, the start
+    /// offset for the BCJ filters and the dictionary size for LZMA2. Prefer
+    /// the typed [`Filter`] enum to avoid mixing these up.
     pub property: u32,
 }
 
@@ -156,6 +305,136 @@ impl FilterConfig {
     }
 }
 
+/// A typed description of a filter in the XZ filter chain.
+///
+/// [`FilterConfig`] stores the filter property as a raw `u32` whose meaning
+/// differs per filter (delta distance vs. BCJ start offset vs. LZMA2
+/// dictionary size). This enum names each property, so one cannot be passed
+/// where another is expected. Convert to a [`FilterConfig`] via `From`, and
+/// back via `TryFrom` (which validates the property range).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Delta filter with its distance in bytes (1-256).
+    Delta {
+        /// The delta distance in bytes (1-256).
+        distance: u32,
+    },
+    /// BCJ x86 filter.
+    BcjX86 {
+        /// The start offset of the filtered data.
+        start: u32,
+    },
+    /// BCJ PowerPC filter.
+    BcjPPC {
+        /// The start offset of the filtered data.
+        start: u32,
+    },
+    /// BCJ IA64 filter.
+    BcjIA64 {
+        /// The start offset of the filtered data.
+        start: u32,
+    },
+    /// BCJ ARM filter.
+    BcjARM {
+        /// The start offset of the filtered data.
+        start: u32,
+    },
+    /// BCJ ARM Thumb filter.
+    BcjARMThumb {
+        /// The start offset of the filtered data.
+        start: u32,
+    },
+    /// BCJ SPARC filter.
+    BcjSPARC {
+        /// The start offset of the filtered data.
+        start: u32,
+    },
+    /// BCJ ARM64 filter.
+    BcjARM64 {
+        /// The start offset of the filtered data.
+        start: u32,
+    },
+    /// BCJ RISC-V filter.
+    BcjRISCV {
+        /// The start offset of the filtered data.
+        start: u32,
+    },
+    /// LZMA2 filter.
+    Lzma2 {
+        /// The dictionary size in bytes.
+        dict_size: u32,
+    },
+}
+
+impl From<Filter> for FilterConfig {
+    fn from(filter: Filter) -> Self {
+        let (filter_type, property) = match filter {
+            Filter::Delta { distance } => (FilterType::Delta, distance),
+            Filter::BcjX86 { start } => (FilterType::BcjX86, start),
+            Filter::BcjPPC { start } => (FilterType::BcjPPC, start),
+            Filter::BcjIA64 { start } => (FilterType::BcjIA64, start),
+            Filter::BcjARM { start } => (FilterType::BcjARM, start),
+            Filter::BcjARMThumb { start } => (FilterType::BcjARMThumb, start),
+            Filter::BcjSPARC { start } => (FilterType::BcjSPARC, start),
+            Filter::BcjARM64 { start } => (FilterType::BcjARM64, start),
+            Filter::BcjRISCV { start } => (FilterType::BcjRISCV, start),
+            Filter::Lzma2 { dict_size } => (FilterType::LZMA2, dict_size),
+        };
+
+        Self {
+            filter_type,
+            property,
+        }
+    }
+}
+
+impl TryFrom<FilterConfig> for Filter {
+    type Error = crate::Error;
+
+    fn try_from(config: FilterConfig) -> crate::Result<Self> {
+        let filter = match config.filter_type {
+            FilterType::Delta => {
+                if config.property == 0 || config.property > 256 {
+                    return Err(error_invalid_input("delta distance must be in [1, 256]"));
+                }
+
+                Filter::Delta {
+                    distance: config.property,
+                }
+            }
+            FilterType::BcjX86 => Filter::BcjX86 {
+                start: config.property,
+            },
+            FilterType::BcjPPC => Filter::BcjPPC {
+                start: config.property,
+            },
+            FilterType::BcjIA64 => Filter::BcjIA64 {
+                start: config.property,
+            },
+            FilterType::BcjARM => Filter::BcjARM {
+                start: config.property,
+            },
+            FilterType::BcjARMThumb => Filter::BcjARMThumb {
+                start: config.property,
+            },
+            FilterType::BcjSPARC => Filter::BcjSPARC {
+                start: config.property,
+            },
+            FilterType::BcjARM64 => Filter::BcjARM64 {
+                start: config.property,
+            },
+            FilterType::BcjRISCV => Filter::BcjRISCV {
+                start: config.property,
+            },
+            FilterType::LZMA2 => Filter::Lzma2 {
+                dict_size: config.property,
+            },
+        };
+
+        Ok(filter)
+    }
+}
+
 /// Supported checksum types in XZ format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CheckType {
@@ -165,7 +444,8 @@ pub enum CheckType {
     Crc32 = 0x01,
     /// CRC64
     Crc64 = 0x04,
-    /// SHA-256
+    /// SHA-256 (needs the `xz-sha256` feature)
+    #[cfg(feature = "xz-sha256")]
     Sha256 = 0x0A,
 }
 
@@ -175,22 +455,24 @@ impl CheckType {
             0x00 => Ok(CheckType::None),
             0x01 => Ok(CheckType::Crc32),
             0x04 => Ok(CheckType::Crc64),
+            #[cfg(feature = "xz-sha256")]
             0x0A => Ok(CheckType::Sha256),
             _ => Err(error_invalid_data("unsupported XZ check type")),
         }
     }
 
-    #[cfg(feature = "encoder")]
     fn checksum_size(self) -> u64 {
         match self {
             CheckType::None => 0,
             CheckType::Crc32 => 4,
             CheckType::Crc64 => 8,
+            #[cfg(feature = "xz-sha256")]
             CheckType::Sha256 => 32,
         }
     }
 }
 
+/// Supported filter types in the XZ filter chain.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum FilterType {
     /// Delta filter
@@ -450,17 +732,8 @@ impl BlockHeader {
                             offset += 4;
 
                             // Validate alignment based on filter type.
-                            let bcj_alignment = match filter_type {
-                                FilterType::BcjX86 => 1,
-                                FilterType::BcjPPC => 4,
-                                FilterType::BcjIA64 => 16,
-                                FilterType::BcjARM => 4,
-                                FilterType::BcjARMThumb => 2,
-                                FilterType::BcjSPARC => 4,
-                                FilterType::BcjARM64 => 4,
-                                FilterType::BcjRISCV => 2,
-                                _ => unreachable!(),
-                            };
+                            let bcj_alignment =
+                                bcj_start_offset_alignment(filter_type).unwrap_or(1);
 
                             if start_offset_value % bcj_alignment != 0 {
                                 return Err(error_invalid_data(
@@ -549,7 +822,9 @@ impl BlockHeader {
         crc.update(&header_data[..offset]);
 
         if expected_crc != crc.finalize() {
-            return Err(error_invalid_data("XZ block header CRC32 mismatch"));
+            return Err(crate::error_checksum_mismatch(
+                "XZ block header CRC32 mismatch",
+            ));
         }
 
         Ok(Some(BlockHeader {
@@ -578,7 +853,24 @@ impl BlockHeader {
             return Err(error_invalid_data("Block data too short for header"));
         }
 
-        let header_data = &block_data[1..header_size];
+        // The last 4 header bytes are the CRC32 over everything before them.
+        let expected_crc = u32::from_le_bytes([
+            block_data[header_size - 4],
+            block_data[header_size - 3],
+            block_data[header_size - 2],
+            block_data[header_size - 1],
+        ]);
+
+        let mut crc = CRC32.digest();
+        crc.update(&block_data[..header_size - 4]);
+
+        if expected_crc != crc.finalize() {
+            return Err(crate::error_checksum_mismatch(
+                "XZ block header CRC32 mismatch",
+            ));
+        }
+
+        let header_data = &block_data[1..header_size - 4];
         let block_flags = header_data[0];
         let num_filters = ((block_flags & 0x03) + 1) as usize;
         let has_compressed_size = (block_flags & 0x40) != 0;
@@ -679,6 +971,17 @@ impl BlockHeader {
                                 header_data[offset + 3],
                             ]);
                             offset += 4;
+
+                            // Validate alignment based on filter type.
+                            let bcj_alignment =
+                                bcj_start_offset_alignment(filter_type).unwrap_or(1);
+
+                            if start_offset % bcj_alignment != 0 {
+                                return Err(error_invalid_data(
+                                    "BCJ start offset not aligned to filter requirements",
+                                ));
+                            }
+
                             start_offset
                         }
                         _ => return Err(error_invalid_data("Invalid BCJ properties size")),
@@ -731,8 +1034,9 @@ impl BlockHeader {
 /// Handles checksum calculation for different XZ check types.
 enum ChecksumCalculator {
     None,
-    Crc32(crc::Digest<'static, u32, crc::Table<16>>),
-    Crc64(crc::Digest<'static, u64, crc::Table<16>>),
+    Crc32(crc::Digest<'static, u32, crate::CrcTable>),
+    Crc64(crc::Digest<'static, u64, crate::CrcTable>),
+    #[cfg(feature = "xz-sha256")]
     Sha256(sha2::Sha256),
 }
 
@@ -742,6 +1046,7 @@ impl ChecksumCalculator {
             CheckType::None => Self::None,
             CheckType::Crc32 => Self::Crc32(CRC32.digest()),
             CheckType::Crc64 => Self::Crc64(CRC64.digest()),
+            #[cfg(feature = "xz-sha256")]
             CheckType::Sha256 => Self::Sha256(sha2::Sha256::new()),
         }
     }
@@ -755,6 +1060,7 @@ impl ChecksumCalculator {
             ChecksumCalculator::Crc64(crc) => {
                 crc.update(data);
             }
+            #[cfg(feature = "xz-sha256")]
             ChecksumCalculator::Sha256(sha) => {
                 sha.update(data);
             }
@@ -796,6 +1102,7 @@ impl ChecksumCalculator {
 
                 final_crc == expected_crc
             }
+            #[cfg(feature = "xz-sha256")]
             ChecksumCalculator::Sha256(sha) => {
                 if expected.len() != 32 {
                     return false;
@@ -814,6 +1121,7 @@ impl ChecksumCalculator {
             ChecksumCalculator::None => Vec::new(),
             ChecksumCalculator::Crc32(crc) => crc.finalize().to_le_bytes().to_vec(),
             ChecksumCalculator::Crc64(crc) => crc.finalize().to_le_bytes().to_vec(),
+            #[cfg(feature = "xz-sha256")]
             ChecksumCalculator::Sha256(sha) => sha.finalize().to_vec(),
         }
     }
@@ -845,7 +1153,9 @@ impl StreamHeader {
         let expected_crc = reader.read_u32()?;
 
         if expected_crc != CRC32.checksum(&flags) {
-            return Err(error_invalid_data("XZ stream header CRC32 mismatch"));
+            return Err(crate::error_checksum_mismatch(
+                "XZ stream header CRC32 mismatch",
+            ));
         }
 
         Ok(StreamHeader { check_type })
@@ -867,7 +1177,9 @@ impl StreamFooter {
         crc.update(&stream_flags);
 
         if expected_crc != crc.finalize() {
-            return Err(error_invalid_data("stream footer CRC32 mismatch"));
+            return Err(crate::error_checksum_mismatch(
+                "stream footer CRC32 mismatch",
+            ));
         }
 
         let mut footer_magic = [0u8; 2];
@@ -944,12 +1256,13 @@ impl Index {
         update_crc_with_padding(&mut crc, padding_needed);
 
         if expected_crc != crc.finalize() {
-            return Err(error_invalid_data("index CRC32 mismatch"));
+            return Err(crate::error_checksum_mismatch("index CRC32 mismatch"));
         }
 
         Ok(Index {
             number_of_records,
             records,
+            size: (bytes_read + padding_needed + 4) as u64,
         })
     }
 }
@@ -969,28 +1282,10 @@ fn write_xz_stream_header<W: Write>(writer: &mut W, check_type: CheckType) -> cr
 
 #[cfg(feature = "encoder")]
 fn encode_lzma2_dict_size(dict_size: u32) -> crate::Result<u8> {
-    if dict_size < 4096 {
-        return Err(error_invalid_input("LZMA2 dictionary size too small"));
-    }
-
-    if dict_size == 0xFFFFFFFF {
-        return Ok(40);
-    }
-
-    // Find the appropriate property value.
-    for prop in 0u8..40 {
-        let base = 2 | ((prop & 1) as u32);
-        let size = base << (prop / 2 + 11);
-
-        if size >= dict_size {
-            return Ok(prop);
-        }
-    }
-
-    Err(error_invalid_input("LZMA2 dictionary size too large"))
+    crate::lzma2_dict_size_to_prop(dict_size)
 }
 
-fn update_crc_with_padding(crc: &mut crc::Digest<'_, u32, crc::Table<16>>, padding_needed: usize) {
+fn update_crc_with_padding(crc: &mut crc::Digest<'_, u32, crate::CrcTable>, padding_needed: usize) {
     match padding_needed {
         1 => crc.update(&[0]),
         2 => crc.update(&[0, 0]),
@@ -1142,10 +1437,50 @@ fn add_padding<W: Write + ?Sized>(writer: &mut W, padding_needed: usize) -> crat
     }
 }
 
+/// The start offset alignment required by each BCJ filter, or `None` for
+/// filters that carry no start offset.
+fn bcj_start_offset_alignment(filter_type: FilterType) -> Option<u32> {
+    match filter_type {
+        FilterType::BcjX86 => Some(1),
+        FilterType::BcjPPC => Some(4),
+        FilterType::BcjIA64 => Some(16),
+        FilterType::BcjARM => Some(4),
+        FilterType::BcjARMThumb => Some(2),
+        FilterType::BcjSPARC => Some(4),
+        FilterType::BcjARM64 => Some(4),
+        FilterType::BcjRISCV => Some(2),
+        FilterType::Delta | FilterType::LZMA2 => None,
+    }
+}
+
+/// Validates the pre-filters of an XZ filter chain, mirroring the checks the
+/// reader applies when parsing a block header.
+#[cfg(feature = "encoder")]
+pub(crate) fn validate_pre_filters(filters: &[FilterConfig]) -> crate::Result<()> {
+    for filter_config in filters {
+        if filter_config.filter_type == FilterType::Delta
+            && (filter_config.property == 0 || filter_config.property > 256)
+        {
+            return Err(error_invalid_input("delta distance must be in [1, 256]"));
+        }
+
+        if let Some(alignment) = bcj_start_offset_alignment(filter_config.filter_type) {
+            if filter_config.property % alignment != 0 {
+                return Err(error_invalid_input(
+                    "BCJ start offset not aligned to filter requirements",
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(feature = "encoder")]
 fn generate_block_header_data(
     filters: &[FilterConfig],
     lzma_dict_size: u32,
+    sizes: Option<(u64, u64)>,
 ) -> crate::Result<Vec<u8>> {
     let mut header_data = Vec::new();
     let num_filters = filters.len();
@@ -1154,12 +1489,23 @@ fn generate_block_header_data(
         return Err(error_invalid_input("too many filters in chain (maximum 4)"));
     }
 
-    // Block flags: no compressed size, no uncompressed size, filter count
-    let block_flags = (num_filters - 1) as u8; // -1 because 0 means 1 filter, 3 means 4 filters
+    // Block flags: filter count, plus the size fields when the compressed
+    // and uncompressed sizes are declared.
+    let mut block_flags = (num_filters - 1) as u8; // -1 because 0 means 1 filter, 3 means 4 filters
+    if sizes.is_some() {
+        block_flags |= 0x40 | 0x80;
+    }
     header_data.push(block_flags);
 
     let mut temp_buf = [0u8; 10];
 
+    if let Some((compressed_size, uncompressed_size)) = sizes {
+        let size = encode_multibyte_integer(compressed_size, &mut temp_buf)?;
+        header_data.extend_from_slice(&temp_buf[..size]);
+        let size = encode_multibyte_integer(uncompressed_size, &mut temp_buf)?;
+        header_data.extend_from_slice(&temp_buf[..size]);
+    }
+
     for filter_config in filters {
         // Write filter ID.
         let filter_id = match filter_config.filter_type {
@@ -1224,8 +1570,9 @@ fn write_xz_block_header<W: Write>(
     writer: &mut W,
     filters: &[FilterConfig],
     lzma_dict_size: u32,
+    sizes: Option<(u64, u64)>,
 ) -> crate::Result<u64> {
-    let header_data = generate_block_header_data(filters, lzma_dict_size)?;
+    let header_data = generate_block_header_data(filters, lzma_dict_size, sizes)?;
 
     // Calculate header size (including size byte and CRC32, rounded up to multiple of 4)
     let total_size_needed: usize = 1 + header_data.len() + 4;
@@ -1327,6 +1674,70 @@ fn write_xz_stream_footer<W: Write>(
 mod tests {
     use super::*;
 
+    #[test]
+    fn filter_chain_header_round_trip() {
+        let pre_filters = [
+            FilterConfig::new_delta(4),
+            FilterConfig::new_bcj_x86(0),
+            FilterConfig::new_bcj_ppc(4),
+            FilterConfig::new_bcj_ia64(16),
+            FilterConfig::new_bcj_arm(4),
+            FilterConfig::new_bcj_arm_thumb(2),
+            FilterConfig::new_bcj_sparc(4),
+            FilterConfig::new_bcj_arm64(4),
+            FilterConfig::new_bcj_risc_v(2),
+        ];
+
+        for pre_filter in pre_filters {
+            let filters = [
+                pre_filter.clone(),
+                FilterConfig {
+                    filter_type: FilterType::LZMA2,
+                    property: 0,
+                },
+            ];
+
+            let mut header = Vec::new();
+            write_xz_block_header(&mut header, &filters, 1 << 20, None).unwrap();
+
+            let (parsed_filters, properties, _header_size) =
+                BlockHeader::parse_from_slice(&header).unwrap();
+
+            assert_eq!(parsed_filters[0], Some(pre_filter.filter_type));
+            assert_eq!(properties[0], pre_filter.property);
+            assert_eq!(parsed_filters[1], Some(FilterType::LZMA2));
+            // The LZMA2 property is recovered as the decoded dictionary size.
+            assert_eq!(properties[1], 1 << 20);
+            assert_eq!(parsed_filters[2], None);
+        }
+
+        // Misaligned BCJ start offsets must be rejected when parsing.
+        let misaligned = [
+            FilterConfig::new_bcj_ppc(2),
+            FilterConfig::new_bcj_ia64(8),
+            FilterConfig::new_bcj_arm(2),
+            FilterConfig::new_bcj_arm_thumb(1),
+            FilterConfig::new_bcj_sparc(2),
+            FilterConfig::new_bcj_arm64(2),
+            FilterConfig::new_bcj_risc_v(1),
+        ];
+
+        for pre_filter in misaligned {
+            let filters = [
+                pre_filter,
+                FilterConfig {
+                    filter_type: FilterType::LZMA2,
+                    property: 0,
+                },
+            ];
+
+            let mut header = Vec::new();
+            write_xz_block_header(&mut header, &filters, 1 << 20, None).unwrap();
+
+            assert!(BlockHeader::parse_from_slice(&header).is_err());
+        }
+    }
+
     #[test]
     fn test_encode_decode_multibyte_integer() {
         let values = [0, 127, 128, 16383, 16384, 2097151, 2097152];
@@ -1390,6 +1801,7 @@ mod tests {
         assert!(calc.verify(&expected));
     }
 
+    #[cfg(feature = "xz-sha256")]
     #[test]
     fn test_checksum_calculator_sha256() {
         let mut calc = ChecksumCalculator::new(CheckType::Sha256);
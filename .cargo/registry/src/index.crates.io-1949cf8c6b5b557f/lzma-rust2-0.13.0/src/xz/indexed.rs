@@ -0,0 +1,165 @@
+//! A high-level indexed archive format on top of seekable XZ.
+//!
+//! XZ itself has no entry names. [`IndexedXzWriter`] concatenates named
+//! entries into one XZ stream, starting a new block per entry, and appends
+//! the name-to-offset mapping as a final metadata block with a recognizable
+//! magic. [`IndexedXzReader`] reads the mapping back and serves individual
+//! entries through [`XzRecordReader`]-style random access, decoding only the
+//! blocks an entry covers. The result stays a valid XZ stream: ordinary
+//! decoders see the entries back to back, followed by the metadata bytes.
+
+use std::io::{self, Read, Seek, Write};
+
+use super::{XzRecord, XzRecordReader};
+use crate::error_invalid_data;
+#[cfg(feature = "encoder")]
+use crate::error_invalid_input;
+#[cfg(feature = "encoder")]
+use crate::{XzOptions, XzWriter};
+
+/// Identifies the trailing metadata block of an indexed XZ archive.
+const INDEX_MAGIC: &[u8; 6] = b"XZIDX\x01";
+
+/// Writes named entries into a single XZ stream with a trailing metadata
+/// block, producing a seekable archive for [`IndexedXzReader`].
+#[cfg(feature = "encoder")]
+pub struct IndexedXzWriter<W: Write> {
+    writer: XzWriter<W>,
+    entries: Vec<(String, u64)>,
+    uncompressed_pos: u64,
+}
+
+#[cfg(feature = "encoder")]
+impl<W: Write> IndexedXzWriter<W> {
+    /// Creates a new indexed archive writer.
+    pub fn new(inner: W, options: XzOptions) -> crate::Result<Self> {
+        Ok(Self {
+            writer: XzWriter::new(inner, options)?,
+            entries: Vec::new(),
+            uncompressed_pos: 0,
+        })
+    }
+
+    /// Starts a new named entry. All following writes belong to this entry
+    /// until the next [`begin_entry`](Self::begin_entry) or
+    /// [`finish`](Self::finish). Every entry starts its own XZ block, so
+    /// reading one entry never decodes another entry's data.
+    pub fn begin_entry(&mut self, name: &str) -> crate::Result<()> {
+        if name.len() > u16::MAX as usize {
+            return Err(error_invalid_input("entry name longer than 65535 bytes"));
+        }
+
+        self.writer.flush_block()?;
+        self.entries.push((name.into(), self.uncompressed_pos));
+
+        Ok(())
+    }
+
+    /// Finishes the archive: appends the metadata block and completes the
+    /// XZ stream, returning the inner writer.
+    pub fn finish(mut self) -> crate::Result<W> {
+        self.writer.flush_block()?;
+
+        let mut metadata = Vec::new();
+        metadata.extend_from_slice(INDEX_MAGIC);
+        metadata.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+
+        for (name, start) in &self.entries {
+            metadata.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            metadata.extend_from_slice(name.as_bytes());
+            metadata.extend_from_slice(&start.to_le_bytes());
+        }
+
+        self.writer.write_all(&metadata)?;
+        self.writer.finish()
+    }
+}
+
+#[cfg(feature = "encoder")]
+impl<W: Write> Write for IndexedXzWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.uncompressed_pos += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads entries of an archive produced by [`IndexedXzWriter`] with random
+/// access by name.
+pub struct IndexedXzReader<R: Read + Seek> {
+    records: XzRecordReader<R>,
+    names: Vec<String>,
+}
+
+impl<R: Read + Seek> IndexedXzReader<R> {
+    /// Opens an indexed archive, reading the trailing metadata block.
+    pub fn new(inner: R) -> io::Result<Self> {
+        let mut scan = XzRecordReader::new(inner, Vec::new())?;
+
+        // The metadata is exactly the last block: the writer ends the final
+        // entry's block before appending it.
+        let (metadata, metadata_start) = scan.load_last_block()?;
+
+        if metadata.len() < INDEX_MAGIC.len() + 4 || !metadata.starts_with(INDEX_MAGIC) {
+            return Err(error_invalid_data("missing indexed XZ metadata magic"));
+        }
+
+        let field = |cursor: usize, length: usize| -> io::Result<&[u8]> {
+            metadata
+                .get(cursor..cursor + length)
+                .ok_or_else(|| error_invalid_data("truncated indexed XZ metadata"))
+        };
+
+        let mut cursor = INDEX_MAGIC.len();
+        let count = u32::from_le_bytes(field(cursor, 4)?.try_into().expect("length checked"));
+        cursor += 4;
+
+        let mut names = Vec::new();
+        let mut boundaries = Vec::new();
+
+        for _ in 0..count {
+            let name_length =
+                u16::from_le_bytes(field(cursor, 2)?.try_into().expect("length checked")) as usize;
+            cursor += 2;
+
+            let name = core::str::from_utf8(field(cursor, name_length)?)
+                .map_err(|_| error_invalid_data("invalid entry name in indexed XZ"))?;
+            cursor += name_length;
+
+            let start = u64::from_le_bytes(field(cursor, 8)?.try_into().expect("length checked"));
+            cursor += 8;
+
+            names.push(name.into());
+            boundaries.push(start);
+        }
+
+        // The metadata region is the final record, bounding the last entry.
+        boundaries.push(metadata_start);
+        scan.set_boundaries(boundaries);
+
+        Ok(Self {
+            records: scan,
+            names,
+        })
+    }
+
+    /// The names of the archive's entries, in writing order.
+    pub fn entry_names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns a reader over the named entry's uncompressed bytes.
+    pub fn entry(&mut self, name: &str) -> io::Result<XzRecord<'_, R>> {
+        let index = self
+            .names
+            .iter()
+            .position(|candidate| candidate == name)
+            .ok_or_else(|| error_invalid_data("no such entry in the indexed XZ"))?;
+
+        self.records.record(index)
+    }
+}
@@ -0,0 +1,134 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{CheckType, Index, StreamFooter, XZ_MAGIC};
+use crate::{error_invalid_data, ByteReader, Result};
+
+/// Statistics for one stream of an XZ file, as reported by [`xz_list`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XzStreamInfo {
+    /// Total compressed size of the stream, including header, index and footer.
+    pub compressed_size: u64,
+    /// Total uncompressed size of the stream's blocks.
+    pub uncompressed_size: u64,
+    /// The number of blocks in the stream.
+    pub block_count: u64,
+    /// The check type used by the stream.
+    pub check_type: CheckType,
+}
+
+/// Statistics for a whole XZ file, as reported by [`xz_list`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct XzListing {
+    /// Per-stream statistics, in file order.
+    pub streams: Vec<XzStreamInfo>,
+    /// Total compressed size over all streams, including stream padding.
+    pub compressed_size: u64,
+    /// Total uncompressed size over all streams.
+    pub uncompressed_size: u64,
+}
+
+impl XzListing {
+    /// The compression ratio, compressed over uncompressed size.
+    pub fn ratio(&self) -> f64 {
+        if self.uncompressed_size == 0 {
+            return 1.0;
+        }
+
+        self.compressed_size as f64 / self.uncompressed_size as f64
+    }
+}
+
+/// Lists the contents of a seekable XZ file from its indexes alone, like
+/// `xz --list`, without touching any block data.
+///
+/// Concatenated streams are walked backward footer by footer, so the cost is
+/// proportional to the index sizes, not the file size.
+pub fn xz_list<R: Read + Seek>(mut reader: R) -> Result<XzListing> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    let mut end = file_size;
+    let mut streams = Vec::new();
+    let mut total_uncompressed = 0;
+
+    while end > 0 {
+        // Skip stream padding (zero bytes in multiples of 4).
+        while end >= 4 {
+            reader.seek(SeekFrom::Start(end - 4))?;
+            let mut padding = [0u8; 4];
+            reader.read_exact(&mut padding)?;
+
+            if padding != [0u8; 4] {
+                break;
+            }
+
+            end -= 4;
+        }
+
+        if end == 0 {
+            break;
+        }
+
+        // Minimum XZ stream: 12 byte header + 12 byte footer + 8 byte index.
+        if end < 32 {
+            return Err(error_invalid_data("XZ stream too small"));
+        }
+
+        reader.seek(SeekFrom::Start(end - 12))?;
+        let stream_footer = StreamFooter::parse(&mut reader)?;
+        let check_type = CheckType::from_byte(stream_footer.stream_flags[1])?;
+
+        let index_size = ((stream_footer.backward_size + 1) * 4) as u64;
+        let index_start = (end - 12)
+            .checked_sub(index_size)
+            .ok_or_else(|| error_invalid_data("XZ index size exceeds the stream"))?;
+
+        reader.seek(SeekFrom::Start(index_start))?;
+        let index_indicator = reader.read_u8()?;
+        if index_indicator != 0 {
+            return Err(error_invalid_data("invalid XZ index indicator"));
+        }
+
+        let index = Index::parse(&mut reader)?;
+
+        let mut blocks_size = 0;
+        let mut uncompressed_size = 0;
+
+        for record in &index.records {
+            let padding_needed = (4 - (record.unpadded_size % 4)) % 4;
+            blocks_size += record.unpadded_size + padding_needed;
+            uncompressed_size += record.uncompressed_size;
+        }
+
+        let stream_start = index_start
+            .checked_sub(blocks_size + 12)
+            .ok_or_else(|| error_invalid_data("XZ index sizes exceed the stream"))?;
+
+        reader.seek(SeekFrom::Start(stream_start))?;
+        let mut magic = [0u8; 6];
+        reader.read_exact(&mut magic)?;
+        if magic != XZ_MAGIC {
+            return Err(error_invalid_data("invalid XZ magic bytes"));
+        }
+
+        streams.push(XzStreamInfo {
+            compressed_size: end - stream_start,
+            uncompressed_size,
+            block_count: index.number_of_records,
+            check_type,
+        });
+        total_uncompressed += uncompressed_size;
+
+        end = stream_start;
+    }
+
+    if streams.is_empty() {
+        return Err(error_invalid_data("no XZ stream found"));
+    }
+
+    streams.reverse();
+
+    Ok(XzListing {
+        streams,
+        compressed_size: file_size,
+        uncompressed_size: total_uncompressed,
+    })
+}
@@ -1,10 +1,11 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 
 use super::{
-    BlockHeader, ChecksumCalculator, FilterType, Index, StreamFooter, StreamHeader, XZ_MAGIC,
+    BlockHeader, BlockInfo, ChecksumCalculator, FilterType, Index, IndexRecord, StreamFooter,
+    StreamHeader, XZ_MAGIC,
 };
 use crate::{
-    error_invalid_data,
+    error_invalid_data, error_other,
     filter::{bcj::BcjReader, delta::DeltaReader},
     CountingReader, Lzma2Reader, Read, Result,
 };
@@ -91,6 +92,17 @@ impl<R: Read> FilterReader<R> {
         chain_reader
     }
 
+    /// Caps how many bytes the innermost counting reader hands out.
+    fn set_read_limit(&mut self, limit: Option<u64>) {
+        match self {
+            FilterReader::Counting(reader) => reader.set_limit(limit),
+            FilterReader::LZMA2(reader) => reader.inner_mut().set_read_limit(limit),
+            FilterReader::Delta(reader) => reader.inner_mut().set_read_limit(limit),
+            FilterReader::Bcj(reader) => reader.inner_mut().set_read_limit(limit),
+            FilterReader::Dummy => unimplemented!(),
+        }
+    }
+
     fn bytes_read(&self) -> u64 {
         match self {
             FilterReader::Counting(reader) => reader.bytes_read(),
@@ -161,6 +173,15 @@ impl<R: Read> FilterReader<R> {
 }
 
 /// A single-threaded XZ decompressor.
+///
+/// In single-stream mode (`allow_multiple_streams` set to `false`), the
+/// reader is guaranteed to consume no bytes from the inner reader beyond the
+/// XZ stream's footer: there is no internal read-ahead. This makes it safe to
+/// use when an XZ stream is one frame inside a larger protocol; after the
+/// stream is fully read, continue reading the following application data from
+/// the inner reader (see [`into_inner_at_end`](Self::into_inner_at_end)).
+/// With multiple streams allowed, the reader scans past the footer looking
+/// for the next stream, so the guarantee does not hold.
 pub struct XzReader<R: Read> {
     reader: FilterReader<R>,
     stream_header: Option<StreamHeader>,
@@ -168,8 +189,31 @@ pub struct XzReader<R: Read> {
     finished: bool,
     allow_multiple_streams: bool,
     blocks_processed: u64,
+    declared_compressed_size: Option<u64>,
+    current_block_header_size: u64,
+    current_block_uncompressed: u64,
+    seen_records: Vec<IndexRecord>,
+    partial_recovery: bool,
+    recovery_truncated: bool,
+    recovered: Vec<u8>,
+    recovered_pos: usize,
+    on_block_header: Option<BlockHeaderCallback>,
+    allowed_filters: Option<Vec<FilterType>>,
+    verify_checks: bool,
 }
 
+/// Callback type for [`XzReader::on_block_header`].
+///
+/// The unwind-safety bounds keep [`XzReader`] unwind safe; wrap state that is
+/// not unwind safe in [`std::panic::AssertUnwindSafe`] before capturing it.
+#[cfg(feature = "std")]
+pub type BlockHeaderCallback =
+    Box<dyn FnMut(&BlockInfo) + std::panic::UnwindSafe + std::panic::RefUnwindSafe>;
+
+/// Callback type for [`XzReader::on_block_header`].
+#[cfg(not(feature = "std"))]
+pub type BlockHeaderCallback = Box<dyn FnMut(&BlockInfo)>;
+
 impl<R: Read> XzReader<R> {
     /// Create a new [`XzReader`].
     pub fn new(inner: R, allow_multiple_streams: bool) -> Self {
@@ -182,20 +226,148 @@ impl<R: Read> XzReader<R> {
             finished: false,
             allow_multiple_streams,
             blocks_processed: 0,
+            declared_compressed_size: None,
+            current_block_header_size: 0,
+            current_block_uncompressed: 0,
+            seen_records: Vec::new(),
+            partial_recovery: false,
+            recovery_truncated: false,
+            recovered: Vec::new(),
+            recovered_pos: 0,
+            on_block_header: None,
+            allowed_filters: None,
+            verify_checks: true,
         }
     }
 
+    /// Registers a callback that is invoked with every block header as it is
+    /// parsed during decoding.
+    ///
+    /// Useful for archive tooling that wants to enumerate the filters and
+    /// sizes of each block without writing a custom parser.
+    pub fn on_block_header(&mut self, callback: BlockHeaderCallback) {
+        self.on_block_header = Some(callback);
+    }
+
+    /// Enables or disables partial recovery of truncated streams.
+    ///
+    /// With partial recovery enabled, a stream that was cut off mid-write
+    /// (truncated final block, missing index or footer) does not cause `read`
+    /// to return an error. Instead all bytes from fully decoded,
+    /// checksum-validated blocks are returned, followed by a clean EOF.
+    /// Partial or corrupt trailing data is discarded.
+    ///
+    /// To guarantee that only validated data is handed out, each block is
+    /// buffered in memory until its checksum has been verified, so reading
+    /// needs memory proportional to the largest block in the stream.
+    ///
+    /// A stream whose header is already invalid is not recoverable and still
+    /// reports an error.
+    pub fn with_partial_recovery(mut self, partial_recovery: bool) -> Self {
+        self.partial_recovery = partial_recovery;
+        self
+    }
+
+    /// Sets whether block checksums are verified (the default) or skipped.
+    ///
+    /// Skipping trades integrity checking for decode speed, which is mainly
+    /// interesting for SHA-256-checked streams where hashing costs real CPU
+    /// time. The check bytes are still consumed so the stream stays
+    /// positioned correctly. Only disable this for data whose integrity is
+    /// guaranteed elsewhere; combined with
+    /// [`with_partial_recovery`](Self::with_partial_recovery), recovered
+    /// data is then no longer validated.
+    pub fn with_verify_checks(mut self, verify_checks: bool) -> Self {
+        self.verify_checks = verify_checks;
+        self
+    }
+
+    /// Restricts decoding to blocks whose filter chain only uses the given
+    /// filter types. Blocks declaring any other filter are rejected with an
+    /// unsupported error, limiting the attack surface when decoding
+    /// untrusted input. The allowlist must include [`FilterType::LZMA2`] for
+    /// any block to decode, since LZMA2 is always the final filter. By
+    /// default all supported filters are allowed.
+    ///
+    /// Combined with [`with_partial_recovery`](Self::with_partial_recovery),
+    /// a rejected block ends recovery instead of reporting the error: the
+    /// blocks before it are returned and
+    /// [`recovery_truncated`](Self::recovery_truncated) reports the cut.
+    pub fn with_allowed_filters(mut self, allowed_filters: &[FilterType]) -> Self {
+        self.allowed_filters = Some(allowed_filters.to_vec());
+        self
+    }
+
+    /// Whether partial recovery discarded truncated, corrupt or disallowed
+    /// trailing data instead of reporting an error.
+    pub fn recovery_truncated(&self) -> bool {
+        self.recovery_truncated
+    }
+
     /// Consume the XzReader and return the inner reader.
+    ///
+    /// Note that until the XZ stream has been fully read, the inner reader's
+    /// position is ahead of the logical decompressed position because of
+    /// internal buffering in the filter chain and the range decoder. Use
+    /// [`into_inner_at_end`](Self::into_inner_at_end) when the position after
+    /// the XZ stream matters.
     pub fn into_inner(self) -> R {
         self.reader.into_inner()
     }
 
+    /// Consume the XzReader and return the inner reader, guaranteeing that it
+    /// is positioned exactly after the XZ stream's footer.
+    ///
+    /// This only succeeds once the stream has been fully consumed (`read`
+    /// returned `0`). It is useful when an XZ stream is embedded in a larger
+    /// file and the trailing data has to be read afterwards.
+    ///
+    /// Note that with `allow_multiple_streams` the reader scans past the
+    /// footer for the start of another stream, so the position guarantee only
+    /// holds for single-stream readers.
+    pub fn into_inner_at_end(self) -> crate::Result<R> {
+        if !self.finished {
+            return Err(error_other("XZ stream was not fully consumed"));
+        }
+
+        if self.recovery_truncated {
+            return Err(error_other("XZ stream was truncated during recovery"));
+        }
+
+        Ok(self.reader.into_inner())
+    }
+
+    /// Decodes just enough of the stream to fill `out` and stops early,
+    /// without decoding the rest of the stream. Returns the number of bytes
+    /// written, which is only smaller than `out.len()` when the stream ends
+    /// first. Input is consumed at the decoder's chunk granularity: only the
+    /// compressed chunks covering the prefix are read from the inner reader,
+    /// making this suitable for previewing large archives.
+    pub fn decode_prefix(&mut self, out: &mut [u8]) -> Result<usize> {
+        let mut filled = 0;
+
+        while filled < out.len() {
+            match self.read(&mut out[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+
+        Ok(filled)
+    }
+
     /// Returns a reference to the inner reader.
+    ///
+    /// See [`into_inner`](Self::into_inner) for a caveat about the inner
+    /// reader's position during decoding.
     pub fn inner(&self) -> &R {
         self.reader.inner()
     }
 
     /// Returns a mutable reference to the inner reader.
+    ///
+    /// See [`into_inner`](Self::into_inner) for a caveat about the inner
+    /// reader's position during decoding.
     pub fn inner_mut(&mut self) -> &mut R {
         self.reader.inner_mut()
     }
@@ -213,6 +385,33 @@ impl<R: Read> XzReader<R> {
     fn prepare_next_block(&mut self) -> Result<bool> {
         match BlockHeader::parse(&mut self.reader)? {
             Some(block_header) => {
+                if let Some(allowed_filters) = &self.allowed_filters {
+                    for filter in block_header.filters.iter().flatten() {
+                        if !allowed_filters.contains(filter) {
+                            return Err(crate::error_unsupported_filter(
+                                *filter,
+                                "block uses a filter outside the decode allowlist",
+                            ));
+                        }
+                    }
+                }
+
+                if let Some(ref mut callback) = self.on_block_header {
+                    let filters = block_header
+                        .filters
+                        .iter()
+                        .zip(block_header.properties.iter())
+                        .filter_map(|(filter, property)| filter.map(|filter| (filter, *property)))
+                        .collect();
+
+                    callback(&BlockInfo {
+                        filters,
+                        compressed_size: block_header.compressed_size,
+                        uncompressed_size: block_header.uncompressed_size,
+                        index: self.blocks_processed,
+                    });
+                }
+
                 let base_reader: FilterReader<R> =
                     core::mem::replace(&mut self.reader, FilterReader::Dummy);
 
@@ -222,12 +421,25 @@ impl<R: Read> XzReader<R> {
                     &block_header.properties,
                 );
 
+                // When the header declares the compressed size, bound the
+                // reads to it and verify it at the end of the block.
+                self.declared_compressed_size = block_header.compressed_size;
+                self.reader.set_read_limit(block_header.compressed_size);
+                self.current_block_header_size = block_header.header_size as u64;
+                self.current_block_uncompressed = 0;
+
                 match self.stream_header.as_ref() {
                     Some(header) => {
-                        self.checksum_calculator = Some(ChecksumCalculator::new(header.check_type));
+                        self.checksum_calculator = Some(if self.verify_checks {
+                            ChecksumCalculator::new(header.check_type)
+                        } else {
+                            // Updating and verifying become no-ops; the
+                            // check bytes are still consumed below.
+                            ChecksumCalculator::None
+                        });
                     }
                     None => {
-                        panic!("stream_header not set");
+                        return Err(error_other("stream header not set"));
                     }
                 }
 
@@ -249,6 +461,31 @@ impl<R: Read> XzReader<R> {
         }
     }
 
+    /// Verifies the block consumed exactly the compressed size declared in
+    /// its header, when one was declared, and records the block's sizes for
+    /// verification against the index.
+    fn check_declared_compressed_size(&mut self, compressed_bytes: u64) -> Result<()> {
+        if let Some(declared) = self.declared_compressed_size.take() {
+            if compressed_bytes != declared {
+                return Err(error_invalid_data(
+                    "block does not match the declared compressed size",
+                ));
+            }
+        }
+
+        let checksum_size = match self.stream_header.as_ref() {
+            Some(header) => header.check_type.checksum_size(),
+            None => 0,
+        };
+
+        self.seen_records.push(IndexRecord {
+            unpadded_size: self.current_block_header_size + compressed_bytes + checksum_size,
+            uncompressed_size: self.current_block_uncompressed,
+        });
+
+        Ok(())
+    }
+
     fn consume_padding(&mut self, compressed_bytes: u64) -> Result<()> {
         let padding_needed = match (4 - (compressed_bytes % 4)) % 4 {
             0 => return Ok(()),
@@ -274,16 +511,28 @@ impl<R: Read> XzReader<R> {
         let checksum_calculator = self
             .checksum_calculator
             .take()
-            .expect("checksum_calculator not set");
+            .ok_or_else(|| error_other("checksum calculator not set"))?;
 
         match checksum_calculator {
-            ChecksumCalculator::None => { /* Nothing to check */ }
+            ChecksumCalculator::None => {
+                // Nothing to verify, but consume the check bytes of the
+                // stream's check type so the reader stays positioned.
+                let checksum_size = match self.stream_header.as_ref() {
+                    Some(header) => header.check_type.checksum_size() as usize,
+                    None => 0,
+                };
+
+                if checksum_size > 0 {
+                    let mut skipped = [0u8; 32];
+                    self.reader.read_exact(&mut skipped[..checksum_size])?;
+                }
+            }
             ChecksumCalculator::Crc32(_) => {
                 let mut checksum = [0u8; 4];
                 self.reader.read_exact(&mut checksum)?;
 
                 if !checksum_calculator.verify(&checksum) {
-                    return Err(error_invalid_data("invalid block checksum"));
+                    return Err(crate::error_checksum_mismatch("invalid block checksum"));
                 }
             }
             ChecksumCalculator::Crc64(_) => {
@@ -291,15 +540,16 @@ impl<R: Read> XzReader<R> {
                 self.reader.read_exact(&mut checksum)?;
 
                 if !checksum_calculator.verify(&checksum) {
-                    return Err(error_invalid_data("invalid block checksum"));
+                    return Err(crate::error_checksum_mismatch("invalid block checksum"));
                 }
             }
+            #[cfg(feature = "xz-sha256")]
             ChecksumCalculator::Sha256(_) => {
                 let mut checksum = [0u8; 32];
                 self.reader.read_exact(&mut checksum)?;
 
                 if !checksum_calculator.verify(&checksum) {
-                    return Err(error_invalid_data("invalid block checksum"));
+                    return Err(crate::error_checksum_mismatch("invalid block checksum"));
                 }
             }
         }
@@ -330,7 +580,7 @@ impl<R: Read> XzReader<R> {
             }
 
             // Non-zero byte found - check if it starts XZ magic.
-            if byte == XZ_MAGIC[0] {
+            if byte != XZ_MAGIC[0] {
                 return Err(error_invalid_data("invalid data after stream"));
             }
 
@@ -347,7 +597,9 @@ impl<R: Read> XzReader<R> {
                         buffer[buffer_pos] = byte_buffer[0];
                         buffer_pos += 1;
                     }
-                    _ => unreachable!(),
+                    _ => {
+                        return Err(error_other("reader returned more bytes than requested"));
+                    }
                 }
             }
 
@@ -378,9 +630,25 @@ impl<R: Read> XzReader<R> {
             ));
         }
 
+        // Verify every block's sizes against its index record.
+        for (record, seen) in index.records.iter().zip(&self.seen_records) {
+            if record.unpadded_size != seen.unpadded_size
+                || record.uncompressed_size != seen.uncompressed_size
+            {
+                return Err(error_invalid_data(
+                    "block sizes don't match the index records",
+                ));
+            }
+        }
+
+        self.seen_records.clear();
+
         let stream_footer = StreamFooter::parse(&mut self.reader)?;
 
-        let header = self.stream_header.as_ref().expect("stream_header not set");
+        let header = self
+            .stream_header
+            .as_ref()
+            .ok_or_else(|| error_other("stream header not set"))?;
 
         let header_flags = [0, header.check_type as u8];
         if stream_footer.stream_flags != header_flags {
@@ -389,12 +657,192 @@ impl<R: Read> XzReader<R> {
             ));
         }
 
+        // The footer's backward size declares the index size; a mismatch
+        // with the index actually read indicates corruption.
+        let declared_index_size = (u64::from(stream_footer.backward_size) + 1) * 4;
+        if index.size != declared_index_size {
+            return Err(error_invalid_data(
+                "index size doesn't match the footer's backward size",
+            ));
+        }
+
         Ok(())
     }
+
+    /// Decodes the next block completely into `self.recovered`, only keeping
+    /// it once padding and checksum verified. Returns `Ok(false)` once the end
+    /// of the stream is reached.
+    fn recover_next_block(&mut self) -> Result<bool> {
+        if !self.prepare_next_block()? {
+            return Ok(false);
+        }
+
+        let mut block = Vec::new();
+        let mut chunk = [0u8; 8192];
+
+        loop {
+            let bytes_read = self.reader.read(&mut chunk)?;
+
+            if bytes_read == 0 {
+                break;
+            }
+
+            if let Some(ref mut calc) = self.checksum_calculator {
+                calc.update(&chunk[..bytes_read]);
+            }
+
+            self.current_block_uncompressed += bytes_read as u64;
+            block.extend_from_slice(&chunk[..bytes_read]);
+        }
+
+        let reader = core::mem::replace(&mut self.reader, FilterReader::Dummy);
+        let compressed_bytes = reader.bytes_read();
+        self.reader = FilterReader::Counting(CountingReader::with_count(
+            reader.into_inner(),
+            compressed_bytes,
+        ));
+
+        self.check_declared_compressed_size(compressed_bytes)?;
+        self.consume_padding(compressed_bytes)?;
+        self.verify_block_checksum()?;
+
+        self.recovered = block;
+        self.recovered_pos = 0;
+
+        Ok(true)
+    }
+
+    /// `read` in partial recovery mode: hand out bytes of verified blocks and
+    /// turn any error past the stream header into a clean EOF.
+    fn read_partial_recovery(&mut self, buf: &mut [u8]) -> Result<usize> {
+        loop {
+            if self.recovered_pos < self.recovered.len() {
+                let available = &self.recovered[self.recovered_pos..];
+                let bytes_read = available.len().min(buf.len());
+                buf[..bytes_read].copy_from_slice(&available[..bytes_read]);
+                self.recovered_pos += bytes_read;
+                return Ok(bytes_read);
+            }
+
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.ensure_stream_header()?;
+
+            match self.recover_next_block() {
+                Ok(true) => { /* A verified block is buffered now. */ }
+                Ok(false) => self.finished = true,
+                Err(_) => {
+                    // Truncated or corrupt trailing data: discard it and
+                    // report a clean EOF after the blocks salvaged so far.
+                    self.finished = true;
+                    self.recovery_truncated = true;
+                }
+            }
+        }
+    }
+}
+
+/// The position of one block inside a seekable XZ stream, as reported by
+/// [`XzReader::block_layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockLayout {
+    /// The offset of the block in the compressed stream.
+    pub compressed_offset: u64,
+    /// The size of the block in the compressed stream, including its header,
+    /// checksum and padding.
+    pub compressed_size: u64,
+    /// The offset of the block's data in the uncompressed output.
+    pub uncompressed_offset: u64,
+    /// The size of the block's data in the uncompressed output.
+    pub uncompressed_size: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + std::io::Seek> XzReader<R> {
+    /// Reads the stream's index and reports where each block lives, without
+    /// touching any block data.
+    ///
+    /// Useful for partial-fetch clients that want to download and decode
+    /// ranges of a remote XZ file. The inner reader's position is restored
+    /// afterwards, so decoding can continue normally. Only the first stream
+    /// of a multi-stream file is described, and the stream must start at
+    /// position 0 of the seekable source: the layout is anchored there, so
+    /// an XZ stream embedded at a nonzero offset is misreported.
+    pub fn block_layout(&mut self) -> Result<Vec<BlockLayout>> {
+        use std::io::SeekFrom;
+
+        use crate::ByteReader;
+
+        let inner = self.reader.inner_mut();
+        let original_position = inner.stream_position()?;
+
+        let result = (|| {
+            inner.seek(SeekFrom::Start(0))?;
+            StreamHeader::parse(inner)?;
+            let header_end_pos = inner.stream_position()?;
+
+            let file_size = inner.seek(SeekFrom::End(0))?;
+
+            // Minimum XZ file: 12 byte header + 12 byte footer + 8 byte minimum index.
+            if file_size < 32 {
+                return Err(error_invalid_data(
+                    "file too small to contain a valid XZ stream",
+                ));
+            }
+
+            inner.seek(SeekFrom::End(-12))?;
+            let stream_footer = StreamFooter::parse(inner)?;
+
+            let index_size = (stream_footer.backward_size + 1) * 4;
+            let index_start_pos = file_size - 12 - index_size as u64;
+
+            inner.seek(SeekFrom::Start(index_start_pos))?;
+
+            let index_indicator = inner.read_u8()?;
+            if index_indicator != 0 {
+                return Err(error_invalid_data("invalid XZ index indicator"));
+            }
+
+            let index = Index::parse(inner)?;
+
+            let mut layout = Vec::with_capacity(index.records.len());
+            let mut compressed_offset = header_end_pos;
+            let mut uncompressed_offset = 0;
+
+            for record in &index.records {
+                let padding_needed = (4 - (record.unpadded_size % 4)) % 4;
+                let compressed_size = record.unpadded_size + padding_needed;
+
+                layout.push(BlockLayout {
+                    compressed_offset,
+                    compressed_size,
+                    uncompressed_offset,
+                    uncompressed_size: record.uncompressed_size,
+                });
+
+                compressed_offset += compressed_size;
+                uncompressed_offset += record.uncompressed_size;
+            }
+
+            Ok(layout)
+        })();
+
+        self.reader
+            .inner_mut()
+            .seek(SeekFrom::Start(original_position))?;
+
+        result
+    }
 }
 
 impl<R: Read> Read for XzReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.partial_recovery {
+            return self.read_partial_recovery(buf);
+        }
+
         if self.finished {
             return Ok(0);
         }
@@ -410,6 +858,8 @@ impl<R: Read> Read for XzReader<R> {
                         calc.update(&buf[..bytes_read]);
                     }
 
+                    self.current_block_uncompressed += bytes_read as u64;
+
                     return Ok(bytes_read);
                 } else {
                     let reader = core::mem::replace(&mut self.reader, FilterReader::Dummy);
@@ -419,6 +869,7 @@ impl<R: Read> Read for XzReader<R> {
                         compressed_bytes,
                     ));
 
+                    self.check_declared_compressed_size(compressed_bytes)?;
                     self.consume_padding(compressed_bytes)?;
                     self.verify_block_checksum()?;
                 }
@@ -432,3 +883,25 @@ impl<R: Read> Read for XzReader<R> {
         }
     }
 }
+
+/// Decodes a complete XZ stream from a byte slice, never panicking on
+/// malformed input.
+///
+/// Intended for fuzzing and other hostile-input scenarios: any malformed
+/// input results in an error, and the decompressed output is capped at
+/// 256 MiB so crafted inputs (decompression bombs) cannot exhaust memory
+/// through the output buffer. Concatenated streams are accepted.
+pub fn try_decode_xz(data: &[u8]) -> Result<Vec<u8>> {
+    const OUTPUT_CAP: usize = 256 << 20;
+
+    xz_decompress(data, OUTPUT_CAP)
+}
+
+/// Decompresses a complete XZ stream in one call.
+///
+/// `max_output_size` caps the decompressed size to guard against
+/// decompression bombs. Concatenated streams are accepted.
+pub fn xz_decompress(data: &[u8], max_output_size: usize) -> Result<Vec<u8>> {
+    let mut reader = XzReader::new(data, true);
+    crate::read_to_end_capped(&mut reader, max_output_size)
+}
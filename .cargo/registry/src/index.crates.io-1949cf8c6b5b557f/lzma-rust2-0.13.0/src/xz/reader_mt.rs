@@ -9,7 +9,10 @@ use std::{
     thread,
 };
 
-use super::{create_filter_chain, BlockHeader, CheckType, Index, StreamFooter, StreamHeader};
+use super::{
+    create_filter_chain, BlockHeader, CheckType, ChecksumCalculator, Index, StreamFooter,
+    StreamHeader,
+};
 use crate::{
     error_invalid_data, set_error,
     work_queue::{WorkStealingQueue, WorkerHandle},
@@ -25,7 +28,23 @@ struct XZBlock {
 
 /// A work unit for a worker thread.
 /// Contains the sequence number and block data.
-type WorkUnit = (u64, Vec<u8>);
+type WorkUnit = (u64, BlockData);
+
+/// The bytes of one block, either copied from the reader or borrowed from a
+/// shared in-memory source.
+enum BlockData {
+    Owned(Vec<u8>),
+    Shared(Arc<dyn AsRef<[u8]> + Send + Sync>, core::ops::Range<usize>),
+}
+
+impl BlockData {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            BlockData::Owned(data) => data,
+            BlockData::Shared(source, range) => &(**source).as_ref()[range.clone()],
+        }
+    }
+}
 
 /// A result unit from a worker thread.
 /// Contains the sequence number and the decompressed data.
@@ -43,6 +62,10 @@ enum State {
 }
 
 /// A multi-threaded XZ decompressor.
+///
+/// Dropping the reader before the end of the stream is cheap: the work
+/// queue is closed and the worker threads shut down promptly without
+/// decoding the remaining blocks.
 pub struct XzReaderMt<R: Read + Seek> {
     inner: Option<R>,
     blocks: Vec<XZBlock>,
@@ -62,6 +85,72 @@ pub struct XzReaderMt<R: Read + Seek> {
     max_workers: u32,
     worker_handles: Vec<thread::JoinHandle<()>>,
     allow_multiple_streams: bool,
+    shared_source: Option<Arc<dyn AsRef<[u8]> + Send + Sync>>,
+    verify_only: bool,
+    max_buffered_results: Option<usize>,
+}
+
+/// A `Read + Seek` view over shared in-memory bytes, as used by
+/// [`XzReaderMt::new_shared`].
+pub struct SharedSlice {
+    data: Arc<dyn AsRef<[u8]> + Send + Sync>,
+    position: u64,
+}
+
+impl Read for SharedSlice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = (*self.data).as_ref();
+        let position = (self.position as usize).min(data.len());
+        let available = &data[position..];
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for SharedSlice {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let len = (*self.data).as_ref().len() as u64;
+        let new_position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match new_position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(error_invalid_data("seek before start of the source")),
+        }
+    }
+}
+
+impl XzReaderMt<SharedSlice> {
+    /// Creates a multi-threaded XZ reader over shared in-memory bytes, for
+    /// example a memory-mapped file wrapped in an [`Arc`].
+    ///
+    /// Unlike [`new`](Self::new), the workers receive sub-ranges of the
+    /// shared source instead of per-block copies, so no compressed bytes
+    /// are copied during decoding.
+    pub fn new_shared<T: AsRef<[u8]> + Send + Sync + 'static>(
+        data: Arc<T>,
+        allow_multiple_streams: bool,
+        num_workers: u32,
+    ) -> io::Result<Self> {
+        let source: Arc<dyn AsRef<[u8]> + Send + Sync> = data;
+        let cursor = SharedSlice {
+            data: Arc::clone(&source),
+            position: 0,
+        };
+
+        let mut reader = Self::new(cursor, allow_multiple_streams, num_workers)?;
+        reader.shared_source = Some(source);
+
+        Ok(reader)
+    }
 }
 
 impl<R: Read + Seek> XzReaderMt<R> {
@@ -69,9 +158,10 @@ impl<R: Read + Seek> XzReaderMt<R> {
     ///
     /// - `inner`: The reader to read compressed data from. Must implement Seek.
     /// - `allow_multiple_streams`: Whether to allow reading multiple XZ streams concatenated together.
-    /// - `num_workers`: The maximum number of worker threads for decompression. Currently capped at 256 Threads.
+    /// - `num_workers`: The maximum number of worker threads for decompression. Pass `0` to
+    ///   use all available cores. Currently capped at 256 Threads.
     pub fn new(inner: R, allow_multiple_streams: bool, num_workers: u32) -> io::Result<Self> {
-        let max_workers = num_workers.clamp(1, 256);
+        let max_workers = crate::resolve_workers(num_workers);
 
         let work_queue = WorkStealingQueue::new();
         let (result_tx, result_rx) = mpsc::sync_channel::<ResultUnit>(1);
@@ -98,6 +188,9 @@ impl<R: Read + Seek> XzReaderMt<R> {
             max_workers,
             worker_handles: Vec::new(),
             allow_multiple_streams,
+            shared_source: None,
+            verify_only: false,
+            max_buffered_results: None,
         };
 
         reader.scan_blocks()?;
@@ -184,12 +277,14 @@ impl<R: Read + Seek> XzReaderMt<R> {
         let error_store = Arc::clone(&self.error_store);
         let active_workers = Arc::clone(&self.active_workers);
         let check_type = self.check_type;
+        let verify_only = self.verify_only;
 
         let handle = thread::spawn(move || {
             worker_thread_logic(
                 worker_handle,
                 result_tx,
                 check_type,
+                verify_only,
                 shutdown_flag,
                 error_store,
                 active_workers,
@@ -204,6 +299,43 @@ impl<R: Read + Seek> XzReaderMt<R> {
         self.blocks.len()
     }
 
+    /// Caps how many completed-but-unconsumed result blocks are buffered.
+    ///
+    /// When the cap is reached, no further blocks are dispatched until the
+    /// consumer drains results, bounding peak memory for a fast producer
+    /// feeding a slow consumer. The default is unbounded.
+    pub fn with_max_buffered_results(mut self, max_buffered_results: Option<usize>) -> Self {
+        self.max_buffered_results = max_buffered_results;
+        self
+    }
+
+    /// Whether the out-of-order result buffer has reached its cap.
+    fn results_buffer_full(&self) -> bool {
+        self.max_buffered_results
+            .is_some_and(|limit| self.out_of_order_chunks.len() >= limit)
+    }
+
+    /// Verifies the integrity of every block in parallel without returning
+    /// the decompressed data, and returns the total uncompressed size.
+    ///
+    /// Each worker decompresses its block and checks it against the block
+    /// checksum as usual, but sends back only the success signal, skipping
+    /// the copies to the caller. Corruption is reported with the failing
+    /// block's index, like in regular multithreaded decoding.
+    pub fn verify(mut self) -> io::Result<u64> {
+        self.verify_only = true;
+
+        let total_uncompressed = self
+            .blocks
+            .iter()
+            .map(|block| block.uncompressed_size)
+            .sum();
+
+        while self.get_next_uncompressed_chunk()?.is_some() {}
+
+        Ok(total_uncompressed)
+    }
+
     fn dispatch_next_block(&mut self) -> io::Result<bool> {
         let block_index = self.next_sequence_to_dispatch as usize;
 
@@ -213,17 +345,48 @@ impl<R: Read + Seek> XzReaderMt<R> {
         }
 
         let block = &self.blocks[block_index];
-        let mut reader = self.inner.take().expect("inner reader not set");
-
-        reader.seek(SeekFrom::Start(block.start_pos))?;
 
         let padding_needed = (4 - (block.unpadded_size % 4)) % 4;
         let total_block_size = block.unpadded_size + padding_needed;
 
-        let mut block_data = vec![0u8; total_block_size as usize];
-        reader.read_exact(&mut block_data)?;
+        let block_data = if let Some(source) = &self.shared_source {
+            // The source is in memory: hand the workers a sub-range instead
+            // of copying the block.
+            let start = block.start_pos as usize;
+            let end = start + total_block_size as usize;
 
-        self.inner = Some(reader);
+            if (**source).as_ref().get(start..end).is_none() {
+                return Err(error_invalid_data("XZ block range outside the source"));
+            }
+
+            BlockData::Shared(Arc::clone(source), start..end)
+        } else {
+            let mut reader = self.inner.take().expect("inner reader not set");
+
+            reader.seek(SeekFrom::Start(block.start_pos))?;
+
+            let mut block_data = vec![0u8; total_block_size as usize];
+            reader.read_exact(&mut block_data)?;
+
+            self.inner = Some(reader);
+
+            BlockData::Owned(block_data)
+        };
+
+        // A single-block stream cannot be parallelized: decode it inline on
+        // the calling thread and skip the worker/channel setup entirely.
+        if self.blocks.len() == 1 {
+            let mut decompressed = decompress_xz_block(block_data.bytes(), self.check_type)?;
+
+            if self.verify_only {
+                decompressed = Vec::new();
+            }
+
+            self.out_of_order_chunks
+                .insert(self.next_sequence_to_dispatch, decompressed);
+            self.next_sequence_to_dispatch += 1;
+            return Ok(true);
+        }
 
         if !self
             .work_queue
@@ -257,6 +420,26 @@ impl<R: Read + Seek> XzReaderMt<R> {
         Ok(true)
     }
 
+    /// Waits for the next result, waking up periodically to notice worker
+    /// errors. A worker that hits a decode error stores it and exits without
+    /// sending a result, so a plain blocking `recv` would deadlock.
+    fn recv_result(&self) -> Result<ResultUnit, mpsc::RecvTimeoutError> {
+        loop {
+            match self
+                .result_rx
+                .recv_timeout(core::time::Duration::from_millis(50))
+            {
+                Ok(unit) => return Ok(unit),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if self.shutdown_flag.load(Ordering::Acquire) {
+                        return Err(mpsc::RecvTimeoutError::Timeout);
+                    }
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
     fn get_next_uncompressed_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
         loop {
             // Always check for already-received chunks first.
@@ -298,8 +481,9 @@ impl<R: Read + Seek> XzReaderMt<R> {
                         }
                     }
 
-                    // If the work queue has capacity, try to read more from the source.
-                    if self.work_queue.is_empty() {
+                    // If the work queue has capacity and the consumer is
+                    // keeping up, try to read more from the source.
+                    if self.work_queue.is_empty() && !self.results_buffer_full() {
                         match self.dispatch_next_block() {
                             Ok(true) => {
                                 // Successfully read and dispatched a block, loop to continue.
@@ -322,7 +506,7 @@ impl<R: Read + Seek> XzReaderMt<R> {
                     }
 
                     // Now we MUST wait for a result to make progress.
-                    match self.result_rx.recv() {
+                    match self.recv_result() {
                         Ok((seq, result)) => {
                             if seq == self.next_sequence_to_return {
                                 self.next_sequence_to_return += 1;
@@ -334,6 +518,11 @@ impl<R: Read + Seek> XzReaderMt<R> {
                             }
                         }
                         Err(_) => {
+                            if self.shutdown_flag.load(Ordering::Acquire) {
+                                self.state = State::Error;
+                                continue;
+                            }
+
                             // All workers are done.
                             self.state = State::Draining;
                         }
@@ -348,7 +537,7 @@ impl<R: Read + Seek> XzReaderMt<R> {
                     }
 
                     // In Draining state, we only wait for results.
-                    match self.result_rx.recv() {
+                    match self.recv_result() {
                         Ok((seq, result)) => {
                             if seq == self.next_sequence_to_return {
                                 self.next_sequence_to_return += 1;
@@ -358,6 +547,11 @@ impl<R: Read + Seek> XzReaderMt<R> {
                             }
                         }
                         Err(_) => {
+                            if self.shutdown_flag.load(Ordering::Acquire) {
+                                self.state = State::Error;
+                                continue;
+                            }
+
                             // All workers finished, and channel is empty. We are done.
                             self.state = State::Finished;
                         }
@@ -382,6 +576,7 @@ fn worker_thread_logic(
     worker_handle: WorkerHandle<WorkUnit>,
     result_tx: SyncSender<ResultUnit>,
     check_type: CheckType,
+    verify_only: bool,
     shutdown_flag: Arc<AtomicBool>,
     error_store: Arc<Mutex<Option<io::Error>>>,
     active_workers: Arc<AtomicU32>,
@@ -398,10 +593,18 @@ fn worker_thread_logic(
             }
         };
 
-        let result = decompress_xz_block(work_unit_data, check_type);
+        let result = decompress_xz_block(work_unit_data.bytes(), check_type);
 
         match result {
             Ok(decompressed_data) => {
+                // In verify-only mode the data was checked and is not needed;
+                // send just the completion signal.
+                let decompressed_data = if verify_only {
+                    Vec::new()
+                } else {
+                    decompressed_data
+                };
+
                 if result_tx.send((seq, decompressed_data)).is_err() {
                     active_workers.fetch_sub(1, Ordering::Release);
                     return;
@@ -409,7 +612,11 @@ fn worker_thread_logic(
             }
             Err(error) => {
                 active_workers.fetch_sub(1, Ordering::Release);
-                set_error(error, &error_store, &shutdown_flag);
+                set_error(
+                    crate::error_with_block_context(error, seq),
+                    &error_store,
+                    &shutdown_flag,
+                );
                 return;
             }
         }
@@ -419,28 +626,39 @@ fn worker_thread_logic(
 }
 
 /// Decompresses a single XZ block by parsing the header and applying filters directly.
-fn decompress_xz_block(block_data: Vec<u8>, check_type: CheckType) -> io::Result<Vec<u8>> {
-    let (filters, properties, header_size) = BlockHeader::parse_from_slice(&block_data)?;
+pub(super) fn decompress_xz_block(block_data: &[u8], check_type: CheckType) -> io::Result<Vec<u8>> {
+    let (filters, properties, header_size) = BlockHeader::parse_from_slice(block_data)?;
 
     let checksum_size = check_type.checksum_size() as usize;
-    let padding_in_block_data = (4 - (block_data.len() % 4)) % 4;
-    let unpadded_size_in_data = block_data.len() - padding_in_block_data;
-    let compressed_data_end = unpadded_size_in_data - checksum_size;
 
-    if compressed_data_end <= header_size {
+    if block_data.len() < header_size + checksum_size + 1 {
         return Err(error_invalid_data(
             "Block data too short for compressed content",
         ));
     }
 
-    let compressed_data = block_data[header_size..compressed_data_end].to_vec();
+    let check_start = block_data.len() - checksum_size;
+    let compressed_data = block_data[header_size..check_start].to_vec();
     let mut compressed_data = compressed_data.as_slice();
 
-    let base_reader: Box<dyn Read> = Box::new(&mut compressed_data);
-    let mut chain_reader = create_filter_chain(base_reader, &filters, &properties);
-
     let mut decompressed_data = Vec::new();
-    chain_reader.read_to_end(&mut decompressed_data)?;
+    {
+        let base_reader: Box<dyn Read> = Box::new(&mut compressed_data);
+        let mut chain_reader = create_filter_chain(base_reader, &filters, &properties);
+        chain_reader.read_to_end(&mut decompressed_data)?;
+    }
+
+    // Whatever the filter chain left unconsumed must be the 0-3 bytes of
+    // zero padding that align the block to 4 bytes.
+    if compressed_data.len() > 3 || compressed_data.iter().any(|byte| *byte != 0) {
+        return Err(error_invalid_data("invalid block padding"));
+    }
+
+    let mut checksum_calculator = ChecksumCalculator::new(check_type);
+    checksum_calculator.update(&decompressed_data);
+    if !checksum_calculator.verify(&block_data[check_start..]) {
+        return Err(crate::error_checksum_mismatch("invalid block checksum"));
+    }
 
     Ok(decompressed_data)
 }
@@ -0,0 +1,286 @@
+use std::{
+    io::{self, Cursor, Read},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc::SyncSender,
+        Arc, Mutex,
+    },
+};
+
+use super::{
+    reader_mt::decompress_xz_block, BlockHeader, CheckType, Index, StreamFooter, StreamHeader,
+    XZ_MAGIC,
+};
+use crate::{
+    error_invalid_data, error_unsupported, set_error,
+    work_pool::{WorkPool, WorkPoolConfig, WorkPoolState},
+    work_queue::WorkerHandle,
+};
+
+/// A work unit for a worker thread: the raw bytes of one complete block and
+/// the check type of its stream.
+type WorkUnit = (Vec<u8>, CheckType);
+
+/// A multi-threaded XZ decompressor for non-seekable sources.
+///
+/// Unlike [`XzReaderMt`](crate::XzReaderMt), this reader does not require
+/// `Seek` and never looks at the stream index: it reads blocks sequentially
+/// from the stream and dispatches each fully-read block to the worker pool as
+/// it arrives, which enables multithreaded decoding over pipes and sockets.
+///
+/// This only works when the block headers carry the compressed size, which is
+/// the case for streams produced by multithreaded encoders (including `xz -T`)
+/// and by this crate's [`XzWriterMt`](crate::XzWriterMt). Streams without the
+/// compressed size in their block headers report an error; decode those with
+/// [`XzReader`](crate::XzReader) instead.
+///
+/// Dropping the reader before the end of the stream is cheap: the work
+/// queue is closed and the worker threads shut down promptly without
+/// decoding the remaining blocks.
+pub struct XzReaderMtStream<R: Read> {
+    scanner: BlockScanner<R>,
+    work_pool: WorkPool<WorkUnit, Vec<u8>>,
+    current_chunk: Cursor<Vec<u8>>,
+}
+
+/// Sequentially scans blocks out of the raw stream, so the work pool's
+/// dispatch closure can pull new work while `work_pool` is borrowed.
+struct BlockScanner<R: Read> {
+    inner: R,
+    check_type: CheckType,
+    allow_multiple_streams: bool,
+    blocks_processed: u64,
+}
+
+impl<R: Read> XzReaderMtStream<R> {
+    /// Creates a new multi-threaded streaming XZ reader.
+    ///
+    /// - `inner`: The reader to read compressed data from.
+    /// - `allow_multiple_streams`: Whether to allow reading multiple XZ streams concatenated together.
+    /// - `num_workers`: The maximum number of worker threads for decompression. Pass `0` to
+    ///   use all available cores. Currently capped at 256 threads.
+    pub fn new(inner: R, allow_multiple_streams: bool, num_workers: u32) -> io::Result<Self> {
+        Self::construct(inner, allow_multiple_streams, num_workers, None)
+    }
+
+    /// Like [`new`](Self::new), but decompresses on threads borrowed from
+    /// `shared_pool` instead of spawning its own. See
+    /// [`SharedWorkerPool`](crate::SharedWorkerPool) for pool sizing.
+    pub fn new_with_pool(
+        inner: R,
+        allow_multiple_streams: bool,
+        num_workers: u32,
+        shared_pool: std::sync::Arc<crate::SharedWorkerPool>,
+    ) -> io::Result<Self> {
+        Self::construct(
+            inner,
+            allow_multiple_streams,
+            num_workers,
+            Some(shared_pool),
+        )
+    }
+
+    fn construct(
+        mut inner: R,
+        allow_multiple_streams: bool,
+        num_workers: u32,
+        shared_pool: Option<std::sync::Arc<crate::SharedWorkerPool>>,
+    ) -> io::Result<Self> {
+        let stream_header = StreamHeader::parse(&mut inner)?;
+        let check_type = stream_header.check_type;
+
+        Ok(Self {
+            scanner: BlockScanner {
+                inner,
+                check_type,
+                allow_multiple_streams,
+                blocks_processed: 0,
+            },
+            work_pool: WorkPool::new(
+                {
+                    let mut config = WorkPoolConfig::new(num_workers, u64::MAX);
+                    config.shared_pool = shared_pool;
+                    config
+                },
+                worker_thread_logic,
+            ),
+            current_chunk: Cursor::new(Vec::new()),
+        })
+    }
+
+    /// The count of XZ blocks read from the stream so far.
+    pub fn block_count(&self) -> u64 {
+        self.scanner.blocks_processed
+    }
+
+    fn get_next_uncompressed_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if matches!(self.work_pool.state(), WorkPoolState::Finished) {
+            return Ok(None);
+        }
+
+        let scanner = &mut self.scanner;
+
+        self.work_pool.get_result(|_| scanner.next_block())
+    }
+}
+
+impl<R: Read> BlockScanner<R> {
+    /// Reads the next complete block from the stream, or `None` once the
+    /// index has been reached and (for multi-stream sources) no further
+    /// stream follows.
+    fn next_block(&mut self) -> io::Result<Option<WorkUnit>> {
+        loop {
+            let mut size_buf = [0u8; 1];
+            self.inner.read_exact(&mut size_buf)?;
+
+            if size_buf[0] != 0x00 {
+                // A block follows.
+                let header_size = (size_buf[0] as usize + 1) * 4;
+                if !(8..=1024).contains(&header_size) {
+                    return Err(error_invalid_data("invalid XZ block header size"));
+                }
+
+                let mut block_data = vec![0u8; header_size];
+                block_data[0] = size_buf[0];
+                self.inner.read_exact(&mut block_data[1..])?;
+
+                let header = BlockHeader::parse(&mut &block_data[..])?
+                    .ok_or_else(|| error_invalid_data("invalid XZ block header"))?;
+
+                let compressed_size = header.compressed_size.ok_or_else(|| {
+                    error_unsupported(
+                        "streaming multithreaded decoding needs the compressed size in the block header",
+                    )
+                })?;
+
+                let checksum_size = self.check_type.checksum_size();
+                let unpadded_size = header_size as u64 + compressed_size + checksum_size;
+                let padding_needed = (4 - (unpadded_size % 4)) % 4;
+                let rest_size = compressed_size + checksum_size + padding_needed;
+
+                let start = block_data.len();
+                block_data.resize(start + rest_size as usize, 0);
+                self.inner.read_exact(&mut block_data[start..])?;
+
+                self.blocks_processed += 1;
+
+                return Ok(Some((block_data, self.check_type)));
+            }
+
+            // End of blocks: validate index and footer. The end-of-blocks
+            // marker byte has already been consumed above.
+            let index = Index::parse(&mut self.inner)?;
+
+            if index.number_of_records != self.blocks_processed {
+                return Err(error_invalid_data(
+                    "number of blocks processed doesn't match index records",
+                ));
+            }
+
+            StreamFooter::parse(&mut self.inner)?;
+
+            if self.allow_multiple_streams && self.try_start_next_stream()? {
+                self.blocks_processed = 0;
+                continue;
+            }
+
+            return Ok(None);
+        }
+    }
+
+    /// Skips stream padding and starts the next concatenated stream, if any.
+    fn try_start_next_stream(&mut self) -> io::Result<bool> {
+        let mut padding_bytes = 0usize;
+
+        loop {
+            let mut byte_buf = [0u8; 1];
+
+            if self.inner.read(&mut byte_buf)? == 0 {
+                return Ok(false);
+            }
+
+            if byte_buf[0] == 0x00 {
+                padding_bytes += 1;
+                continue;
+            }
+
+            if padding_bytes % 4 != 0 {
+                return Err(error_invalid_data("stream padding size not multiple of 4"));
+            }
+
+            let mut magic = [0u8; 6];
+            magic[0] = byte_buf[0];
+            self.inner.read_exact(&mut magic[1..])?;
+
+            if magic != XZ_MAGIC {
+                return Err(error_invalid_data("invalid data after stream padding"));
+            }
+
+            let stream_header = StreamHeader::parse_stream_header_flags_and_crc(&mut self.inner)?;
+            self.check_type = stream_header.check_type;
+
+            return Ok(true);
+        }
+    }
+}
+
+impl<R: Read> Read for XzReaderMtStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let bytes_read = self.current_chunk.read(buf)?;
+
+            if bytes_read > 0 {
+                return Ok(bytes_read);
+            }
+
+            match self.get_next_uncompressed_chunk()? {
+                Some(chunk) => {
+                    self.current_chunk = Cursor::new(chunk);
+                }
+                None => {
+                    return Ok(0);
+                }
+            }
+        }
+    }
+}
+
+/// The logic for a single worker thread.
+fn worker_thread_logic(
+    worker_handle: WorkerHandle<(u64, WorkUnit)>,
+    result_tx: SyncSender<(u64, Vec<u8>)>,
+    shutdown_flag: Arc<AtomicBool>,
+    error_store: Arc<Mutex<Option<io::Error>>>,
+    active_workers: Arc<AtomicU32>,
+) {
+    while !shutdown_flag.load(Ordering::Acquire) {
+        let (index, (block_data, check_type)) = match worker_handle.steal() {
+            Some(work) => {
+                active_workers.fetch_add(1, Ordering::Release);
+                work
+            }
+            None => {
+                // No more work available and queue is closed.
+                break;
+            }
+        };
+
+        let result = decompress_xz_block(&block_data, check_type);
+
+        match result {
+            Ok(decompressed_data) => {
+                if result_tx.send((index, decompressed_data)).is_err() {
+                    active_workers.fetch_sub(1, Ordering::Release);
+                    return;
+                }
+            }
+            Err(error) => {
+                active_workers.fetch_sub(1, Ordering::Release);
+                set_error(error, &error_store, &shutdown_flag);
+                return;
+            }
+        }
+
+        active_workers.fetch_sub(1, Ordering::Release);
+    }
+}
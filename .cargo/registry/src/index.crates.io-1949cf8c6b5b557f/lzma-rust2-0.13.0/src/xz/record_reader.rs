@@ -0,0 +1,215 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::{reader_mt::decompress_xz_block, CheckType, Index, StreamFooter, StreamHeader};
+use crate::{error_invalid_data, error_invalid_input, ByteReader};
+
+#[derive(Debug, Clone)]
+struct RecordBlock {
+    start_pos: u64,
+    unpadded_size: u64,
+    uncompressed_start: u64,
+    uncompressed_size: u64,
+}
+
+/// Random access to logical records inside a seekable XZ stream.
+///
+/// Producers that concatenate logical files into one XZ stream and track the
+/// uncompressed boundaries out-of-band can use this to extract each record
+/// separately. The stream's index is scanned once, and every record is then
+/// decoded on demand starting at the block containing its first byte, so
+/// records in a multi-block stream don't pay for decoding the whole stream.
+///
+/// `boundaries` holds the uncompressed start offset of every record; each
+/// record ends where the next one starts, the last one at the end of the
+/// stream.
+pub struct XzRecordReader<R: Read + Seek> {
+    inner: R,
+    blocks: Vec<RecordBlock>,
+    check_type: CheckType,
+    boundaries: Vec<u64>,
+    total_uncompressed_size: u64,
+}
+
+impl<R: Read + Seek> XzRecordReader<R> {
+    /// Creates a new [`XzRecordReader`] for a single-stream XZ file.
+    pub fn new(mut inner: R, boundaries: Vec<u64>) -> io::Result<Self> {
+        let stream_header = StreamHeader::parse(&mut inner)?;
+        let check_type = stream_header.check_type;
+
+        let header_end_pos = inner.stream_position()?;
+        let file_size = inner.seek(SeekFrom::End(0))?;
+
+        if file_size < 32 {
+            return Err(error_invalid_data(
+                "File too small to contain a valid XZ stream",
+            ));
+        }
+
+        inner.seek(SeekFrom::End(-12))?;
+        let stream_footer = StreamFooter::parse(&mut inner)?;
+
+        let index_size = (stream_footer.backward_size + 1) * 4;
+        let index_start_pos = file_size - 12 - index_size as u64;
+
+        inner.seek(SeekFrom::Start(index_start_pos))?;
+
+        let index_indicator = inner.read_u8()?;
+        if index_indicator != 0 {
+            return Err(error_invalid_data("invalid XZ index indicator"));
+        }
+
+        let index = Index::parse(&mut inner)?;
+
+        let mut blocks = Vec::with_capacity(index.records.len());
+        let mut block_start_pos = header_end_pos;
+        let mut uncompressed_start = 0;
+
+        for record in &index.records {
+            blocks.push(RecordBlock {
+                start_pos: block_start_pos,
+                unpadded_size: record.unpadded_size,
+                uncompressed_start,
+                uncompressed_size: record.uncompressed_size,
+            });
+
+            let padding_needed = (4 - (record.unpadded_size % 4)) % 4;
+            block_start_pos += record.unpadded_size + padding_needed;
+            uncompressed_start += record.uncompressed_size;
+        }
+
+        let total_uncompressed_size = uncompressed_start;
+
+        for window in boundaries.windows(2) {
+            if window[0] > window[1] {
+                return Err(error_invalid_input("record boundaries must be sorted"));
+            }
+        }
+
+        if let Some(&last) = boundaries.last() {
+            if last > total_uncompressed_size {
+                return Err(error_invalid_input(
+                    "record boundary past the end of the stream",
+                ));
+            }
+        }
+
+        Ok(Self {
+            inner,
+            blocks,
+            check_type,
+            boundaries,
+            total_uncompressed_size,
+        })
+    }
+
+    /// The number of records.
+    pub fn record_count(&self) -> usize {
+        self.boundaries.len()
+    }
+
+    /// Returns a reader over the uncompressed bytes of record `index`.
+    ///
+    /// Blocks are decoded one at a time as the record is read, so peak memory
+    /// is one uncompressed block regardless of record size.
+    pub fn record(&mut self, index: usize) -> io::Result<XzRecord<'_, R>> {
+        let start = *self
+            .boundaries
+            .get(index)
+            .ok_or_else(|| error_invalid_input("record index out of range"))?;
+        let end = self
+            .boundaries
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.total_uncompressed_size);
+
+        Ok(XzRecord {
+            parent: self,
+            position: start,
+            end,
+            block_data: Vec::new(),
+            block_offset: 0,
+        })
+    }
+
+    /// Consume the XzRecordReader and return the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Decodes the stream's last block, returning its uncompressed bytes
+    /// and their uncompressed start offset.
+    pub(super) fn load_last_block(&mut self) -> io::Result<(Vec<u8>, u64)> {
+        let start = self
+            .blocks
+            .last()
+            .map(|block| block.uncompressed_start)
+            .ok_or_else(|| error_invalid_data("XZ stream holds no blocks"))?;
+
+        self.load_block_at(start)
+    }
+
+    /// Replaces the record boundaries after construction.
+    pub(super) fn set_boundaries(&mut self, boundaries: Vec<u64>) {
+        self.boundaries = boundaries;
+    }
+
+    /// Decodes the block containing the uncompressed offset `position`.
+    fn load_block_at(&mut self, position: u64) -> io::Result<(Vec<u8>, u64)> {
+        let block = self
+            .blocks
+            .iter()
+            .find(|block| {
+                position >= block.uncompressed_start
+                    && position < block.uncompressed_start + block.uncompressed_size
+            })
+            .cloned()
+            .ok_or_else(|| error_invalid_data("no block covers the requested offset"))?;
+
+        self.inner.seek(SeekFrom::Start(block.start_pos))?;
+
+        let padding_needed = (4 - (block.unpadded_size % 4)) % 4;
+        let mut block_data = vec![0u8; (block.unpadded_size + padding_needed) as usize];
+        self.inner.read_exact(&mut block_data)?;
+
+        let decompressed = decompress_xz_block(&block_data, self.check_type)?;
+
+        Ok((decompressed, block.uncompressed_start))
+    }
+}
+
+/// A reader over one record of an [`XzRecordReader`].
+pub struct XzRecord<'a, R: Read + Seek> {
+    parent: &'a mut XzRecordReader<R>,
+    position: u64,
+    end: u64,
+    block_data: Vec<u8>,
+    block_offset: u64,
+}
+
+impl<R: Read + Seek> Read for XzRecord<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.end || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let in_block = (self.position - self.block_offset) as usize;
+
+        if self.block_data.is_empty() || in_block >= self.block_data.len() {
+            let (block_data, block_offset) = self.parent.load_block_at(self.position)?;
+            self.block_data = block_data;
+            self.block_offset = block_offset;
+        }
+
+        let in_block = (self.position - self.block_offset) as usize;
+        let available = (self.end - self.position) as usize;
+        let bytes_read = buf
+            .len()
+            .min(available)
+            .min(self.block_data.len() - in_block);
+
+        buf[..bytes_read].copy_from_slice(&self.block_data[in_block..in_block + bytes_read]);
+        self.position += bytes_read as u64;
+
+        Ok(bytes_read)
+    }
+}
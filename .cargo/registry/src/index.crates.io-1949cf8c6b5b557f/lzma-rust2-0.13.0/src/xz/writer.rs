@@ -3,8 +3,11 @@ use core::num::NonZeroU64;
 
 use super::{
     add_padding, write_xz_block_header, write_xz_index, write_xz_stream_footer,
-    write_xz_stream_header, CheckType, ChecksumCalculator, FilterConfig, FilterType, IndexRecord,
+    write_xz_stream_header, CheckType, ChecksumCalculator, Filter, FilterConfig, FilterType,
+    IndexRecord,
 };
+#[cfg(feature = "std")]
+use super::{Index, StreamFooter, StreamHeader};
 use crate::{
     enc::{Lzma2Writer, LzmaOptions},
     error_invalid_data, error_invalid_input,
@@ -162,6 +165,16 @@ impl<W: Write> FilterWriter<W> {
         }
     }
 
+    fn bytes_written(&self) -> u64 {
+        match self {
+            FilterWriter::Counting(writer) => writer.bytes_written(),
+            FilterWriter::LZMA2(writer) => writer.inner().bytes_written(),
+            FilterWriter::Delta(writer) => writer.inner().bytes_written(),
+            FilterWriter::Bcj(writer) => writer.inner().bytes_written(),
+            FilterWriter::Dummy => unimplemented!(),
+        }
+    }
+
     fn finish(self) -> Result<CountingWriter<W>> {
         match self {
             FilterWriter::Counting(writer) => Ok(writer),
@@ -194,6 +207,16 @@ pub struct XzOptions {
     pub block_size: Option<NonZeroU64>,
     /// Pre-filter to use (at most 3).
     pub filters: Vec<FilterConfig>,
+    /// Store blocks uncompressed when compression does not help.
+    pub store_incompressible: bool,
+    /// Memory budget in KiB for the multithreaded writer's workers.
+    pub max_total_memory: Option<u64>,
+    /// Spill threshold in bytes for the multithreaded writer's reorder
+    /// buffer (only effective with the `spill-to-disk` feature).
+    pub spill_threshold: Option<u64>,
+    /// Zero padding in bytes written between concatenated streams by
+    /// [`XzMultiStreamWriter`]. Must be a multiple of 4.
+    pub stream_padding: u32,
 }
 
 impl Default for XzOptions {
@@ -203,6 +226,10 @@ impl Default for XzOptions {
             check_type: CheckType::Crc32,
             block_size: None,
             filters: Vec::new(),
+            store_incompressible: false,
+            max_total_memory: None,
+            spill_threshold: None,
+            stream_padding: 0,
         }
     }
 }
@@ -215,6 +242,10 @@ impl XzOptions {
             check_type: CheckType::Crc64,
             block_size: None,
             filters: Vec::new(),
+            store_incompressible: false,
+            max_total_memory: None,
+            spill_threshold: None,
+            stream_padding: 0,
         }
     }
 
@@ -228,6 +259,64 @@ impl XzOptions {
         self.block_size = block_size;
     }
 
+    /// Set the match finder used by the LZMA2 encoder, forwarding to
+    /// [`lzma_options`](Self::lzma_options).
+    ///
+    /// [`MfType::Hc4`](crate::MfType::Hc4) uses hash chains: faster, but it
+    /// settles for the first acceptable match, costing some ratio.
+    /// [`MfType::Bt4`](crate::MfType::Bt4) uses binary trees: slower, but it
+    /// searches more thoroughly for the best match. The presets pick Hc4 up
+    /// to preset 3 and Bt4 from preset 4 on.
+    pub fn set_match_finder(&mut self, mf: crate::MfType) {
+        self.lzma_options.mf = mf;
+    }
+
+    /// Set the nice length of a match, forwarding to
+    /// [`LzmaOptions::set_nice_len`](crate::LzmaOptions::set_nice_len).
+    pub fn set_nice_len(&mut self, nice_len: u32) {
+        self.lzma_options.set_nice_len(nice_len);
+    }
+
+    /// Set the zero padding in bytes written between concatenated streams by
+    /// [`XzMultiStreamWriter`]. The XZ format requires stream padding to be
+    /// a multiple of 4 bytes; other values are rejected when the writer is
+    /// created.
+    pub fn set_stream_padding(&mut self, stream_padding: u32) {
+        self.stream_padding = stream_padding;
+    }
+
+    /// Store blocks uncompressed when compression does not help.
+    ///
+    /// When enabled, [`XzWriter`] buffers each block in memory and compares
+    /// the compressed size against storing the data as uncompressed LZMA2
+    /// chunks. Whichever is smaller is written, so incompressible input can
+    /// no longer expand beyond the small chunk framing overhead.
+    ///
+    /// This costs memory proportional to the block size (or the whole input
+    /// if no block size is set). It only takes effect when no pre-filters
+    /// are configured, since pre-filtered data cannot be stored raw.
+    pub fn set_store_incompressible(&mut self, store_incompressible: bool) {
+        self.store_incompressible = store_incompressible;
+    }
+
+    /// Caps the total memory of [`XzWriterMt`](crate::XzWriterMt)'s workers,
+    /// in KiB. See
+    /// [`Lzma2Options::set_max_total_memory`](crate::Lzma2Options::set_max_total_memory).
+    pub fn set_max_total_memory(&mut self, max_total_memory: Option<u64>) {
+        self.max_total_memory = max_total_memory;
+    }
+
+    /// Spills completed-but-not-yet-ordered blocks of
+    /// [`XzWriterMt`](crate::XzWriterMt) to temporary files once the
+    /// in-memory reorder buffer exceeds `spill_threshold` bytes.
+    ///
+    /// This bounds peak memory for very large multithreaded jobs at the cost
+    /// of temporary disk I/O when workers finish far out of order. Only
+    /// effective when the `spill-to-disk` feature is enabled.
+    pub fn set_spill_threshold(&mut self, spill_threshold: Option<u64>) {
+        self.spill_threshold = spill_threshold;
+    }
+
     /// Prepend a filter to the chain. You can prepend at most 3 additional filter.
     pub fn prepend_pre_filter(&mut self, filter_type: FilterType, property: u32) {
         self.filters.insert(
@@ -238,6 +327,130 @@ impl XzOptions {
             },
         );
     }
+
+    /// Prepend a filter to the chain using the typed [`Filter`] description.
+    /// You can prepend at most 3 additional filter.
+    pub fn prepend_filter(&mut self, filter: Filter) {
+        self.filters.insert(0, filter.into());
+    }
+}
+
+/// Builds a validated XZ pre-filter chain for [`XzOptions`].
+///
+/// Centralizes the chain rules that are otherwise only caught when the
+/// writer starts: at most 3 pre-filters, no duplicate filter types, delta
+/// distances within `[1, 256]`. LZMA2 is always implied as the final filter
+/// and must not be added explicitly.
+///
+/// ```
+/// use lzma_rust2::{XzFilterChainBuilder, XzOptions};
+///
+/// let mut options = XzOptions::with_preset(6);
+/// options.filters = XzFilterChainBuilder::new()
+///     .add_delta(4)
+///     .add_bcj_x86(0)
+///     .finish()
+///     .unwrap();
+/// ```
+#[derive(Default)]
+pub struct XzFilterChainBuilder {
+    filters: Vec<Filter>,
+}
+
+impl XzFilterChainBuilder {
+    /// Creates an empty filter chain builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a typed filter to the chain.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Adds a delta filter with the given distance.
+    pub fn add_delta(self, distance: u32) -> Self {
+        self.add(Filter::Delta { distance })
+    }
+
+    /// Adds a BCJ x86 filter with the given start offset.
+    pub fn add_bcj_x86(self, start: u32) -> Self {
+        self.add(Filter::BcjX86 { start })
+    }
+
+    /// Adds a BCJ ARM filter with the given start offset.
+    pub fn add_bcj_arm(self, start: u32) -> Self {
+        self.add(Filter::BcjARM { start })
+    }
+
+    /// Adds a BCJ ARM64 filter with the given start offset.
+    pub fn add_bcj_arm64(self, start: u32) -> Self {
+        self.add(Filter::BcjARM64 { start })
+    }
+
+    /// Adds a BCJ ARM Thumb filter with the given start offset.
+    pub fn add_bcj_arm_thumb(self, start: u32) -> Self {
+        self.add(Filter::BcjARMThumb { start })
+    }
+
+    /// Adds a BCJ PowerPC filter with the given start offset.
+    pub fn add_bcj_ppc(self, start: u32) -> Self {
+        self.add(Filter::BcjPPC { start })
+    }
+
+    /// Adds a BCJ IA-64 filter with the given start offset.
+    pub fn add_bcj_ia64(self, start: u32) -> Self {
+        self.add(Filter::BcjIA64 { start })
+    }
+
+    /// Adds a BCJ SPARC filter with the given start offset.
+    pub fn add_bcj_sparc(self, start: u32) -> Self {
+        self.add(Filter::BcjSPARC { start })
+    }
+
+    /// Adds a BCJ RISC-V filter with the given start offset.
+    pub fn add_bcj_riscv(self, start: u32) -> Self {
+        self.add(Filter::BcjRISCV { start })
+    }
+
+    /// Validates the chain and produces the filter configurations for
+    /// [`XzOptions::filters`].
+    pub fn finish(self) -> Result<Vec<FilterConfig>> {
+        if self.filters.len() > 3 {
+            return Err(error_invalid_input(
+                "XZ allows only at most 3 pre-filters plus LZMA2",
+            ));
+        }
+
+        let mut configs: Vec<FilterConfig> = Vec::with_capacity(self.filters.len());
+
+        for filter in &self.filters {
+            if matches!(filter, Filter::Lzma2 { .. }) {
+                return Err(error_invalid_input(
+                    "LZMA2 is implied as the final filter and cannot be added",
+                ));
+            }
+
+            let config: FilterConfig = (*filter).into();
+
+            if configs
+                .iter()
+                .any(|earlier| earlier.filter_type == config.filter_type)
+            {
+                return Err(error_invalid_input(
+                    "duplicate filter type in the XZ filter chain",
+                ));
+            }
+
+            configs.push(config);
+        }
+
+        super::validate_pre_filters(&configs)?;
+
+        Ok(configs)
+    }
 }
 
 /// A single-threaded XZ compressor.
@@ -252,6 +465,7 @@ pub struct XzWriter<W: Write> {
     total_uncompressed_pos: u64,
     current_block_start_pos: u64,
     current_block_header_size: u64,
+    raw_block_buf: Vec<u8>,
 }
 
 impl<W: Write> XzWriter<W> {
@@ -265,6 +479,8 @@ impl<W: Write> XzWriter<W> {
             ));
         }
 
+        super::validate_pre_filters(&options.filters)?;
+
         if let Some(block_size) = options.block_size.as_mut() {
             *block_size =
                 NonZeroU64::new(block_size.get().max(options.lzma_options.dict_size as u64))
@@ -291,6 +507,7 @@ impl<W: Write> XzWriter<W> {
             total_uncompressed_pos: 0,
             current_block_start_pos: 0,
             current_block_header_size: 0,
+            raw_block_buf: Vec::new(),
         })
     }
 
@@ -335,6 +552,7 @@ impl<W: Write> XzWriter<W> {
             &mut self.writer,
             &self.options.filters,
             self.options.lzma_options.dict_size,
+            None,
         )?;
 
         let writer = core::mem::replace(&mut self.writer, FilterWriter::Dummy);
@@ -342,17 +560,30 @@ impl<W: Write> XzWriter<W> {
         let bytes_written = counting_writer.bytes_written();
         self.current_block_start_pos = bytes_written;
 
-        self.writer = FilterWriter::create_filter_chain(
-            counting_writer,
-            &self.options.filters,
-            &self.options.lzma_options,
-        )?;
+        if self.buffers_blocks() {
+            // The block payload is buffered raw and encoded at block end, so
+            // no filter chain is needed.
+            self.writer = FilterWriter::Counting(counting_writer);
+        } else {
+            self.writer = FilterWriter::create_filter_chain(
+                counting_writer,
+                &self.options.filters,
+                &self.options.lzma_options,
+            )?;
+        }
 
         self.block_uncompressed_size = 0;
 
         Ok(())
     }
 
+    /// Whether blocks are buffered to decide between compressed and stored
+    /// encoding. Pre-filtered data cannot be stored raw, so buffering is only
+    /// active for a pure LZMA2 filter chain.
+    fn buffers_blocks(&self) -> bool {
+        self.options.store_incompressible && self.options.filters.len() == 1
+    }
+
     fn should_finish_block(&self) -> bool {
         if let Some(block_size) = self.options.block_size {
             self.block_uncompressed_size >= block_size.get()
@@ -362,6 +593,10 @@ impl<W: Write> XzWriter<W> {
     }
 
     fn finish_current_block(&mut self) -> Result<()> {
+        if self.buffers_blocks() {
+            return self.finish_current_block_buffered();
+        }
+
         // Finish the filter chain and get back to the counting writer.
         let writer = core::mem::replace(&mut self.writer, FilterWriter::Dummy);
         let counting_writer = writer.finish()?;
@@ -391,6 +626,75 @@ impl<W: Write> XzWriter<W> {
         Ok(())
     }
 
+    /// Encodes the buffered block either LZMA2-compressed or as uncompressed
+    /// LZMA2 chunks, whichever is smaller.
+    fn finish_current_block_buffered(&mut self) -> Result<()> {
+        const CHUNK_SIZE_MAX: usize = 64 << 10;
+
+        let raw = core::mem::take(&mut self.raw_block_buf);
+
+        let mut compressed = Vec::new();
+
+        {
+            let options = Lzma2Options {
+                lzma_options: self.options.lzma_options.clone(),
+                chunk_size: None,
+                low_latency: false,
+                max_total_memory: None,
+                force_compressed_chunks: false,
+                reset_every_n_chunks: None,
+                max_output: None,
+                skip_incompressible: false,
+            };
+            let mut writer = Lzma2Writer::new(&mut compressed, options);
+            writer.write_all(&raw)?;
+            writer.finish()?;
+        }
+
+        // Storing raw costs a 3 byte header per 64 KiB chunk plus the end
+        // marker byte.
+        let stored_size = raw.len() + 3 * raw.len().div_ceil(CHUNK_SIZE_MAX) + 1;
+
+        let payload = if compressed.len() <= stored_size {
+            compressed
+        } else {
+            let mut stored = Vec::with_capacity(stored_size);
+
+            for (index, chunk) in raw.chunks(CHUNK_SIZE_MAX).enumerate() {
+                // The first chunk resets the dictionary (0x01), the rest
+                // continue without reset (0x02).
+                stored.push(if index == 0 { 0x01 } else { 0x02 });
+                stored.extend_from_slice(&((chunk.len() - 1) as u16).to_be_bytes());
+                stored.extend_from_slice(chunk);
+            }
+
+            stored.push(0x00);
+            stored
+        };
+
+        self.writer.write_all(&payload)?;
+
+        let block_compressed_size = payload.len() as u64;
+        let padding_needed = (4 - (block_compressed_size % 4)) % 4;
+
+        add_padding(&mut self.writer, padding_needed as usize)?;
+
+        self.write_block_checksum()?;
+
+        let unpadded_size = self.current_block_header_size
+            + block_compressed_size
+            + self.options.check_type.checksum_size();
+
+        self.index_records.push(IndexRecord {
+            unpadded_size,
+            uncompressed_size: self.block_uncompressed_size,
+        });
+
+        self.block_uncompressed_size = 0;
+
+        Ok(())
+    }
+
     fn get_block_header_size(&self, _compressed_size: u64, _uncompressed_size: u64) -> u64 {
         // Block header: size_byte(1) + flags(1) + filter_id(1) + props_size(1)
         // + dict_prop(1) + padding + crc32(4)
@@ -416,14 +720,58 @@ impl<W: Write> XzWriter<W> {
         calculator.finalize_to_bytes()
     }
 
+    /// Ends the current block, writing its padding, checksum and index
+    /// record, so everything written so far forms a fully decodable
+    /// boundary (the single-threaded analogue of
+    /// [`XzWriterMt::flush_block`](crate::XzWriterMt::flush_block)).
+    ///
+    /// A new block starts with the next write. Does nothing when no data is
+    /// buffered in the current block.
+    pub fn flush_block(&mut self) -> Result<()> {
+        if self.block_uncompressed_size == 0 {
+            return Ok(());
+        }
+
+        self.finish_current_block()
+    }
+
+    /// Drains the LZMA2 encoder without ending the block, the analogue of
+    /// zlib's `Z_SYNC_FLUSH`.
+    ///
+    /// Every byte written so far becomes part of a completed LZMA2 chunk, so
+    /// a chunk-level reader can decode the partial block up to this point.
+    /// Unlike [`flush_block`](Self::flush_block) this does not write the
+    /// block checksum, so the XZ container machinery cannot validate the
+    /// partial block yet. This is also what `flush` from the `Write` trait
+    /// does, followed by flushing the inner writer.
+    pub fn sync_flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+
     /// Finish writing the XZ stream and return the inner writer.
-    pub fn finish(mut self) -> Result<W> {
+    pub fn finish(self) -> Result<W> {
+        Ok(self.finish_with_stats()?.0)
+    }
+
+    /// Finish writing the XZ stream, returning the inner writer along with
+    /// the total uncompressed and compressed byte counts.
+    pub fn finish_with_stats(mut self) -> Result<(W, crate::CompressionStats)> {
         if self.finished {
-            return Ok(self.into_inner());
+            let stats = crate::CompressionStats {
+                uncompressed_in: self.total_uncompressed_pos,
+                compressed_out: self.writer.bytes_written(),
+            };
+            return Ok((self.into_inner(), stats));
         }
 
         self.write_stream_header()?;
-        self.finish_current_block()?;
+
+        // Nothing written since the last block ended (or at all): there is
+        // no block in progress to finish, and trying to finish one would
+        // emit a bogus index record.
+        if self.block_uncompressed_size > 0 || !self.raw_block_buf.is_empty() {
+            self.finish_current_block()?;
+        }
 
         write_xz_index(&mut self.writer, &self.index_records)?;
 
@@ -433,7 +781,72 @@ impl<W: Write> XzWriter<W> {
             self.options.check_type,
         )?;
 
-        Ok(self.into_inner())
+        let stats = crate::CompressionStats {
+            uncompressed_in: self.total_uncompressed_pos,
+            compressed_out: self.writer.bytes_written(),
+        };
+
+        Ok((self.into_inner(), stats))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Read + Write + std::io::Seek> XzWriter<W> {
+    /// Opens an existing XZ stream for appending new blocks without
+    /// recompressing the existing ones.
+    ///
+    /// The stream's index and footer are parsed, the writer is positioned
+    /// right after the last existing block, and [`finish`](Self::finish)
+    /// writes a merged index covering both the old and the newly appended
+    /// blocks. The check type of the existing stream is adopted, overriding
+    /// whatever `options` requests.
+    pub fn append_to(mut inner: W, mut options: XzOptions) -> Result<Self> {
+        use std::io::SeekFrom;
+
+        use crate::ByteReader;
+
+        let stream_header = StreamHeader::parse(&mut inner)?;
+        options.check_type = stream_header.check_type;
+
+        let file_size = inner.seek(SeekFrom::End(0))?;
+
+        // Minimum XZ file: 12 byte header + 12 byte footer + 8 byte minimum index.
+        if file_size < 32 {
+            return Err(error_invalid_data(
+                "file too small to contain a valid XZ stream",
+            ));
+        }
+
+        inner.seek(SeekFrom::End(-12))?;
+        let stream_footer = StreamFooter::parse(&mut inner)?;
+
+        let header_flags = [0, stream_header.check_type as u8];
+        if stream_footer.stream_flags != header_flags {
+            return Err(error_invalid_data(
+                "stream header and footer flags mismatch",
+            ));
+        }
+
+        let index_size = (stream_footer.backward_size + 1) * 4;
+        let index_start_pos = file_size - 12 - index_size as u64;
+
+        inner.seek(SeekFrom::Start(index_start_pos))?;
+
+        let index_indicator = inner.read_u8()?;
+        if index_indicator != 0 {
+            return Err(error_invalid_data("invalid XZ index indicator"));
+        }
+
+        let index = Index::parse(&mut inner)?;
+
+        // New blocks overwrite the old index; it is rewritten merged on finish.
+        inner.seek(SeekFrom::Start(index_start_pos))?;
+
+        let mut writer = Self::new(inner, options)?;
+        writer.header_written = true;
+        writer.index_records = index.records;
+
+        Ok(writer)
     }
 }
 
@@ -475,7 +888,13 @@ impl<W: Write> Write for XzWriter<W> {
             }
 
             let chunk_to_write = &remaining[..max_write_size];
-            let written = self.writer.write(chunk_to_write)?;
+
+            let written = if self.buffers_blocks() {
+                self.raw_block_buf.extend_from_slice(chunk_to_write);
+                chunk_to_write.len()
+            } else {
+                self.writer.write(chunk_to_write)?
+            };
 
             self.checksum_calculator.update(&remaining[..written]);
 
@@ -488,6 +907,24 @@ impl<W: Write> Write for XzWriter<W> {
         Ok(total_written)
     }
 
+    /// Compresses all buffers in order, so callers using vectored I/O don't
+    /// fall back to one `write` call per slice.
+    #[cfg(feature = "std")]
+    fn write_vectored(&mut self, bufs: &[std::io::IoSlice<'_>]) -> Result<usize> {
+        let mut total_written = 0;
+
+        for buf in bufs {
+            let bytes_written = self.write(buf)?;
+            total_written += bytes_written;
+
+            if bytes_written < buf.len() {
+                break;
+            }
+        }
+
+        Ok(total_written)
+    }
+
     fn flush(&mut self) -> Result<()> {
         self.writer.flush()
     }
@@ -498,6 +935,15 @@ impl<W: Write> Write for XzWriter<W> {
 /// This can be created by the [`XzWriter::auto_finish`] method.
 pub struct AutoFinishXzWriter<W: Write>(Option<XzWriter<W>>);
 
+impl<W: Write> AutoFinishXzWriter<W> {
+    /// Finishes the stream explicitly, returning the inner writer and
+    /// making finish errors observable. The drop-based finish, which
+    /// swallows errors, only runs when this was not called.
+    pub fn finish(mut self) -> Result<W> {
+        self.0.take().expect("writer not set").finish()
+    }
+}
+
 impl<W: Write> Drop for AutoFinishXzWriter<W> {
     fn drop(&mut self) {
         if let Some(writer) = self.0.take() {
@@ -515,3 +961,104 @@ impl<W: Write> Write for AutoFinishXzWriter<W> {
         self.0.as_mut().unwrap().flush()
     }
 }
+
+/// Compresses `data` into a complete XZ stream in one call.
+pub fn xz_compress(data: &[u8], options: &XzOptions) -> Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = XzWriter::new(&mut compressed, options.clone())?;
+        writer.write_all(data)?;
+        writer.finish()?;
+    }
+
+    Ok(compressed)
+}
+
+/// A writer that splits its input into multiple concatenated XZ streams.
+///
+/// Unlike multi-block output (one stream, many blocks), every `stream_size`
+/// bytes of input become a complete XZ stream with its own header, index and
+/// footer, so each slice is independently decodable by tools that split
+/// files at stream boundaries. Decode the result with
+/// [`XzReader`](crate::XzReader) with `allow_multiple_streams` enabled.
+pub struct XzMultiStreamWriter<W: Write> {
+    writer: Option<XzWriter<W>>,
+    options: XzOptions,
+    stream_size: u64,
+    current_stream_size: u64,
+}
+
+impl<W: Write> XzMultiStreamWriter<W> {
+    /// Creates a new multi-stream writer that starts a new XZ stream after
+    /// every `stream_size` bytes of input.
+    pub fn new(inner: W, options: XzOptions, stream_size: NonZeroU64) -> Result<Self> {
+        if options.stream_padding % 4 != 0 {
+            return Err(error_invalid_input(
+                "stream padding must be a multiple of 4 bytes",
+            ));
+        }
+
+        Ok(Self {
+            writer: Some(XzWriter::new(inner, options.clone())?),
+            options,
+            stream_size: stream_size.get(),
+            current_stream_size: 0,
+        })
+    }
+
+    /// Finishes the current stream, writes the configured stream padding
+    /// and starts the next one.
+    fn finish_stream(&mut self) -> Result<()> {
+        let writer = self.writer.take().expect("writer not set");
+        let mut inner = writer.finish()?;
+
+        // Not add_padding: that helper only covers the 1-3 byte block
+        // padding, while stream padding can be any multiple of 4.
+        const ZEROS: [u8; 64] = [0; 64];
+        let mut remaining = self.options.stream_padding as usize;
+        while remaining > 0 {
+            let chunk = remaining.min(ZEROS.len());
+            inner.write_all(&ZEROS[..chunk])?;
+            remaining -= chunk;
+        }
+
+        self.writer = Some(XzWriter::new(inner, self.options.clone())?);
+        self.current_stream_size = 0;
+
+        Ok(())
+    }
+
+    /// Finishes the final stream and returns the inner writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.take().expect("writer not set").finish()
+    }
+}
+
+impl<W: Write> Write for XzMultiStreamWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let mut total_written = 0;
+        let mut remaining = buf;
+
+        while !remaining.is_empty() {
+            if self.current_stream_size >= self.stream_size {
+                self.finish_stream()?;
+            }
+
+            let capacity = (self.stream_size - self.current_stream_size) as usize;
+            let chunk = &remaining[..remaining.len().min(capacity)];
+
+            let written = self.writer.as_mut().expect("writer not set").write(chunk)?;
+
+            self.current_stream_size += written as u64;
+            total_written += written;
+            remaining = &remaining[written..];
+        }
+
+        Ok(total_written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.as_mut().expect("writer not set").flush()
+    }
+}
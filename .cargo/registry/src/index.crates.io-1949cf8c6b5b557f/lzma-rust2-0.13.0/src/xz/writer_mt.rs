@@ -35,12 +35,56 @@ struct ResultUnit {
     uncompressed_size: u64,
 }
 
+#[cfg(feature = "spill-to-disk")]
+impl crate::work_pool::Spill for ResultUnit {
+    fn spill_to(&self, writer: &mut dyn std::io::Write) -> io::Result<()> {
+        writer.write_all(&(self.compressed_data.len() as u64).to_le_bytes())?;
+        writer.write_all(&self.compressed_data)?;
+        writer.write_all(&(self.checksum.len() as u64).to_le_bytes())?;
+        writer.write_all(&self.checksum)?;
+        writer.write_all(&self.uncompressed_size.to_le_bytes())
+    }
+
+    fn restore_from(reader: &mut dyn std::io::Read) -> io::Result<Self> {
+        let mut len_buf = [0u8; 8];
+
+        reader.read_exact(&mut len_buf)?;
+        let mut compressed_data = vec![0u8; u64::from_le_bytes(len_buf) as usize];
+        reader.read_exact(&mut compressed_data)?;
+
+        reader.read_exact(&mut len_buf)?;
+        let mut checksum = vec![0u8; u64::from_le_bytes(len_buf) as usize];
+        reader.read_exact(&mut checksum)?;
+
+        reader.read_exact(&mut len_buf)?;
+        let uncompressed_size = u64::from_le_bytes(len_buf);
+
+        Ok(Self {
+            compressed_data,
+            checksum,
+            uncompressed_size,
+        })
+    }
+
+    fn spill_size(&self) -> usize {
+        self.compressed_data.len() + self.checksum.len()
+    }
+}
+
 /// A multi-threaded XZ compressor.
+///
+/// The output is deterministic: for the same input, options and sequence of
+/// `write`/`flush` calls, the produced bytes are identical regardless of
+/// worker count or thread scheduling. Block boundaries depend only on
+/// `block_size` and the input length (`flush` additionally ends the current
+/// block early), and blocks are written out strictly in input order.
 pub struct XzWriterMt<W: Write> {
     inner: W,
     options: XzOptions,
     current_work_unit: Vec<u8>,
     block_size: usize,
+    max_block_size: Option<usize>,
+    auto_hold_remaining: u32,
     work_pool: WorkPool<WorkUnit, ResultUnit>,
     index_records: Vec<IndexRecord>,
     checksum_calculator: ChecksumCalculator,
@@ -54,15 +98,62 @@ impl<W: Write> XzWriterMt<W> {
     /// - `inner`: The writer to write compressed data to.
     /// - `options`: The XZ options used for compressing. Block size must be set when using the
     ///   multi-threaded encoder. If you need just one block, then use the single-threaded encoder.
-    /// - `num_workers`: The maximum number of worker threads for compression.
-    ///   Currently capped at 256 threads.
+    /// - `num_workers`: The maximum number of worker threads for compression. Pass `0` to
+    ///   use all available cores. Currently capped at 256 threads.
     pub fn new(inner: W, options: XzOptions, num_workers: u32) -> Result<Self> {
+        Self::construct(inner, options, num_workers, None)
+    }
+
+    /// Like [`new`](Self::new), but borrows worker threads from a shared
+    /// pool instead of spawning its own, avoiding per-instance thread churn
+    /// when compressing many files sequentially. See
+    /// [`SharedWorkerPool`](crate::SharedWorkerPool) for how to size the
+    /// pool when several instances use it concurrently.
+    pub fn new_with_pool(
+        inner: W,
+        options: XzOptions,
+        num_workers: u32,
+        shared_pool: std::sync::Arc<crate::SharedWorkerPool>,
+    ) -> Result<Self> {
+        Self::construct(inner, options, num_workers, Some(shared_pool))
+    }
+
+    /// Creates a multi-threaded XZ writer that auto-tunes its block size,
+    /// for use when the input size is not known up front.
+    ///
+    /// The policy starts with one dictionary size per block and holds that
+    /// size until `num_workers` blocks have been dispatched, so any input
+    /// larger than `num_workers` dictionaries splits into at least
+    /// `num_workers` blocks. After that it doubles the block size per
+    /// dispatched block up to eight dictionary sizes, recovering
+    /// compression ratio on large inputs while keeping per-block memory
+    /// bounded.
+    pub fn new_auto(inner: W, preset: u32, num_workers: u32) -> Result<Self> {
+        let mut options = XzOptions::with_preset(preset);
+        let dict_size = options.lzma_options.dict_size as u64;
+        options.set_block_size(core::num::NonZeroU64::new(dict_size));
+
+        let mut writer = Self::construct(inner, options, num_workers, None)?;
+        writer.max_block_size = Some((dict_size as usize).saturating_mul(8));
+        writer.auto_hold_remaining = crate::resolve_workers(num_workers);
+
+        Ok(writer)
+    }
+
+    fn construct(
+        inner: W,
+        options: XzOptions,
+        num_workers: u32,
+        shared_pool: Option<std::sync::Arc<crate::SharedWorkerPool>>,
+    ) -> Result<Self> {
         if options.filters.len() > 3 {
             return Err(error_invalid_input(
                 "XZ allows only at most 3 pre-filters plus LZMA2",
             ));
         }
 
+        super::validate_pre_filters(&options.filters)?;
+
         let block_size = match options.block_size {
             None => return Err(error_invalid_input("block size must be set")),
             Some(block_size) => block_size.get().max(options.lzma_options.dict_size as u64),
@@ -71,7 +162,19 @@ impl<W: Write> XzWriterMt<W> {
         let block_size = usize::try_from(block_size)
             .map_err(|_| error_invalid_input("block size bigger than usize"))?;
 
+        // Apply the memory budget by capping the worker count.
+        let num_workers = match options.max_total_memory {
+            Some(budget_kb) => crate::enc::workers_for_memory_budget(
+                &options.lzma_options,
+                crate::resolve_workers(num_workers),
+                budget_kb,
+            ),
+            None => num_workers,
+        };
+
         let checksum_calculator = ChecksumCalculator::new(options.check_type);
+        #[cfg(feature = "spill-to-disk")]
+        let spill_threshold = options.spill_threshold;
 
         // We don't know how many work units we'll have ahead of time.
         let num_work = u64::MAX;
@@ -81,8 +184,18 @@ impl<W: Write> XzWriterMt<W> {
             options,
             current_work_unit: Vec::with_capacity(block_size.min(1024 * 1024)),
             block_size,
+            max_block_size: None,
+            auto_hold_remaining: 0,
             work_pool: WorkPool::new(
-                WorkPoolConfig::new(num_workers, num_work),
+                {
+                    let mut config = WorkPoolConfig::new(num_workers, num_work);
+                    config.shared_pool = shared_pool;
+                    #[cfg(feature = "spill-to-disk")]
+                    {
+                        config.spill_threshold = spill_threshold;
+                    }
+                    config
+                },
                 worker_thread_logic,
             ),
             index_records: Vec::new(),
@@ -103,7 +216,11 @@ impl<W: Write> XzWriterMt<W> {
         Ok(())
     }
 
-    fn write_block_header(&mut self, _block_uncompressed_size: u64) -> Result<u64> {
+    fn write_block_header(
+        &mut self,
+        block_compressed_size: u64,
+        block_uncompressed_size: u64,
+    ) -> Result<u64> {
         // Add LZMA2 filter to the list
         let mut filters = self.options.filters.clone();
         filters.push(FilterConfig {
@@ -111,13 +228,28 @@ impl<W: Write> XzWriterMt<W> {
             property: 0,
         });
 
+        // Declaring the sizes lets [`XzReaderMtStream`](super::XzReaderMtStream)
+        // split the stream into blocks without seeking, like `xz -T` output.
         write_xz_block_header(
             &mut self.inner,
             &filters,
             self.options.lzma_options.dict_size,
+            Some((block_compressed_size, block_uncompressed_size)),
         )
     }
 
+    /// Dispatches the buffered partial block to the workers and starts a
+    /// new block, without waiting for a full `block_size` of input.
+    ///
+    /// This lets consumers decode the flushed blocks early in streaming
+    /// scenarios. Block order and index records follow the input order, and
+    /// [`finish`](Self::finish) works as usual after manual flushes. Flushing
+    /// with no buffered data is a no-op.
+    pub fn flush_block(&mut self) -> Result<()> {
+        self.send_work_unit()?;
+        self.drain_available_results()
+    }
+
     /// Sends the current work unit to the workers.
     fn send_work_unit(&mut self) -> Result<()> {
         if self.current_work_unit.is_empty() {
@@ -136,13 +268,23 @@ impl<W: Write> XzWriterMt<W> {
             let data = work_data_opt.take().ok_or_else(|| {
                 io::Error::new(io::ErrorKind::InvalidInput, "work already provided")
             })?;
-            Ok(WorkUnit {
+            Ok(Some(WorkUnit {
                 uncompressed_data: data,
                 lzma_options: self.options.lzma_options.clone(),
                 check_type: self.options.check_type,
-            })
+            }))
         })?;
 
+        // In auto mode, grow the block size towards the cap so large inputs
+        // regain compression ratio, but only once every worker has a block.
+        if let Some(max_block_size) = self.max_block_size {
+            if self.auto_hold_remaining > 0 {
+                self.auto_hold_remaining -= 1;
+            } else {
+                self.block_size = self.block_size.saturating_mul(2).min(max_block_size);
+            }
+        }
+
         self.drain_available_results()?;
 
         Ok(())
@@ -166,9 +308,9 @@ impl<W: Write> XzWriterMt<W> {
         checksum: Vec<u8>,
         block_uncompressed_size: u64,
     ) -> Result<()> {
-        let block_header_size = self.write_block_header(block_uncompressed_size)?;
-
         let data_size = compressed_data.len() as u64;
+        let block_header_size = self.write_block_header(data_size, block_uncompressed_size)?;
+
         let padding_needed = (4 - (data_size % 4)) % 4;
 
         self.inner.write_all(&compressed_data)?;
@@ -235,12 +377,7 @@ impl<W: Write> XzWriterMt<W> {
         self.work_pool.finish();
 
         // Wait for all remaining work to complete.
-        while let Some(result) = self.work_pool.get_result(|_| {
-            Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "no more work to dispatch",
-            ))
-        })? {
+        while let Some(result) = self.work_pool.get_result(|_| Ok(None))? {
             self.write_compressed_block(
                 result.compressed_data,
                 result.checksum,
@@ -372,6 +509,15 @@ impl<W: Write> Write for XzWriterMt<W> {
 /// This can be created by the [`XzWriterMt::auto_finish`] method.
 pub struct AutoFinishXzWriterMt<W: Write>(Option<XzWriterMt<W>>);
 
+impl<W: Write> AutoFinishXzWriterMt<W> {
+    /// Finishes the stream explicitly, returning the inner writer and
+    /// making finish errors observable. The drop-based finish, which
+    /// swallows errors, only runs when this was not called.
+    pub fn finish(mut self) -> Result<W> {
+        self.0.take().expect("writer not set").finish()
+    }
+}
+
 impl<W: Write> Drop for AutoFinishXzWriterMt<W> {
     fn drop(&mut self) {
         if let Some(writer) = self.0.take() {
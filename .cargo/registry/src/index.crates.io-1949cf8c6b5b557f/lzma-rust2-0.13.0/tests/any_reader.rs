@@ -0,0 +1,101 @@
+use std::io::{Read, Write};
+
+use lzma_rust2::{AnyReader, Format, LzipOptions, LzipWriter, XzOptions, XzWriter};
+
+#[test]
+fn dispatches_each_format() {
+    let data = b"auto dispatch sample".repeat(300);
+
+    // XZ
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+    let mut reader = AnyReader::new(compressed.as_slice()).unwrap();
+    assert_eq!(reader.format(), Format::Xz);
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+
+    // LZIP
+    let mut compressed = Vec::new();
+    {
+        let mut writer = LzipWriter::new(&mut compressed, LzipOptions::with_preset(1));
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+    let mut reader = AnyReader::new(compressed.as_slice()).unwrap();
+    assert_eq!(reader.format(), Format::Lzip);
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+
+    // LZMA alone, via liblzma's encoder.
+    let mut compressed = Vec::new();
+    {
+        use liblzma::{bufread::XzEncoder, stream};
+        let options = stream::LzmaOptions::new_preset(1).unwrap();
+        let stream = stream::Stream::new_lzma_encoder(&options).unwrap();
+        let mut encoder = XzEncoder::new_stream(data.as_slice(), stream);
+        encoder.read_to_end(&mut compressed).unwrap();
+    }
+    let mut reader = AnyReader::new(compressed.as_slice()).unwrap();
+    assert_eq!(reader.format(), Format::LzmaAlone);
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn unknown_magic_is_rejected() {
+    let error = match AnyReader::new(&b"definitely not compressed"[..]) {
+        Ok(_) => panic!("unknown magic was accepted"),
+        Err(error) => error,
+    };
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn heterogeneous_concatenation_decodes_as_one() {
+    let first = b"first stream, in xz".repeat(300);
+    let second = b"second stream, in lzip".repeat(300);
+
+    let mut file = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut file, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(&first).unwrap();
+        writer.finish().unwrap();
+    }
+    {
+        let mut writer = LzipWriter::new(&mut file, LzipOptions::with_preset(1));
+        writer.write_all(&second).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut reader = AnyReader::new_multi(file.as_slice()).unwrap();
+    assert_eq!(reader.format(), Format::Xz);
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+
+    let mut expected = first.clone();
+    expected.extend_from_slice(&second);
+    assert!(uncompressed == expected);
+    assert_eq!(reader.format(), Format::Lzip);
+
+    // Without multi mode, the XZ reader treats the LZIP stream as invalid
+    // trailing data, matching its strict behavior for concatenated input.
+    let mut reader = AnyReader::new(file.as_slice()).unwrap();
+    let mut uncompressed = Vec::new();
+    let error = reader.read_to_end(&mut uncompressed).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    // Unrecognized trailing data reports an error in multi mode.
+    let mut garbage = file.clone();
+    garbage.extend_from_slice(b"GARBAGE FOLLOWS HERE");
+    let mut reader = AnyReader::new_multi(garbage.as_slice()).unwrap();
+    let mut uncompressed = Vec::new();
+    let error = reader.read_to_end(&mut uncompressed).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
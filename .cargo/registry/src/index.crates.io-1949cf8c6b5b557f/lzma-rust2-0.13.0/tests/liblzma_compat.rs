@@ -0,0 +1,163 @@
+#![cfg(feature = "liblzma-compat-tests")]
+
+use std::io::{Read, Write};
+
+use liblzma::{bufread::XzEncoder, read::XzDecoder, stream};
+use lzma_rust2::{Filter, XzOptions, XzReader, XzWriter};
+
+/// Compresses with our `XzWriter` using `filter` and decompresses with
+/// liblzma, then compresses with liblzma using `liblzma_filter` and
+/// decompresses with our `XzReader`, asserting byte-exact round trips.
+fn cross_check(
+    path: &str,
+    filter: Filter,
+    liblzma_filter: impl Fn(&mut stream::Filters) -> &mut stream::Filters,
+) {
+    let data = std::fs::read(path).unwrap();
+
+    // Our encoder, their decoder.
+    let mut option = XzOptions::with_preset(1);
+    option.prepend_filter(filter);
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut liblzma_uncompressed = Vec::new();
+    let mut decoder = XzDecoder::new(compressed.as_slice());
+    decoder.read_to_end(&mut liblzma_uncompressed).unwrap();
+
+    // We don't use assert_eq since the debug output would be too big.
+    assert!(liblzma_uncompressed == data);
+
+    // Their encoder, our decoder.
+    let lzma_options = stream::LzmaOptions::new_preset(1).unwrap();
+    let mut filters = stream::Filters::new();
+    liblzma_filter(&mut filters);
+    filters.lzma2(&lzma_options);
+
+    let stream = stream::Stream::new_stream_encoder(&filters, stream::Check::Crc32).unwrap();
+    let mut liblzma_compressed = Vec::new();
+    let mut encoder = XzEncoder::new_stream(data.as_slice(), stream);
+    encoder.read_to_end(&mut liblzma_compressed).unwrap();
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(liblzma_compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn cross_check_delta() {
+    cross_check(
+        "tests/data/executable.exe",
+        Filter::Delta { distance: 4 },
+        |f| f.delta_properties(&[0x03]).unwrap(),
+    );
+}
+
+#[test]
+fn cross_check_bcj_x86() {
+    cross_check("tests/data/wget-x86", Filter::BcjX86 { start: 0 }, |f| {
+        f.x86()
+    });
+}
+
+#[test]
+fn cross_check_bcj_ppc() {
+    cross_check("tests/data/wget-ppc", Filter::BcjPPC { start: 0 }, |f| {
+        f.powerpc()
+    });
+}
+
+#[test]
+fn cross_check_bcj_ia64() {
+    cross_check("tests/data/wget-ia64", Filter::BcjIA64 { start: 0 }, |f| {
+        f.ia64()
+    });
+}
+
+#[test]
+fn cross_check_bcj_arm() {
+    cross_check("tests/data/wget-arm", Filter::BcjARM { start: 0 }, |f| {
+        f.arm()
+    });
+}
+
+#[test]
+fn cross_check_bcj_arm_thumb() {
+    cross_check(
+        "tests/data/wget-arm-thumb",
+        Filter::BcjARMThumb { start: 0 },
+        |f| f.arm_thumb(),
+    );
+}
+
+#[test]
+fn cross_check_bcj_sparc() {
+    cross_check(
+        "tests/data/wget-sparc",
+        Filter::BcjSPARC { start: 0 },
+        |f| f.sparc(),
+    );
+}
+
+#[test]
+fn cross_check_bcj_arm64() {
+    cross_check(
+        "tests/data/wget-arm64",
+        Filter::BcjARM64 { start: 0 },
+        |f| f.arm64(),
+    );
+}
+
+#[test]
+fn cross_check_bcj_riscv() {
+    cross_check(
+        "tests/data/wget-riscv",
+        Filter::BcjRISCV { start: 0 },
+        |f| f.riscv(),
+    );
+}
+
+#[test]
+fn cross_check_delta_with_bcj_x86() {
+    // A two-pre-filter chain: delta first, then x86, matching on both sides.
+    let data = std::fs::read("tests/data/wget-x86").unwrap();
+
+    let mut option = XzOptions::with_preset(1);
+    option.prepend_filter(Filter::BcjX86 { start: 0 });
+    option.prepend_filter(Filter::Delta { distance: 1 });
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut liblzma_uncompressed = Vec::new();
+    let mut decoder = XzDecoder::new(compressed.as_slice());
+    decoder.read_to_end(&mut liblzma_uncompressed).unwrap();
+    assert!(liblzma_uncompressed == data);
+
+    let lzma_options = stream::LzmaOptions::new_preset(1).unwrap();
+    let mut filters = stream::Filters::new();
+    filters.delta_properties(&[0x00]).unwrap().x86();
+    filters.lzma2(&lzma_options);
+
+    let stream = stream::Stream::new_stream_encoder(&filters, stream::Check::Crc32).unwrap();
+    let mut liblzma_compressed = Vec::new();
+    let mut encoder = XzEncoder::new_stream(data.as_slice(), stream);
+    encoder.read_to_end(&mut liblzma_compressed).unwrap();
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(liblzma_compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
@@ -179,3 +179,122 @@ fn round_trip_pg6800_8() {
 fn round_trip_pg6800_9() {
     test_round_trip(PG6800, 9);
 }
+
+#[test]
+fn trailing_data_handling() {
+    let data = b"lzip trailing data test".repeat(500);
+
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = LzipWriter::new(&mut compressed, LzipOptions::with_preset(3));
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut with_junk = compressed.clone();
+    with_junk.extend_from_slice(b"JUNK BYTES AFTER THE MEMBER");
+
+    // By default, trailing data is ignored.
+    let mut uncompressed = Vec::new();
+    LzipReader::new(with_junk.as_slice())
+        .unwrap()
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.as_slice() == data);
+
+    // In strict mode, trailing data is an error.
+    let mut uncompressed = Vec::new();
+    let result = LzipReader::new(with_junk.as_slice())
+        .unwrap()
+        .with_allow_trailing_data(false)
+        .read_to_end(&mut uncompressed);
+    assert!(result.is_err());
+
+    // A clean stream passes strict mode.
+    let mut uncompressed = Vec::new();
+    LzipReader::new(compressed.as_slice())
+        .unwrap()
+        .with_allow_trailing_data(false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.as_slice() == data);
+}
+
+#[test]
+fn nearest_legal_dict_size() {
+    use lzma_rust2::LzipOptions;
+
+    // Exact legal values are kept.
+    assert_eq!(LzipOptions::nearest_legal_dict_size(4096), 4096);
+    assert_eq!(LzipOptions::nearest_legal_dict_size(1 << 20), 1 << 20);
+    assert_eq!(LzipOptions::nearest_legal_dict_size(512 << 20), 512 << 20);
+
+    // 2^19 - 6 * 2^15 = 320 KiB is the spec example of a fractional size.
+    assert_eq!(LzipOptions::nearest_legal_dict_size(320 << 10), 320 << 10);
+
+    // Requests round up to the next legal value, never down.
+    assert_eq!(LzipOptions::nearest_legal_dict_size(5000), 5120);
+    assert_eq!(
+        LzipOptions::nearest_legal_dict_size((320 << 10) + 1),
+        352 << 10
+    );
+
+    // Out-of-range requests clamp to the spec limits.
+    assert_eq!(LzipOptions::nearest_legal_dict_size(0), 4096);
+    assert_eq!(LzipOptions::nearest_legal_dict_size(u32::MAX), 512 << 20);
+}
+
+#[test]
+fn empty_input_produces_valid_stream() {
+    let mut compressed = Vec::new();
+    let writer = LzipWriter::new(&mut compressed, LzipOptions::with_preset(1));
+    writer.finish().unwrap();
+
+    let mut uncompressed = Vec::new();
+    LzipReader::new(compressed.as_slice())
+        .unwrap()
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.is_empty());
+}
+
+#[test]
+fn corrupted_member_crc_identifies_the_member() {
+    use std::num::NonZeroU64;
+
+    // Two members of one dictionary size each (member sizes get clamped up
+    // to the dictionary size).
+    let dict_size = LzipOptions::with_preset(0).lzma_options.dict_size as u64;
+    let data = b"member crc verification".repeat(2 * dict_size as usize / 23);
+    let mut option = LzipOptions::with_preset(0);
+    option.set_member_size(NonZeroU64::new(dict_size));
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = LzipWriter::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // Corrupt the CRC32 in the SECOND member's trailer (the last 20 bytes
+    // of the file are the trailer: CRC32 + data size + member size).
+    let position = compressed.len() - 20;
+    compressed[position] ^= 0xFF;
+
+    let mut reader = LzipReader::new(compressed.as_slice()).unwrap();
+    let mut uncompressed = Vec::new();
+    let error = reader.read_to_end(&mut uncompressed).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    // The first member was read fine; the failure is in member 1.
+    assert_eq!(reader.members_read(), 1);
+
+    // An intact file reports both members read.
+    compressed[position] ^= 0xFF;
+    let mut reader = LzipReader::new(compressed.as_slice()).unwrap();
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+    assert_eq!(reader.members_read(), 2);
+}
@@ -3,7 +3,7 @@ use std::{
     num::{NonZero, NonZeroU64},
 };
 
-use lzma_rust2::{LzipOptions, LzipReaderMt, LzipWriterMt};
+use lzma_rust2::{LzipOptions, LzipReader, LzipReaderMt, LzipWriterMt};
 
 static EXECUTABLE: &str = "tests/data/executable.exe";
 static PG100: &str = "tests/data/pg100.txt";
@@ -196,3 +196,55 @@ fn round_trip_pg6800_8() {
 fn round_trip_pg6800_9() {
     test_round_trip(PG6800, 9);
 }
+
+#[test]
+fn member_size_variation() {
+    let data = std::fs::read(EXECUTABLE).unwrap();
+    let data = &data[..1 << 20];
+
+    // Preset 0 keeps the dictionary at 256 KiB, so a smaller member size
+    // exercises the clamp while a larger one splits normally.
+    let dict_size = LzipOptions::with_preset(0).lzma_options.dict_size as u64;
+    assert_eq!(dict_size, 256 * 1024);
+
+    let cases: [(u64, usize); 3] = [
+        // Clamped up to the dictionary size: four members.
+        (64 << 10, 4),
+        // Much larger than the input: a single member.
+        (64 << 20, 1),
+        // Exactly the input length: a single member.
+        (data.len() as u64, 1),
+    ];
+
+    for (member_size, expected_members) in cases {
+        let mut option = LzipOptions::with_preset(0);
+        option.set_member_size(NonZeroU64::new(member_size));
+
+        let mut compressed = Vec::new();
+
+        {
+            let mut writer = LzipWriterMt::new(&mut compressed, option, 2).unwrap();
+            writer.write_all(data).unwrap();
+            writer.finish().unwrap();
+        }
+
+        // Both readers agree on the contents.
+        let mut uncompressed = Vec::new();
+        LzipReader::new(compressed.as_slice())
+            .unwrap()
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed.as_slice() == data);
+
+        let mut reader = LzipReaderMt::new(Cursor::new(compressed), 2).unwrap();
+        let mut uncompressed = Vec::new();
+        reader.read_to_end(&mut uncompressed).unwrap();
+        assert!(uncompressed.as_slice() == data);
+
+        assert_eq!(
+            reader.member_count(),
+            expected_members,
+            "member_size {member_size}"
+        );
+    }
+}
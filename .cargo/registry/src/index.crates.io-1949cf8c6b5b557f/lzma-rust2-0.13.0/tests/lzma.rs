@@ -188,3 +188,94 @@ fn round_trip_pg6800_8() {
 fn round_trip_pg6800_9() {
     test_round_trip(PG6800, 9);
 }
+
+#[test]
+fn total_out_reporting() {
+    let data = std::fs::read(PG6800).unwrap();
+    let data = &data[..50_000];
+
+    let option = LzmaOptions::with_preset(4);
+
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = LzmaWriter::new_no_header(&mut compressed, &option, true).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // Known size: total_out matches what was produced.
+    let mut reader = LzmaReader::new(
+        compressed.as_slice(),
+        data.len() as u64,
+        option.lc,
+        option.lp,
+        option.pb,
+        option.dict_size,
+        None,
+    )
+    .unwrap();
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+    assert_eq!(reader.total_out(), data.len() as u64);
+
+    // Unknown size: the end marker terminates the stream and total_out
+    // reports the actual size.
+    let mut reader = LzmaReader::new(
+        compressed.as_slice(),
+        u64::MAX,
+        option.lc,
+        option.lp,
+        option.pb,
+        option.dict_size,
+        None,
+    )
+    .unwrap();
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+    assert_eq!(reader.total_out(), data.len() as u64);
+}
+
+#[test]
+fn no_leading_zero_round_trip() {
+    let data = b"raw LZMA without the leading zero byte".repeat(400);
+
+    let options = LzmaOptions::with_preset(3);
+    let mut compressed = Vec::new();
+    let mut writer = LzmaWriter::new_no_header(&mut compressed, &options, false).unwrap();
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    // Raw LZMA always starts with the range encoder's zero byte.
+    assert_eq!(compressed[0], 0);
+    let stripped = &compressed[1..];
+
+    let mut reader = LzmaReader::new_no_leading_zero(
+        stripped,
+        data.len() as u64,
+        options.lc,
+        options.lp,
+        options.pb,
+        options.dict_size,
+        None,
+    )
+    .unwrap();
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+
+    // The standard constructor rejects the stripped stream, whose first
+    // byte is no longer zero.
+    let result = LzmaReader::new(
+        stripped,
+        data.len() as u64,
+        options.lc,
+        options.lp,
+        options.pb,
+        options.dict_size,
+        None,
+    );
+    assert!(result.is_err());
+}
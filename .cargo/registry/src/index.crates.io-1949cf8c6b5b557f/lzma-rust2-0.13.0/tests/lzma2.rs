@@ -180,3 +180,1016 @@ fn round_trip_pg6800_8() {
 fn round_trip_pg6800_9() {
     test_round_trip(PG6800, 9);
 }
+
+#[test]
+fn write_vectored_matches_sequential() {
+    use std::io::IoSlice;
+
+    let data = std::fs::read(PG6800).unwrap();
+    let chunks: Vec<&[u8]> = data.chunks(977).collect();
+
+    let option = Lzma2Options::with_preset(3);
+
+    let mut sequential = Vec::new();
+    {
+        let mut writer = Lzma2Writer::new(&mut sequential, option.clone());
+        for chunk in &chunks {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    let mut vectored = Vec::new();
+    {
+        let mut writer = Lzma2Writer::new(&mut vectored, option.clone());
+        let slices: Vec<IoSlice> = chunks.iter().map(|chunk| IoSlice::new(chunk)).collect();
+        let written = writer.write_vectored(&slices).unwrap();
+        assert_eq!(written, data.len());
+        writer.finish().unwrap();
+    }
+
+    assert!(sequential == vectored);
+
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(vectored.as_slice(), option.lzma_options.dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.as_slice() == data);
+}
+
+#[test]
+fn low_latency_flushed_messages() {
+    use lzma_rust2::Lzma2ChunkReader;
+
+    // Highly compressible messages: without low latency the encoder would
+    // emit LZMA chunks for these, with it every flush becomes a predictable
+    // uncompressed chunk.
+    let messages: Vec<Vec<u8>> = (0..100).map(|i| vec![i as u8; 100]).collect();
+    let expected: Vec<u8> = messages.concat();
+
+    let compress = |low_latency: bool| {
+        let mut option = Lzma2Options::with_preset(6);
+        option.set_low_latency(low_latency);
+
+        let mut compressed = Vec::new();
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        for message in &messages {
+            writer.write_all(message).unwrap();
+            writer.flush().unwrap();
+        }
+        writer.finish().unwrap();
+        compressed
+    };
+
+    let count_chunks = |compressed: &[u8]| {
+        let mut uncompressed_chunks = 0;
+        let mut lzma_chunks = 0;
+        for chunk in Lzma2ChunkReader::new(compressed) {
+            if chunk.unwrap().is_uncompressed() {
+                uncompressed_chunks += 1;
+            } else {
+                lzma_chunks += 1;
+            }
+        }
+        (uncompressed_chunks, lzma_chunks)
+    };
+
+    let plain = compress(false);
+    let low_latency = compress(true);
+
+    let (_, plain_lzma) = count_chunks(&plain);
+    let (low_latency_uncompressed, low_latency_lzma) = count_chunks(&low_latency);
+
+    assert!(plain_lzma > 0);
+    assert_eq!(low_latency_lzma, 0);
+    assert_eq!(low_latency_uncompressed, messages.len());
+
+    let option = Lzma2Options::with_preset(6);
+    for compressed in [&plain, &low_latency] {
+        let mut uncompressed = Vec::new();
+        Lzma2Reader::new(compressed.as_slice(), option.lzma_options.dict_size, None)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed == expected);
+    }
+}
+
+#[test]
+fn dict_window_chaining() {
+    let first = std::fs::read(PG6800).unwrap();
+    // A "next file" similar to the first one.
+    let mut second = first[..first.len() / 2].to_vec();
+    second.extend_from_slice(b"a tail of new content not seen before");
+
+    let option = Lzma2Options::with_preset(6);
+    let dict_size = option.lzma_options.dict_size;
+
+    // Compress the first file and snapshot its trailing window.
+    let mut first_compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut first_compressed, option.clone());
+    writer.write_all(&first).unwrap();
+    let window = writer.take_dict_window();
+    writer.finish().unwrap();
+    assert!(!window.is_empty());
+
+    // Compress the second file cold and with the chained preset dict.
+    let mut cold = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut cold, option.clone());
+    writer.write_all(&second).unwrap();
+    writer.finish().unwrap();
+
+    let mut chained_option = option.clone();
+    chained_option.lzma_options.preset_dict = Some(window.clone());
+    let mut chained = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut chained, chained_option);
+    writer.write_all(&second).unwrap();
+    writer.finish().unwrap();
+
+    // The shared history must improve compression of the similar file.
+    assert!(chained.len() < cold.len() / 2);
+
+    // And it round-trips when the reader gets the same preset dict.
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(chained.as_slice(), dict_size, Some(&window))
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == second);
+}
+
+#[test]
+fn cloned_options_are_independent() {
+    let data = std::fs::read(PG6800).unwrap();
+    let data = &data[..40_000];
+
+    let mut option = Lzma2Options::with_preset(6);
+    option.lzma_options.preset_dict = Some(b"shared preset dictionary".repeat(100));
+
+    let mut clone = option.clone();
+
+    // The preset dictionary is deeply cloned: mutating the clone must leave
+    // the original untouched.
+    if let Some(dict) = clone.lzma_options.preset_dict.as_mut() {
+        dict[0] ^= 0xFF;
+    }
+    assert_ne!(
+        option.lzma_options.preset_dict.as_ref().unwrap()[0],
+        clone.lzma_options.preset_dict.as_ref().unwrap()[0],
+    );
+
+    // A faithful clone produces byte-identical output to the original.
+    let compress = |option: Lzma2Options| {
+        let mut compressed = Vec::new();
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+        compressed
+    };
+
+    let original_output = compress(option.clone());
+    let clone_output = compress(option);
+    assert!(original_output == clone_output);
+}
+
+#[test]
+fn push_decoder_arbitrary_chunking() {
+    use lzma_rust2::Lzma2PushDecoder;
+
+    let data = std::fs::read(PG6800).unwrap();
+    let option = Lzma2Options::with_preset(6);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    // Feed the stream in pseudo-random push sizes between 1 and 4097 bytes.
+    let mut decoder = Lzma2PushDecoder::new(dict_size, None);
+    let mut output = Vec::new();
+    let mut offset = 0;
+    let mut seed = 0xDEADBEEFu64;
+
+    while offset < compressed.len() {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let push_len = (1 + (seed % 4097) as usize).min(compressed.len() - offset);
+
+        let consumed = decoder
+            .push(&compressed[offset..offset + push_len])
+            .unwrap();
+        assert_eq!(consumed, push_len);
+        offset += push_len;
+
+        let mut buf = [0u8; 8192];
+        loop {
+            let produced = decoder.pull(&mut buf).unwrap();
+            if produced == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..produced]);
+        }
+    }
+
+    assert!(decoder.is_finished());
+    assert!(output == data);
+
+    // Pushing anything after the end marker is rejected.
+    assert!(decoder.push(b"extra").is_err());
+}
+
+#[test]
+fn uncompressed_reset_chunks_interleaved() {
+    use lzma_rust2::{lzma2_compress, Lzma2ReaderMt};
+
+    fn uncompressed_chunk(control: u8, data: &[u8]) -> Vec<u8> {
+        let mut chunk = vec![control];
+        chunk.extend_from_slice(&((data.len() - 1) as u16).to_be_bytes());
+        chunk.extend_from_slice(data);
+        chunk
+    }
+
+    let first = b"first piece behind a 0x01 dictionary reset".to_vec();
+    let second = b"second piece, 0x02 keeps the dictionary".to_vec();
+    let third = b"compressed content ".repeat(300);
+    let fourth = b"final piece behind another 0x01 reset".to_vec();
+
+    let option = Lzma2Options::with_preset(6);
+    let dict_size = option.lzma_options.dict_size;
+
+    // Compressed chunks of `third` start with their own dictionary reset, so
+    // they splice in cleanly after the uncompressed chunks.
+    let mut third_chunks = lzma2_compress(&third, &option).unwrap();
+    assert_eq!(third_chunks.pop(), Some(0x00));
+
+    let mut stream = Vec::new();
+    stream.extend_from_slice(&uncompressed_chunk(0x01, &first));
+    stream.extend_from_slice(&uncompressed_chunk(0x02, &second));
+    stream.extend_from_slice(&third_chunks);
+    stream.extend_from_slice(&uncompressed_chunk(0x01, &fourth));
+    stream.push(0x00);
+
+    let mut expected = first.clone();
+    expected.extend_from_slice(&second);
+    expected.extend_from_slice(&third);
+    expected.extend_from_slice(&fourth);
+
+    // The single-threaded reader resets the dictionary exactly on the 0x01
+    // chunks and the compressed chunk's reset bits.
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(stream.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == expected);
+
+    // The multithreaded reader splits work at the same boundaries.
+    let mut uncompressed = Vec::new();
+    Lzma2ReaderMt::new(stream.as_slice(), dict_size, None, 2)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == expected);
+}
+
+#[test]
+fn preset_dict_distance_accounting() {
+    // Unique, non-repetitive dictionary content, so matches must reference
+    // deep into the preset dictionary rather than a nearby repetition.
+    let dict: Vec<u8> = (0..3000u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 11) as u8)
+        .collect();
+
+    let mut data = b"HEAD ".to_vec();
+    data.extend_from_slice(&dict[..500]);
+    data.extend_from_slice(b" TAIL");
+
+    let mut option = Lzma2Options::with_preset(6);
+    option.lzma_options.preset_dict = Some(dict.clone());
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // References into the supplied preset dictionary decode fine.
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), dict_size, Some(&dict))
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+
+    // With only a truncated tail of the dictionary supplied, the same
+    // references exceed preset + window and must be rejected.
+    let short_dict = &dict[dict.len() - 100..];
+    let mut uncompressed = Vec::new();
+    let error = Lzma2Reader::new(compressed.as_slice(), dict_size, Some(short_dict))
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+    assert_eq!(error.to_string(), "dist overflow");
+}
+
+#[test]
+fn force_compressed_chunks() {
+    use lzma_rust2::Lzma2ChunkReader;
+
+    // Incompressible data would normally be stored as uncompressed chunks.
+    let mut seed = 0x0123456789ABCDEFu64;
+    let data: Vec<u8> = (0..256 * 1024)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed >> 32) as u8
+        })
+        .collect();
+
+    let mut option = Lzma2Options::with_preset(1);
+    option.set_force_compressed_chunks(true);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // No stored chunks in the output, only LZMA chunks.
+    for chunk in Lzma2ChunkReader::new(compressed.as_slice()) {
+        assert!(!chunk.unwrap().is_uncompressed());
+    }
+
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn decode_prefix_stops_early() {
+    let data = b"prefix decoding test data".repeat(1000);
+
+    let option = Lzma2Options::with_preset(3);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut reader = Lzma2Reader::new(compressed.as_slice(), dict_size, None);
+    let mut prefix = vec![0u8; 4096];
+    let filled = reader.decode_prefix(&mut prefix).unwrap();
+    assert_eq!(filled, 4096);
+    assert!(prefix == data[..4096]);
+
+    // A prefix larger than the stream stops at the end of the stream.
+    let mut reader = Lzma2Reader::new(compressed.as_slice(), dict_size, None);
+    let mut oversized = vec![0u8; data.len() + 1024];
+    let filled = reader.decode_prefix(&mut oversized).unwrap();
+    assert_eq!(filled, data.len());
+    assert!(oversized[..filled] == data);
+}
+
+#[test]
+fn trained_preset_dict_improves_ratio() {
+    use lzma_rust2::{train_preset_dict, Lzma2Reader};
+
+    // Small structured samples sharing boilerplate, as in a JSON corpus.
+    let samples: Vec<Vec<u8>> = (0..50)
+        .map(|index| {
+            format!(
+                "{{\"schema\":\"com.example.sensor-reading.v2\",\"device\":\"sensor-{index:04}\",\
+                 \"firmware\":\"3.14.159-production\",\"temperature_celsius\":{},\
+                 \"humidity_percent\":{},\"status\":\"nominal operation, no faults detected\"}}",
+                20 + index % 10,
+                40 + index % 30,
+            )
+            .into_bytes()
+        })
+        .collect();
+
+    let training: Vec<&[u8]> = samples[..49].iter().map(|s| s.as_slice()).collect();
+    let dict = train_preset_dict(&training, 4096);
+    assert!(!dict.is_empty());
+    assert!(dict.len() <= 4096);
+
+    let target = samples[49].as_slice();
+
+    let compress = |preset_dict: Option<Vec<u8>>| {
+        let mut option = Lzma2Options::with_preset(6);
+        option.lzma_options.preset_dict = preset_dict;
+        let mut compressed = Vec::new();
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(target).unwrap();
+        writer.finish().unwrap();
+        compressed
+    };
+
+    let without_dict = compress(None);
+    let with_dict = compress(Some(dict.clone()));
+
+    assert!(
+        with_dict.len() < without_dict.len(),
+        "dict: {} bytes, no dict: {} bytes",
+        with_dict.len(),
+        without_dict.len()
+    );
+
+    // The dictionary round-trips when passed to the reader as well.
+    let dict_size = Lzma2Options::with_preset(6).lzma_options.dict_size;
+    let mut reader = Lzma2Reader::new(with_dict.as_slice(), dict_size, Some(&dict));
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == target);
+}
+
+#[test]
+fn empty_input_produces_valid_stream() {
+    let option = Lzma2Options::with_preset(1);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    let writer = Lzma2Writer::new(&mut compressed, option);
+    writer.finish().unwrap();
+
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.is_empty());
+}
+
+#[test]
+fn tee_writer_sees_exactly_the_written_bytes() {
+    use lzma_rust2::TeeWriter;
+
+    let data = b"tee writer callback bytes".repeat(400);
+
+    let mut teed = Vec::new();
+    let mut compressed = Vec::new();
+    {
+        let mut writer = TeeWriter::new(
+            Lzma2Writer::new(&mut compressed, Lzma2Options::with_preset(1)),
+            |bytes: &[u8]| teed.extend_from_slice(bytes),
+        );
+        // Uneven write sizes so partial forwarding is exercised.
+        for chunk in data.chunks(777) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.into_inner().finish().unwrap();
+    }
+
+    assert!(teed == data);
+
+    let dict_size = Lzma2Options::with_preset(1).lzma_options.dict_size;
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn reset_every_n_chunks_bounds_the_dependency_chain() {
+    use std::num::NonZeroU32;
+
+    use lzma_rust2::Lzma2ChunkReader;
+
+    // Compressible data, so each encoding chunk maps to one compressed
+    // wire chunk and the chunk counts line up.
+    let data = b"reset interval measured in encoding chunks ".repeat(200_000);
+
+    let count_independent = |reset: Option<NonZeroU32>| {
+        let mut option = Lzma2Options::with_preset(1);
+        option.set_reset_every_n_chunks(reset);
+        let mut compressed = Vec::new();
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut total = 0usize;
+        let mut independent = 0usize;
+        let mut reader = Lzma2ChunkReader::new(compressed.as_slice());
+        while let Some(chunk) = reader.next_chunk().unwrap() {
+            total += 1;
+            if chunk.is_independent {
+                independent += 1;
+            }
+        }
+        (total, independent)
+    };
+
+    let (total, baseline) = count_independent(None);
+    assert_eq!(baseline, 1);
+    assert!(total > 4, "expected several encoding chunks, got {total}");
+
+    // Resetting every 2 chunks yields roughly total / 2 independent chunks,
+    // bounding how many chunks can depend on each other.
+    let (total, independent) = count_independent(NonZeroU32::new(2));
+    assert!(
+        independent + 1 >= total / 2,
+        "{independent} independent of {total}"
+    );
+    assert!(independent > 1);
+
+    // The stream still decodes byte-exactly.
+    let mut option = Lzma2Options::with_preset(1);
+    option.set_reset_every_n_chunks(NonZeroU32::new(2));
+    let dict_size = option.lzma_options.dict_size;
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn input_size_hint_caps_the_dictionary() {
+    // Preset 9 uses a 64 MiB dictionary; the hint shrinks the effective
+    // dictionary to the smallest valid size covering the input.
+    let data = b"small buffer at a high preset".repeat(100);
+
+    let mut option = Lzma2Options::with_preset(9);
+    option
+        .lzma_options
+        .set_input_size_hint(Some(data.len() as u64));
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    // The stream decodes with a dictionary as small as the hint allows,
+    // which proves the encoder never referenced further back.
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), 4096, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+
+    // Inputs larger than the hint still compress correctly.
+    let bigger = b"small buffer at a high preset".repeat(2000);
+    let mut option = Lzma2Options::with_preset(9);
+    option.lzma_options.set_input_size_hint(Some(100));
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&bigger).unwrap();
+    writer.finish().unwrap();
+
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), 4096, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == bigger);
+}
+
+#[test]
+fn dict_size_prop_round_trip() {
+    use lzma_rust2::{lzma2_dict_size_to_prop, lzma2_prop_to_dict_size};
+
+    // Every valid property value survives the round trip.
+    for prop in 0u8..=40 {
+        let dict_size = lzma2_prop_to_dict_size(prop).unwrap();
+        assert_eq!(lzma2_dict_size_to_prop(dict_size).unwrap(), prop);
+    }
+
+    // Sizes round up to the next representable value.
+    assert_eq!(
+        lzma2_prop_to_dict_size(lzma2_dict_size_to_prop(4097).unwrap()).unwrap(),
+        6144
+    );
+    assert_eq!(lzma2_dict_size_to_prop(1 << 20).unwrap(), 16);
+
+    // Invalid inputs are rejected.
+    assert!(lzma2_dict_size_to_prop(1024).is_err());
+    assert!(lzma2_prop_to_dict_size(41).is_err());
+}
+
+#[test]
+fn output_budget_aborts_early() {
+    // Incompressible data blows through a small budget quickly.
+    let data: Vec<u8> = (0..1_000_000u64)
+        .flat_map(|i| (i.wrapping_mul(6364136223846793005) >> 24).to_le_bytes())
+        .collect();
+
+    let mut option = Lzma2Options::with_preset(1);
+    option.set_max_output(Some(64 * 1024));
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    let result = writer
+        .write_all(&data)
+        .and_then(|_| writer.finish().map(|_| ()));
+    let error = result.unwrap_err();
+    assert_eq!(error.to_string(), "output budget exceeded");
+
+    // The abort happened close to the budget, not after compressing
+    // everything.
+    assert!(compressed.len() < 256 * 1024, "{} bytes", compressed.len());
+
+    // A sufficient budget succeeds and stays within it.
+    let mut option = Lzma2Options::with_preset(1);
+    option.set_max_output(Some(16 * 1024 * 1024));
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn auto_dict_reader_handles_large_distances() {
+    // Long-range matches: a block repeated after 4 MiB forces distances far
+    // beyond the initial lazy buffer.
+    let block = b"large distance material ".repeat(1000);
+    let mut data = block.clone();
+    data.resize(4 << 20, b'x');
+    data.extend_from_slice(&block);
+
+    let mut option = Lzma2Options::with_preset(6);
+    let dict_size = option.lzma_options.dict_size;
+    option.lzma_options.set_nice_len(273);
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    // Decoding with the real dictionary size works, so the stream uses the
+    // distances we want.
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+
+    // The auto-dict reader decodes without knowing the dictionary size.
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new_auto_dict(compressed.as_slice(), None, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+
+    // A cap below the distances the stream uses still fails cleanly.
+    let mut uncompressed = Vec::new();
+    let error = Lzma2Reader::new_auto_dict(compressed.as_slice(), Some(1 << 16), None)
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn chunk_header_sizes_are_big_endian() {
+    use lzma_rust2::{Lzma2ChunkReader, Lzma2ReaderMt};
+
+    // A hand-built stream of uncompressed chunks with byte-order-asymmetric
+    // size fields: chunk one holds 0x0103 (259) bytes, chunk two 0x0080
+    // (128). A native little-endian misread of the sizes would try to read
+    // 0x0301 or 0x8000 bytes and fail or misalign.
+    let payload_one: Vec<u8> = (0..0x0103u32).map(|i| (i % 251) as u8).collect();
+    let payload_two: Vec<u8> = (0..0x0080u32).map(|i| (i % 13) as u8).collect();
+
+    let mut stream = vec![0x01, 0x01, 0x02];
+    stream.extend_from_slice(&payload_one);
+    stream.extend_from_slice(&[0x02, 0x00, 0x7F]);
+    stream.extend_from_slice(&payload_two);
+    stream.push(0x00);
+
+    let mut expected = payload_one.clone();
+    expected.extend_from_slice(&payload_two);
+
+    // Single-threaded reader.
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(stream.as_slice(), 1 << 16, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == expected);
+
+    // Chunk reader reports the exact big-endian sizes.
+    let mut chunks = Lzma2ChunkReader::new(stream.as_slice());
+    let first = chunks.next_chunk().unwrap().unwrap();
+    assert_eq!(first.uncompressed_size, 0x0103);
+    let second = chunks.next_chunk().unwrap().unwrap();
+    assert_eq!(second.uncompressed_size, 0x0080);
+
+    // Multi-threaded reader.
+    let mut uncompressed = Vec::new();
+    Lzma2ReaderMt::new(stream.as_slice(), 1 << 16, None, 2)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == expected);
+
+    // The writer emits big-endian sizes as well: for a compressed chunk the
+    // header's bytes 3..5 hold compressed size - 1 in big-endian order.
+    let data = b"writer endianness check".repeat(500);
+    let mut option = Lzma2Options::with_preset(6);
+    option.set_force_compressed_chunks(true);
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    assert!(compressed[0] >= 0xE0, "compressed chunk with dict reset");
+    let header_uncompressed = u16::from_be_bytes([compressed[1], compressed[2]]) as usize + 1;
+    assert_eq!(header_uncompressed, data.len());
+}
+
+#[test]
+fn buffer_provider_pool_recycles_buffers() {
+    use lzma_rust2::BufferProvider;
+
+    /// A pool that hands out recycled buffers and counts fresh allocations.
+    #[derive(Default)]
+    struct Pool {
+        free: Vec<Vec<u8>>,
+        fresh_allocations: usize,
+    }
+
+    impl BufferProvider for Pool {
+        fn take_buffer(&mut self, len: usize) -> Vec<u8> {
+            if let Some(position) = self.free.iter().position(|buf| buf.len() == len) {
+                let mut buf = self.free.swap_remove(position);
+                buf.fill(0);
+                return buf;
+            }
+
+            self.fresh_allocations += 1;
+            vec![0; len]
+        }
+    }
+
+    let data = b"buffer provider pooling".repeat(2000);
+    let option = Lzma2Options::with_preset(1);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    let mut pool = Pool::default();
+
+    for round in 0..5 {
+        let mut reader =
+            Lzma2Reader::new_with_provider(compressed.as_slice(), dict_size, None, &mut pool);
+        let mut uncompressed = Vec::new();
+        reader.read_to_end(&mut uncompressed).unwrap();
+        assert!(uncompressed == data, "round {round}");
+
+        let (dict_buf, rc_buf) = reader.into_buffers();
+        pool.free.push(dict_buf);
+        pool.free.push(rc_buf);
+    }
+
+    // Only the first round allocated; the other four recycled.
+    assert_eq!(pool.fresh_allocations, 2);
+}
+
+#[test]
+fn skip_incompressible_round_trips_mixed_input() {
+    // Random data (skipped proactively) interleaved with compressible text.
+    let mut random_state = 99u64;
+    let mut random = move |n: usize| -> Vec<u8> {
+        (0..n)
+            .map(|_| {
+                random_state = random_state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (random_state >> 56) as u8
+            })
+            .collect()
+    };
+
+    let noise_one = random(100_000);
+    let text_one = b"compressible section one ".repeat(2000);
+    let noise_two = random(70_000);
+    let text_two = b"compressible section two ".repeat(1500);
+
+    let mut option = Lzma2Options::with_preset(3);
+    option.set_skip_incompressible(true);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    for part in [
+        &noise_one,
+        &text_one.to_vec(),
+        &noise_two,
+        &text_two.to_vec(),
+    ] {
+        writer.write_all(part).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let mut expected = noise_one.clone();
+    expected.extend_from_slice(&text_one);
+    expected.extend_from_slice(&noise_two);
+    expected.extend_from_slice(&text_two);
+
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == expected);
+
+    // The noise went through as stored chunks: output is not much larger
+    // than the input's incompressible portion plus compressed text.
+    assert!(compressed.len() < expected.len());
+}
+
+#[test]
+fn estimate_matches_actual_output_size() {
+    use lzma_rust2::estimate_lzma2_size;
+
+    let data = b"estimate the compressed size up front".repeat(1500);
+    let option = Lzma2Options::with_preset(3);
+
+    let estimate = estimate_lzma2_size(&data, &option).unwrap();
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option.clone());
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    // The estimate is exact for a single write_all with the same options.
+    assert_eq!(estimate, compressed.len() as u64);
+
+    // Empty input estimates the end marker alone.
+    assert_eq!(estimate_lzma2_size(&[], &option).unwrap(), 1);
+}
+
+#[test]
+fn extreme_mode_round_trips_and_changes_the_stream() {
+    let data = std::fs::read("tests/data/executable.exe").unwrap();
+    let data = &data[..2 << 20];
+
+    let compress = |option: Lzma2Options| {
+        let mut compressed = Vec::new();
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+        compressed
+    };
+
+    let plain = compress(Lzma2Options::with_preset(9));
+
+    let mut option = Lzma2Options::with_preset(9);
+    option.lzma_options.set_extreme();
+    let dict_size = option.lzma_options.dict_size;
+    let extreme = compress(option);
+
+    // Extreme mode searches harder, so the stream differs and must not be
+    // (meaningfully) larger.
+    assert!(extreme != plain);
+    assert!(extreme.len() <= plain.len() + plain.len() / 100);
+
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(extreme.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == *data);
+}
+
+#[test]
+fn lenient_mode_recovers_dist_overflows_with_positions() {
+    let data = b"recover the rest of this stream around a bad reference ".repeat(400);
+
+    let option = Lzma2Options::with_preset(6);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    // Find a byte flip in the compressed payload that makes strict decoding
+    // fail with a distance overflow. The input is fixed, so the position
+    // found is deterministic.
+    let mut corrupted = None;
+    for position in 8..compressed.len() - 8 {
+        let mut candidate = compressed.clone();
+        candidate[position] ^= 0xFF;
+
+        let mut uncompressed = Vec::new();
+        let result =
+            Lzma2Reader::new(candidate.as_slice(), dict_size, None).read_to_end(&mut uncompressed);
+
+        if result.is_err_and(|error| error.to_string() == "dist overflow") {
+            corrupted = Some(candidate);
+            break;
+        }
+    }
+    let corrupted = corrupted.expect("found a corruption causing a dist overflow");
+
+    // Lenient mode decodes the whole stream instead: the declared chunk
+    // sizes still hold, the overflows are zero-filled and their output
+    // offsets are recorded.
+    let mut reader = Lzma2Reader::new_lenient(corrupted.as_slice(), dict_size, None);
+    let mut recovered = Vec::new();
+    reader.read_to_end(&mut recovered).unwrap();
+
+    assert_eq!(recovered.len(), data.len());
+    assert!(reader.recovered_errors() > 0);
+
+    let positions = reader.recovered_positions();
+    assert!(!positions.is_empty());
+    assert_eq!(positions.len() as u64, reader.recovered_errors().min(64));
+
+    // Every recorded offset marks the start of a zero-filled reference.
+    for position in positions {
+        assert_eq!(recovered[*position as usize], 0);
+    }
+}
+
+#[test]
+fn lenient_mode_survives_a_shortened_chunk() {
+    // Shrinking a compressed chunk's declared size leaves the range coder
+    // unfinished: strict mode errors, lenient mode keeps going.
+    let data = b"shortened chunk declared size".repeat(300);
+
+    let option = Lzma2Options::with_preset(6);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    // Chunk header: control, usize-1 (2 bytes BE), csize-1 (2 bytes BE).
+    assert!(compressed[0] >= 0xE0);
+    let mut bad = compressed.clone();
+    let compressed_size = u16::from_be_bytes([bad[3], bad[4]]);
+    bad[3..5].copy_from_slice(&(compressed_size - 1).to_be_bytes());
+
+    let mut uncompressed = Vec::new();
+    let error = Lzma2Reader::new(bad.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+    assert_eq!(error.to_string(), "rc not finished or lz has pending");
+
+    let mut reader = Lzma2Reader::new_lenient(bad.as_slice(), dict_size, None);
+    let mut recovered = Vec::new();
+    reader.read_to_end(&mut recovered).unwrap();
+    assert_eq!(recovered.len(), data.len());
+}
+
+#[test]
+fn skip_incompressible_is_bounded_per_chunk() {
+    // One big write whose first 4 KiB sample is random but whose bulk is
+    // highly compressible: only the leading chunk may be stored, the tail
+    // must still compress.
+    let mut random_state = 7u64;
+    let mut data: Vec<u8> = (0..4096)
+        .map(|_| {
+            random_state = random_state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (random_state >> 56) as u8
+        })
+        .collect();
+    data.extend_from_slice(&b"very compressible payload ".repeat(200_000));
+
+    let mut option = Lzma2Options::with_preset(3);
+    option.set_skip_incompressible(true);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    let mut writer = Lzma2Writer::new(&mut compressed, option);
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+
+    // A stale sample can cost at most one stored 64 KiB chunk; the rest of
+    // the five-megabyte buffer compresses as usual.
+    assert!(
+        compressed.len() < 128 * 1024,
+        "{} bytes for {} of input",
+        compressed.len(),
+        data.len()
+    );
+
+    let mut uncompressed = Vec::new();
+    Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
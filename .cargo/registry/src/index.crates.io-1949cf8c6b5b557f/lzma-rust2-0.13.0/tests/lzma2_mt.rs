@@ -200,3 +200,69 @@ fn round_trip_pg6800_8() {
 fn round_trip_pg6800_9() {
     test_round_trip(PG6800, 9);
 }
+
+#[test]
+fn corrupt_input_error_kind() {
+    // Errors found on the reader thread keep their kind.
+    let bogus: &[u8] = &[0x7F, 0x00, 0x00];
+    let mut uncompressed = Vec::new();
+    use lzma_rust2::Lzma2Writer;
+
+    let error = Lzma2ReaderMt::new(bogus, 1 << 16, None, 2)
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    // Errors found on a worker thread keep their kind as well, and must not
+    // deadlock the reading thread.
+    let data = b"worker side corruption".repeat(3000);
+    let option = Lzma2Options::with_preset(6);
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mid = compressed.len() / 2;
+    compressed[mid] ^= 0xA5;
+
+    let mut uncompressed = Vec::new();
+    let error = Lzma2ReaderMt::new(compressed.as_slice(), dict_size, None, 2)
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn corrupt_chunk_error_names_the_chunk() {
+    use std::num::NonZeroU64;
+
+    use lzma_rust2::Lzma2Writer;
+
+    let data = b"which chunk failed during the multithreaded decode".repeat(80_000);
+
+    let mut option = Lzma2Options::with_preset(0);
+    option.set_chunk_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+    let dict_size = option.lzma_options.dict_size;
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let position = compressed.len() * 3 / 4;
+    compressed[position] ^= 0xFF;
+
+    let mut reader = Lzma2ReaderMt::new(compressed.as_slice(), dict_size, None, 2);
+    let mut uncompressed = Vec::new();
+    let error = reader.read_to_end(&mut uncompressed).unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("block "), "message: {message}");
+    assert!(!message.contains("block 0"), "message: {message}");
+}
@@ -0,0 +1,86 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use lzma_rust2::{Lzma2Options, Lzma2Writer};
+
+/// Allocator wrapper that tracks the peak number of live heap bytes.
+struct PeakAllocator;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+/// A sink that consumes output without storing it.
+struct NullSink;
+
+impl Write for NullSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Streaming 100 MB through `Lzma2Writer` must keep peak heap usage at
+/// roughly `dict_size` plus the fixed chunk buffers, independent of the
+/// input size.
+#[test]
+fn lzma2_writer_memory_stays_bounded() {
+    let option = Lzma2Options::with_preset(1);
+    let dict_size = option.lzma_options.dict_size as usize;
+
+    let baseline = PEAK.load(Ordering::Relaxed);
+
+    let mut writer = Lzma2Writer::new(NullSink, option);
+
+    // Deterministic incompressible-ish data, fed in 64 KiB slices.
+    let mut chunk = vec![0u8; 64 << 10];
+    let mut seed = 0x243F6A8885A308D3u64;
+    let mut remaining = 100usize << 20;
+
+    while remaining > 0 {
+        for byte in chunk.iter_mut() {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            *byte = (seed >> 32) as u8;
+        }
+
+        let to_write = remaining.min(chunk.len());
+        writer.write_all(&chunk[..to_write]).unwrap();
+        remaining -= to_write;
+    }
+
+    writer.finish().unwrap();
+
+    let peak = PEAK.load(Ordering::Relaxed).saturating_sub(baseline);
+
+    // The match finder and dictionary need a small multiple of dict_size;
+    // 16x leaves room for the hash tables and chunk buffers but is far below
+    // the 100 MB input size.
+    assert!(
+        peak < dict_size * 16,
+        "peak heap usage {peak} exceeds bound {}",
+        dict_size * 16
+    );
+}
@@ -0,0 +1,176 @@
+#![cfg(feature = "proptest-tests")]
+
+use std::io::{Cursor, Read, Write};
+use std::num::NonZeroU64;
+
+use lzma_rust2::{
+    LzipOptions, LzipReader, LzipWriter, Lzma2Options, Lzma2Reader, Lzma2ReaderMt, Lzma2Writer,
+    LzmaOptions, LzmaReader, LzmaWriter, XzOptions, XzReader, XzReaderMt, XzWriter, XzWriterMt,
+};
+use proptest::prelude::*;
+
+/// Inputs of varying size and compressibility: random bytes, repeated
+/// patterns and runs of a single byte.
+fn input_strategy() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        proptest::collection::vec(any::<u8>(), 0..32_768),
+        (proptest::collection::vec(any::<u8>(), 1..64), 1usize..2048).prop_map(
+            |(pattern, repeats)| {
+                let mut data = Vec::with_capacity(pattern.len() * repeats);
+                for _ in 0..repeats {
+                    data.extend_from_slice(&pattern);
+                }
+                data
+            }
+        ),
+        (any::<u8>(), 0usize..65_536).prop_map(|(byte, len)| vec![byte; len]),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn lzma2_round_trip(data in input_strategy(), preset in 0u32..=3) {
+        let option = Lzma2Options::with_preset(preset);
+        let dict_size = option.lzma_options.dict_size;
+
+        let mut compressed = Vec::new();
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut uncompressed = Vec::new();
+        Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        prop_assert!(uncompressed == data);
+    }
+
+    #[test]
+    fn lzma2_mt_round_trip(
+        data in input_strategy(),
+        preset in 0u32..=2,
+        num_workers in 1u32..=3,
+    ) {
+        let mut option = Lzma2Options::with_preset(preset);
+        option.set_chunk_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+        let dict_size = option.lzma_options.dict_size;
+
+        let mut compressed = Vec::new();
+        let mut writer =
+            lzma_rust2::Lzma2WriterMt::new(&mut compressed, option, num_workers).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut uncompressed = Vec::new();
+        Lzma2ReaderMt::new(compressed.as_slice(), dict_size, None, num_workers)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        prop_assert!(uncompressed == data);
+    }
+
+    #[test]
+    fn lzma_round_trip(data in input_strategy(), preset in 0u32..=3) {
+        let option = LzmaOptions::with_preset(preset);
+
+        let mut compressed = Vec::new();
+        let mut writer =
+            LzmaWriter::new_use_header(&mut compressed, &option, Some(data.len() as u64)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut uncompressed = Vec::new();
+        LzmaReader::new_mem_limit(compressed.as_slice(), u32::MAX, None)
+            .unwrap()
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        prop_assert!(uncompressed == data);
+    }
+
+    #[test]
+    fn xz_round_trip(
+        data in input_strategy(),
+        preset in 0u32..=2,
+        multi_block in proptest::bool::ANY,
+    ) {
+        let mut option = XzOptions::with_preset(preset);
+        if multi_block {
+            option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+        }
+
+        let mut compressed = Vec::new();
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut uncompressed = Vec::new();
+        XzReader::new(compressed.as_slice(), false)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        prop_assert!(uncompressed == data);
+    }
+
+    #[test]
+    fn xz_mt_round_trip(
+        data in input_strategy(),
+        preset in 0u32..=2,
+        num_workers in 1u32..=3,
+    ) {
+        let mut option = XzOptions::with_preset(preset);
+        option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+        let mut compressed = Vec::new();
+        let mut writer = XzWriterMt::new(&mut compressed, option, num_workers).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut uncompressed = Vec::new();
+        XzReaderMt::new(Cursor::new(compressed), false, num_workers)
+            .unwrap()
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        prop_assert!(uncompressed == data);
+    }
+
+    #[test]
+    fn lzma2_round_trip_at_the_dict_boundary(
+        length in 262_143usize..=262_145,
+        byte in any::<u8>(),
+    ) {
+        // Preset 0 uses a 256 KiB dictionary; sizes straddling it exercise
+        // the window-wrap edge.
+        let data = vec![byte; length];
+        let option = Lzma2Options::with_preset(0);
+        let dict_size = option.lzma_options.dict_size;
+        prop_assert_eq!(dict_size, 256 * 1024);
+
+        let mut compressed = Vec::new();
+        let mut writer = Lzma2Writer::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut uncompressed = Vec::new();
+        Lzma2Reader::new(compressed.as_slice(), dict_size, None)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        prop_assert!(uncompressed == data);
+    }
+
+    #[test]
+    fn lzip_round_trip(data in input_strategy(), preset in 0u32..=2) {
+        let option = LzipOptions::with_preset(preset);
+
+        let mut compressed = Vec::new();
+        let mut writer = LzipWriter::new(&mut compressed, option);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut uncompressed = Vec::new();
+        LzipReader::new(compressed.as_slice())
+            .unwrap()
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        prop_assert!(uncompressed == data);
+    }
+}
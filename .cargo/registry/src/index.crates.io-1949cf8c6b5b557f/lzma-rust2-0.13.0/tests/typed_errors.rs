@@ -0,0 +1,128 @@
+#![cfg(all(feature = "typed-errors", feature = "xz"))]
+
+use std::io::{Read, Write};
+use std::num::NonZeroU64;
+
+use lzma_rust2::{
+    FilterType, LzmaError, LzmaOptions, XzOptions, XzReader, XzReaderMt, XzWriter, XzWriterMt,
+};
+
+/// Extracts the typed payload of an error produced by this crate.
+fn payload(error: &std::io::Error) -> &LzmaError {
+    error
+        .get_ref()
+        .and_then(|source| source.downcast_ref::<LzmaError>())
+        .expect("error carries an LzmaError payload")
+}
+
+#[test]
+fn checksum_mismatch_is_typed() {
+    let data = b"typed checksum errors".repeat(1000);
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // Flip a byte of the CRC64 block checksum (right before the 12-byte
+    // index and 12-byte footer of this single-record stream).
+    let position = compressed.len() - 25;
+    compressed[position] ^= 0xFF;
+
+    let mut uncompressed = Vec::new();
+    let error = XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(payload(&error), &LzmaError::ChecksumMismatch);
+}
+
+#[test]
+fn mt_errors_carry_the_block_index() {
+    let data = b"typed multithreaded block context".repeat(40_000);
+
+    let mut option = XzOptions::with_preset(0);
+    option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriterMt::new(&mut compressed, option, 2).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let position = compressed.len() / 2;
+    compressed[position] ^= 0xFF;
+
+    let mut uncompressed = Vec::new();
+    let error = XzReaderMt::new(std::io::Cursor::new(compressed), false, 2)
+        .unwrap()
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+
+    match payload(&error) {
+        LzmaError::InBlock(block_index, source) => {
+            assert!(*block_index > 0);
+            assert_eq!(error.to_string(), format!("block {block_index}: {source}"));
+        }
+        other => panic!("expected InBlock, got {other:?}"),
+    }
+}
+
+#[test]
+fn allowlist_rejection_names_the_filter() {
+    use lzma_rust2::Filter;
+
+    let data = std::fs::read("tests/data/wget-x86").unwrap();
+
+    let mut option = XzOptions::with_preset(1);
+    option.prepend_filter(Filter::BcjX86 { start: 0 });
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut uncompressed = Vec::new();
+    let error = XzReader::new(compressed.as_slice(), false)
+        .with_allowed_filters(&[FilterType::LZMA2])
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+    assert_eq!(
+        payload(&error),
+        &LzmaError::UnsupportedFilter(FilterType::BcjX86)
+    );
+}
+
+#[test]
+fn invalid_dict_size_is_typed() {
+    let error = lzma_rust2::lzma2_dict_size_to_prop(16).unwrap_err();
+    assert_eq!(payload(&error), &LzmaError::InvalidDictSize);
+
+    let mut options = LzmaOptions::with_preset(1);
+    options.dict_size = 1024;
+    let _ = options;
+}
+
+#[test]
+fn io_error_kind_mapping() {
+    use std::io::ErrorKind;
+
+    for (error, kind) in [
+        (LzmaError::ChecksumMismatch, ErrorKind::InvalidData),
+        (LzmaError::Corrupted("x"), ErrorKind::InvalidData),
+        (LzmaError::Truncated, ErrorKind::UnexpectedEof),
+        (
+            LzmaError::UnsupportedFilter(FilterType::Delta),
+            ErrorKind::Unsupported,
+        ),
+        (LzmaError::InvalidDictSize, ErrorKind::InvalidInput),
+        (LzmaError::OutOfMemory("x"), ErrorKind::OutOfMemory),
+    ] {
+        assert_eq!(std::io::Error::from(error).kind(), kind);
+    }
+}
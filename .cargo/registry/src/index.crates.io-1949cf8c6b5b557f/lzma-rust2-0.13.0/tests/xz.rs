@@ -189,3 +189,1130 @@ fn round_trip_pg6800_8() {
 fn round_trip_pg6800_9() {
     test_round_trip(PG6800, 9);
 }
+
+#[test]
+fn partial_recovery_truncated_stream() {
+    let data = std::fs::read(PG6800).unwrap();
+
+    let mut option = XzOptions::with_preset(3);
+    option.set_block_size(std::num::NonZeroU64::new(16 << 10));
+
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // The complete stream still decodes fully with recovery enabled.
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .with_partial_recovery(true)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.as_slice() == data);
+
+    // Truncating anywhere must neither error nor return non-prefix data:
+    // every fully decoded, checksum-validated block is salvaged, the rest
+    // is discarded.
+    let mut salvaged_everything_once = false;
+
+    let tail = compressed.len().saturating_sub(64);
+
+    for truncated_len in (13..tail).step_by(131).chain(tail..compressed.len()) {
+        let truncated = &compressed[..truncated_len];
+
+        let mut uncompressed = Vec::new();
+        XzReader::new(truncated, false)
+            .with_partial_recovery(true)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+
+        assert!(uncompressed.len() <= data.len());
+        assert!(uncompressed.as_slice() == &data[..uncompressed.len()]);
+
+        if uncompressed.len() == data.len() {
+            salvaged_everything_once = true;
+        }
+    }
+
+    // Truncating inside the index/footer must still salvage all blocks.
+    assert!(salvaged_everything_once);
+
+    // Without recovery, a truncated stream reports an error.
+    let truncated = &compressed[..compressed.len() / 2];
+    let mut uncompressed = Vec::new();
+    assert!(XzReader::new(truncated, false)
+        .read_to_end(&mut uncompressed)
+        .is_err());
+}
+
+#[test]
+fn round_trip_sha256_check() {
+    use lzma_rust2::{CheckType, XzReaderMt};
+
+    let data = std::fs::read(PG6800).unwrap();
+
+    let mut option = XzOptions::with_preset(6);
+    option.set_check_sum_type(CheckType::Sha256);
+
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.as_slice() == data);
+
+    let mut uncompressed = Vec::new();
+    XzReaderMt::new(std::io::Cursor::new(compressed.as_slice()), false, 2)
+        .unwrap()
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.as_slice() == data);
+
+    // liblzma must also accept the SHA-256 check.
+    let mut liblzma_uncompressed = Vec::new();
+    {
+        use liblzma::read::XzDecoder;
+        let mut decoder = XzDecoder::new(compressed.as_slice());
+        decoder.read_to_end(&mut liblzma_uncompressed).unwrap();
+    }
+    assert!(liblzma_uncompressed.as_slice() == data);
+}
+
+#[test]
+fn round_trip_sha256_check_multi_block() {
+    use lzma_rust2::{CheckType, XzReaderMt};
+
+    let data = std::fs::read(PG6800).unwrap();
+
+    let mut option = XzOptions::with_preset(3);
+    option.set_check_sum_type(CheckType::Sha256);
+    option.set_block_size(std::num::NonZeroU64::new(16 << 10));
+
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.as_slice() == data);
+
+    let mut uncompressed = Vec::new();
+    XzReaderMt::new(std::io::Cursor::new(compressed.as_slice()), false, 2)
+        .unwrap()
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.as_slice() == data);
+}
+
+#[test]
+fn write_vectored_matches_sequential() {
+    use std::io::IoSlice;
+
+    let data = std::fs::read(PG6800).unwrap();
+    let chunks: Vec<&[u8]> = data.chunks(977).collect();
+
+    let mut sequential = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut sequential, XzOptions::with_preset(3)).unwrap();
+        for chunk in &chunks {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    let mut vectored = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut vectored, XzOptions::with_preset(3)).unwrap();
+        let slices: Vec<IoSlice> = chunks.iter().map(|chunk| IoSlice::new(chunk)).collect();
+        let mut written = 0;
+        while written < data.len() {
+            // Skip fully written slices and re-slice the partial one.
+            let mut offset = written;
+            let mut index = 0;
+            while offset >= slices[index].len() {
+                offset -= slices[index].len();
+                index += 1;
+            }
+            let remainder = &chunks[index][offset..];
+            let remainder_slice = [IoSlice::new(remainder)];
+            let bufs: Vec<IoSlice> = remainder_slice
+                .iter()
+                .chain(slices[index + 1..].iter())
+                .map(|slice| IoSlice::new(slice))
+                .collect();
+            written += writer.write_vectored(&bufs).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    assert!(sequential == vectored);
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(vectored.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.as_slice() == data);
+}
+
+#[test]
+fn delta_distance_validation() {
+    use lzma_rust2::{Filter, XzReaderMt};
+
+    let data = b"delta distance validation".repeat(200);
+
+    // Distance 0 and 257 are rejected by both writers.
+    for distance in [0u32, 257] {
+        let mut option = XzOptions::with_preset(1);
+        option.prepend_filter(Filter::Delta { distance });
+        assert!(XzWriter::new(Vec::new(), option.clone()).is_err());
+
+        option.set_block_size(std::num::NonZeroU64::new(1 << 20));
+        assert!(lzma_rust2::XzWriterMt::new(Vec::new(), option, 1).is_err());
+    }
+
+    // Distance 1 and 256 are legal and round-trip.
+    for distance in [1u32, 256] {
+        let mut option = XzOptions::with_preset(1);
+        option.prepend_filter(Filter::Delta { distance });
+
+        let mut compressed = Vec::new();
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut uncompressed = Vec::new();
+        XzReader::new(compressed.as_slice(), false)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed.as_slice() == data);
+
+        let mut uncompressed = Vec::new();
+        XzReaderMt::new(std::io::Cursor::new(compressed), false, 1)
+            .unwrap()
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed.as_slice() == data);
+    }
+}
+
+#[test]
+fn store_incompressible_blocks() {
+    // Pseudo-random data the encoder cannot compress.
+    let mut seed = 0x9E3779B97F4A7C15u64;
+    let incompressible: Vec<u8> = (0..256 * 1024)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed >> 32) as u8
+        })
+        .collect();
+
+    let mut plain = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut plain, XzOptions::with_preset(6)).unwrap();
+        writer.write_all(&incompressible).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut option = XzOptions::with_preset(6);
+    option.set_store_incompressible(true);
+    let mut stored = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut stored, option).unwrap();
+        writer.write_all(&incompressible).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // The stored variant must not expand the data beyond the chunk framing
+    // and container overhead, and must never be larger than the plain one.
+    assert!(stored.len() <= plain.len());
+    assert!(stored.len() < incompressible.len() + 1024);
+
+    // It is still a valid XZ stream for our reader and liblzma.
+    let mut uncompressed = Vec::new();
+    XzReader::new(stored.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == incompressible);
+
+    let mut liblzma_uncompressed = Vec::new();
+    {
+        use liblzma::read::XzDecoder;
+        let mut decoder = XzDecoder::new(stored.as_slice());
+        decoder.read_to_end(&mut liblzma_uncompressed).unwrap();
+    }
+    assert!(liblzma_uncompressed == incompressible);
+
+    // Compressible data keeps being compressed.
+    let compressible = std::fs::read(PG6800).unwrap();
+    let mut option = XzOptions::with_preset(6);
+    option.set_store_incompressible(true);
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&compressible).unwrap();
+        writer.finish().unwrap();
+    }
+    assert!(compressed.len() < compressible.len() / 2);
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == compressible);
+}
+
+#[test]
+fn mixed_check_concatenated_streams() {
+    use lzma_rust2::CheckType;
+
+    let first = b"first stream with crc32".repeat(300);
+    let second = b"second stream with sha-256".repeat(300);
+
+    let mut concatenated = Vec::new();
+
+    for (data, check_type) in [(&first, CheckType::Crc32), (&second, CheckType::Sha256)] {
+        let mut option = XzOptions::with_preset(3);
+        option.set_check_sum_type(check_type);
+
+        let mut writer = XzWriter::new(&mut concatenated, option).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut expected = first.clone();
+    expected.extend_from_slice(&second);
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(concatenated.as_slice(), true)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == expected);
+
+    // liblzma agrees on the mixed-check concatenation.
+    let mut liblzma_uncompressed = Vec::new();
+    {
+        use liblzma::read::XzDecoder;
+        let mut decoder = XzDecoder::new_multi_decoder(concatenated.as_slice());
+        decoder.read_to_end(&mut liblzma_uncompressed).unwrap();
+    }
+    assert!(liblzma_uncompressed == expected);
+}
+
+#[test]
+fn framed_protocol_no_read_ahead() {
+    // Two XZ frames with interleaved application data in a single buffer:
+    // the reader must never consume bytes beyond the frame it was given.
+    let first = b"frame one payload".repeat(100);
+    let second = b"frame two".repeat(200);
+
+    let mut framed = Vec::new();
+
+    for (data, tag) in [(&first, b"<TAG-A>".as_slice()), (&second, b"<TAG-B>")] {
+        let mut writer = XzWriter::new(&mut framed, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+        framed.extend_from_slice(tag);
+    }
+
+    let mut rest = framed.as_slice();
+
+    for (expected, tag) in [(&first, b"<TAG-A>"), (&second, b"<TAG-B>")] {
+        let mut reader = XzReader::new(rest, false);
+        let mut uncompressed = Vec::new();
+        reader.read_to_end(&mut uncompressed).unwrap();
+        assert!(uncompressed == *expected);
+
+        rest = reader.into_inner_at_end().unwrap();
+
+        let mut tag_buf = [0u8; 7];
+        rest.read_exact(&mut tag_buf).unwrap();
+        assert_eq!(&tag_buf, tag);
+    }
+
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn append_to_existing_stream() {
+    use std::io::Cursor;
+
+    let first = b"original two-block stream".repeat(400);
+    let second = b"appended data".repeat(500);
+
+    // A two-block stream to start from.
+    let mut option = XzOptions::with_preset(1);
+    option.set_block_size(std::num::NonZeroU64::new(4 << 10));
+    let mut file = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut file, option).unwrap();
+        writer.write_all(&first).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // Append without recompressing the existing blocks.
+    let mut cursor = Cursor::new(file);
+    {
+        let mut writer = XzWriter::append_to(&mut cursor, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(&second).unwrap();
+        writer.finish().unwrap();
+    }
+    let combined = cursor.into_inner();
+
+    let mut expected = first.clone();
+    expected.extend_from_slice(&second);
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(combined.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == expected);
+
+    // liblzma accepts the merged stream as well.
+    let mut liblzma_uncompressed = Vec::new();
+    {
+        use liblzma::read::XzDecoder;
+        let mut decoder = XzDecoder::new(combined.as_slice());
+        decoder.read_to_end(&mut liblzma_uncompressed).unwrap();
+    }
+    assert!(liblzma_uncompressed == expected);
+}
+
+#[test]
+fn cloned_options_filters_are_independent() {
+    use lzma_rust2::Filter;
+
+    let mut option = XzOptions::with_preset(1);
+    option.prepend_filter(Filter::Delta { distance: 4 });
+
+    let mut clone = option.clone();
+    clone.prepend_filter(Filter::BcjX86 { start: 0 });
+
+    // The filters vector is deeply cloned.
+    assert_eq!(option.filters.len(), 1);
+    assert_eq!(clone.filters.len(), 2);
+
+    // Both configurations still compress and round-trip independently.
+    let data = b"independent clone".repeat(300);
+    for option in [option, clone] {
+        let mut compressed = Vec::new();
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut uncompressed = Vec::new();
+        XzReader::new(compressed.as_slice(), false)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed == data);
+    }
+}
+
+#[test]
+fn finish_with_stats_reports_totals() {
+    let data = std::fs::read(PG6800).unwrap();
+
+    let mut writer = XzWriter::new(Vec::new(), XzOptions::with_preset(3)).unwrap();
+    writer.write_all(&data).unwrap();
+    let (compressed, stats) = writer.finish_with_stats().unwrap();
+
+    assert_eq!(stats.uncompressed_in, data.len() as u64);
+    assert_eq!(stats.compressed_out, compressed.len() as u64);
+
+    // The raw LZMA2 writer reports its own totals as well.
+    let mut writer =
+        lzma_rust2::Lzma2Writer::new(Vec::new(), lzma_rust2::Lzma2Options::with_preset(3));
+    writer.write_all(&data).unwrap();
+    let (compressed, stats) = writer.finish_with_stats().unwrap();
+
+    assert_eq!(stats.uncompressed_in, data.len() as u64);
+    assert_eq!(stats.compressed_out, compressed.len() as u64);
+}
+
+#[test]
+fn flush_type_guarantees() {
+    use lzma_rust2::{Lzma2Reader, LzmaOptions};
+
+    let first = b"first flushed message".repeat(50);
+    let second = b"second message".repeat(60);
+
+    // sync_flush: the partial block's LZMA2 chunks decode up to the flush.
+    let mut writer = XzWriter::new(Vec::new(), XzOptions::with_preset(3)).unwrap();
+    writer.write_all(&first).unwrap();
+    writer.sync_flush().unwrap();
+
+    {
+        let snapshot = writer.inner().clone();
+        let header_size = (snapshot[12] as usize + 1) * 4;
+        let mut payload = snapshot[12 + header_size..].to_vec();
+        payload.push(0x00);
+
+        let mut uncompressed = Vec::new();
+        Lzma2Reader::new(payload.as_slice(), LzmaOptions::DICT_SIZE_DEFAULT, None)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed == first);
+    }
+
+    // flush_block: everything so far is recoverable as validated blocks.
+    let mut writer = XzWriter::new(Vec::new(), XzOptions::with_preset(3)).unwrap();
+    writer.write_all(&first).unwrap();
+    writer.flush_block().unwrap();
+
+    {
+        let snapshot = writer.inner().clone();
+        let mut uncompressed = Vec::new();
+        XzReader::new(snapshot.as_slice(), false)
+            .with_partial_recovery(true)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed == first);
+    }
+
+    // Writing continues after both flush types and the final stream is whole.
+    writer.write_all(&second).unwrap();
+    let compressed = writer.finish().unwrap();
+
+    let mut expected = first.clone();
+    expected.extend_from_slice(&second);
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == expected);
+}
+
+#[test]
+fn xz_list_two_streams() {
+    use lzma_rust2::{xz_list, CheckType};
+
+    let first = b"first stream".repeat(400);
+    let second = b"second stream".repeat(300);
+
+    let mut file = Vec::new();
+
+    for (data, check_type) in [(&first, CheckType::Crc64), (&second, CheckType::Crc32)] {
+        let mut option = XzOptions::with_preset(1);
+        option.set_check_sum_type(check_type);
+        option.set_block_size(std::num::NonZeroU64::new(1 << 10));
+
+        let mut writer = XzWriter::new(&mut file, option).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let listing = xz_list(std::io::Cursor::new(&file)).unwrap();
+
+    assert_eq!(listing.streams.len(), 2);
+    assert_eq!(listing.compressed_size, file.len() as u64);
+    assert_eq!(
+        listing.uncompressed_size,
+        (first.len() + second.len()) as u64
+    );
+    assert_eq!(listing.streams[0].check_type, CheckType::Crc64);
+    assert_eq!(listing.streams[1].check_type, CheckType::Crc32);
+    assert_eq!(listing.streams[0].uncompressed_size, first.len() as u64);
+    assert_eq!(listing.streams[1].uncompressed_size, second.len() as u64);
+    assert!(listing.streams.iter().all(|stream| stream.block_count >= 1));
+}
+
+#[test]
+fn decode_prefix_stops_early() {
+    let data = std::fs::read(PG6800).unwrap();
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // A prefix smaller than the stream is filled completely.
+    let mut reader = XzReader::new(compressed.as_slice(), false);
+    let mut prefix = vec![0u8; 4096];
+    let filled = reader.decode_prefix(&mut prefix).unwrap();
+    assert_eq!(filled, 4096);
+    assert!(prefix == data[..4096]);
+
+    // A prefix larger than the stream stops at the end of the stream.
+    let mut reader = XzReader::new(compressed.as_slice(), false);
+    let mut oversized = vec![0u8; data.len() + 1024];
+    let filled = reader.decode_prefix(&mut oversized).unwrap();
+    assert_eq!(filled, data.len());
+    assert!(oversized[..filled] == data);
+}
+
+#[test]
+fn bcj_x86_nonzero_start_offset_round_trip() {
+    use lzma_rust2::Filter;
+
+    let data = std::fs::read("tests/data/wget-x86").unwrap();
+
+    let mut option = XzOptions::with_preset(1);
+    option.prepend_filter(Filter::BcjX86 { start: 16 });
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+
+    // liblzma agrees on the start offset property encoding.
+    let mut liblzma_uncompressed = Vec::new();
+    {
+        use liblzma::read::XzDecoder;
+        let mut decoder = XzDecoder::new(compressed.as_slice());
+        decoder.read_to_end(&mut liblzma_uncompressed).unwrap();
+    }
+    assert!(liblzma_uncompressed == data);
+}
+
+#[test]
+fn bcj_arm_nonzero_start_offset_round_trip() {
+    use lzma_rust2::Filter;
+
+    let data = std::fs::read("tests/data/wget-arm").unwrap();
+
+    let mut option = XzOptions::with_preset(1);
+    option.prepend_filter(Filter::BcjARM { start: 1024 });
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn misaligned_bcj_start_offset_is_rejected() {
+    use lzma_rust2::Filter;
+
+    // ARM requires 4-byte alignment.
+    let mut option = XzOptions::with_preset(1);
+    option.prepend_filter(Filter::BcjARM { start: 2 });
+    assert!(XzWriter::new(Vec::new(), option).is_err());
+
+    // The builder rejects it as well.
+    let result = lzma_rust2::XzFilterChainBuilder::new()
+        .add_bcj_arm(2)
+        .finish();
+    assert!(result.is_err());
+}
+
+#[test]
+fn multi_stream_padding_round_trip() {
+    use std::num::NonZeroU64;
+
+    use lzma_rust2::XzMultiStreamWriter;
+
+    let data = b"padded multi-stream data".repeat(2000);
+
+    let mut option = XzOptions::with_preset(1);
+    option.set_stream_padding(8);
+
+    let mut writer =
+        XzMultiStreamWriter::new(Vec::new(), option, NonZeroU64::new(10_000).unwrap()).unwrap();
+    writer.write_all(&data).unwrap();
+    let compressed = writer.finish().unwrap();
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), true)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+
+    // The padding bytes are actually present in front of the next stream
+    // header (decoders tolerate missing padding, so round-tripping alone
+    // would not catch it).
+    let magic = b"\xfd7zXZ\x00";
+    let second_stream = compressed
+        .windows(magic.len())
+        .skip(1)
+        .position(|window| window == magic)
+        .map(|position| position + 1)
+        .unwrap();
+    assert!(compressed[second_stream - 8..second_stream]
+        .iter()
+        .all(|byte| *byte == 0));
+
+    // Padding that is not a multiple of 4 is rejected up front.
+    let mut option = XzOptions::with_preset(1);
+    option.set_stream_padding(6);
+    assert!(
+        XzMultiStreamWriter::new(Vec::new(), option, NonZeroU64::new(10_000).unwrap()).is_err()
+    );
+}
+
+#[test]
+fn filter_allowlist_rejects_bcj() {
+    use lzma_rust2::{Filter, FilterType};
+
+    let data = std::fs::read("tests/data/wget-x86").unwrap();
+
+    let mut option = XzOptions::with_preset(1);
+    option.prepend_filter(Filter::BcjX86 { start: 0 });
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // LZMA2-only allowlist rejects the BCJ block.
+    let mut reader =
+        XzReader::new(compressed.as_slice(), false).with_allowed_filters(&[FilterType::LZMA2]);
+    let mut uncompressed = Vec::new();
+    let error = reader.read_to_end(&mut uncompressed).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+
+    // Whitelisting the BCJ filter decodes normally.
+    let mut reader = XzReader::new(compressed.as_slice(), false)
+        .with_allowed_filters(&[FilterType::LZMA2, FilterType::BcjX86]);
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+
+    // A plain LZMA2 stream passes the LZMA2-only allowlist.
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(b"plain stream").unwrap();
+        writer.finish().unwrap();
+    }
+    let mut reader =
+        XzReader::new(compressed.as_slice(), false).with_allowed_filters(&[FilterType::LZMA2]);
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == b"plain stream");
+}
+
+#[test]
+fn corrupted_backward_size_is_rejected() {
+    use lzma_rust2::xz_crc32;
+
+    let data = b"backward size check".repeat(500);
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // Bump the footer backward size by one and re-fix the footer CRC so only
+    // the new check can catch the mismatch.
+    let len = compressed.len();
+    let backward_size = u32::from_le_bytes(compressed[len - 8..len - 4].try_into().unwrap()) + 1;
+    compressed[len - 8..len - 4].copy_from_slice(&backward_size.to_le_bytes());
+    let footer_crc = xz_crc32(&compressed[len - 8..len - 2]);
+    compressed[len - 12..len - 8].copy_from_slice(&footer_crc.to_le_bytes());
+
+    let mut uncompressed = Vec::new();
+    let error = XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn empty_input_produces_valid_stream() {
+    let mut compressed = Vec::new();
+    let writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+    writer.finish().unwrap();
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed.is_empty());
+
+    // liblzma accepts the empty stream as well.
+    let mut liblzma_uncompressed = Vec::new();
+    {
+        use liblzma::read::XzDecoder;
+        let mut decoder = XzDecoder::new(compressed.as_slice());
+        decoder.read_to_end(&mut liblzma_uncompressed).unwrap();
+    }
+    assert!(liblzma_uncompressed.is_empty());
+}
+
+#[test]
+fn finish_directly_after_flush_block() {
+    let data = b"flush then finish".repeat(100);
+
+    let mut compressed = Vec::new();
+    let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+    writer.write_all(&data).unwrap();
+    writer.flush_block().unwrap();
+    writer.finish().unwrap();
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn match_finder_shortcuts_change_the_stream() {
+    use lzma_rust2::MfType;
+
+    let data = std::fs::read(PG6800).unwrap();
+
+    let compress = |option: XzOptions| {
+        let mut compressed = Vec::new();
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+        compressed
+    };
+
+    // Preset 6 uses Bt4; forcing Hc4 through the shortcut must change the
+    // produced stream (and typically worsen the ratio).
+    let bt4 = compress(XzOptions::with_preset(6));
+
+    let mut option = XzOptions::with_preset(6);
+    option.set_match_finder(MfType::Hc4);
+    let hc4 = compress(option);
+    assert!(hc4 != bt4);
+
+    // The nice length shortcut is forwarded as well.
+    let mut option = XzOptions::with_preset(6);
+    option.set_nice_len(273);
+    let long_nice = compress(option);
+    assert!(long_nice != bt4);
+
+    // All variants still decode to the input.
+    for compressed in [bt4, hc4, long_nice] {
+        let mut uncompressed = Vec::new();
+        XzReader::new(compressed.as_slice(), false)
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed == data);
+    }
+}
+
+#[test]
+fn auto_finish_explicit_finish_surfaces_errors() {
+    // A writer that fails once more than `limit` bytes arrive, so finish()
+    // fails while earlier writes succeed.
+    #[derive(Debug)]
+    struct Failing {
+        written: usize,
+        limit: usize,
+    }
+
+    impl Write for Failing {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.written + buf.len() > self.limit {
+                return Err(std::io::Error::other("sink failed"));
+            }
+            self.written += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let make_writer = |limit| {
+        let sink = Failing { written: 0, limit };
+        XzWriter::new(sink, XzOptions::with_preset(1))
+            .unwrap()
+            .auto_finish()
+    };
+
+    // The explicit finish reports the failure.
+    let mut writer = make_writer(64);
+    writer
+        .write_all(b"data that overflows the sink on finish")
+        .unwrap();
+    let error = writer.finish().unwrap_err();
+    assert_eq!(error.to_string(), "sink failed");
+
+    // The drop path swallows the same failure silently (and must not
+    // double-finish after an explicit finish).
+    let mut writer = make_writer(64);
+    writer
+        .write_all(b"data that overflows the sink on finish")
+        .unwrap();
+    drop(writer);
+
+    // A working sink round-trips through the explicit finish.
+    let data = b"auto finish explicit".repeat(100);
+    let mut writer = XzWriter::new(Vec::new(), XzOptions::with_preset(1))
+        .unwrap()
+        .auto_finish();
+    writer.write_all(&data).unwrap();
+    let compressed = writer.finish().unwrap();
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn block_info_filter_property_accessors() {
+    use std::sync::{Arc, Mutex};
+
+    use lzma_rust2::{BlockInfo, Filter, FilterType};
+
+    let data = std::fs::read("tests/data/wget-x86").unwrap();
+
+    let mut option = XzOptions::with_preset(1);
+    let dict_size = option.lzma_options.dict_size;
+    option.prepend_filter(Filter::BcjX86 { start: 16 });
+    option.prepend_filter(Filter::Delta { distance: 4 });
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let seen: Arc<Mutex<Vec<BlockInfo>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+
+    let mut reader = XzReader::new(compressed.as_slice(), false);
+    reader.on_block_header(Box::new(move |info| {
+        sink.lock().unwrap().push(info.clone());
+    }));
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    let info = &seen[0];
+    assert_eq!(info.delta_distance(), Some(4));
+    assert_eq!(info.bcj_filter(), Some((FilterType::BcjX86, 16)));
+    assert_eq!(info.bcj_start_offset(), Some(16));
+    assert_eq!(info.lzma2_dict_size(), Some(dict_size));
+
+    // A plain stream reports no pre-filters.
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(b"plain").unwrap();
+        writer.finish().unwrap();
+    }
+    let seen: Arc<Mutex<Vec<BlockInfo>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&seen);
+    let mut reader = XzReader::new(compressed.as_slice(), false);
+    reader.on_block_header(Box::new(move |info| {
+        sink.lock().unwrap().push(info.clone());
+    }));
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen[0].delta_distance(), None);
+    assert_eq!(seen[0].bcj_filter(), None);
+    assert!(seen[0].lzma2_dict_size().is_some());
+}
+
+#[test]
+fn verify_checks_can_be_disabled() {
+    let data = b"ignore check speed mode".repeat(2000);
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // Corrupt one byte of the block checksum (CRC64 sits right before the
+    // index; the index starts 24 bytes from the end for one record).
+    let mut file = compressed.clone();
+    let position = compressed.len() - 25;
+    file[position] ^= 0xFF;
+
+    // Default: the corruption is detected.
+    let mut uncompressed = Vec::new();
+    let error = XzReader::new(file.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    // With verification disabled the stream decodes and stays positioned
+    // correctly (index and footer still parse).
+    let mut uncompressed = Vec::new();
+    XzReader::new(file.as_slice(), false)
+        .with_verify_checks(false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn indexed_archive_round_trip() {
+    use std::io::Cursor;
+
+    use lzma_rust2::{IndexedXzReader, IndexedXzWriter};
+
+    let alpha = b"first entry contents".repeat(500);
+    let beta = b"second entry, different data".repeat(800);
+    let gamma = b"third".to_vec();
+
+    let mut writer = IndexedXzWriter::new(Vec::new(), XzOptions::with_preset(1)).unwrap();
+    writer.begin_entry("alpha.txt").unwrap();
+    writer.write_all(&alpha).unwrap();
+    writer.begin_entry("beta.bin").unwrap();
+    writer.write_all(&beta).unwrap();
+    writer.begin_entry("gamma").unwrap();
+    writer.write_all(&gamma).unwrap();
+    let archive = writer.finish().unwrap();
+
+    // Random access by name, out of order.
+    let mut reader = IndexedXzReader::new(Cursor::new(archive.clone())).unwrap();
+    assert_eq!(reader.entry_names(), ["alpha.txt", "beta.bin", "gamma"]);
+
+    for (name, expected) in [
+        ("gamma", gamma.as_slice()),
+        ("alpha.txt", alpha.as_slice()),
+        ("beta.bin", beta.as_slice()),
+    ] {
+        let mut contents = Vec::new();
+        reader
+            .entry(name)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert!(contents == expected, "entry {name}");
+    }
+
+    // Unknown names report an error.
+    assert!(reader.entry("missing").is_err());
+
+    // The archive is still a plain XZ stream for ordinary decoders: the
+    // entries appear back to back, followed by the metadata.
+    let mut plain = Vec::new();
+    XzReader::new(archive.as_slice(), false)
+        .read_to_end(&mut plain)
+        .unwrap();
+    assert!(plain.starts_with(&alpha));
+    assert!(plain[alpha.len()..].starts_with(&beta));
+
+    // A non-indexed stream is rejected cleanly.
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(1)).unwrap();
+        writer.write_all(b"no index here").unwrap();
+        writer.finish().unwrap();
+    }
+    assert!(IndexedXzReader::new(Cursor::new(compressed)).is_err());
+}
+
+#[test]
+fn block_layout_reports_decodable_ranges() {
+    use std::io::Cursor;
+    use std::num::NonZeroU64;
+
+    use lzma_rust2::XzRecordReader;
+
+    let data = std::fs::read(PG100).unwrap();
+
+    // Block sizes clamp to the dictionary, so use preset 0 for small blocks.
+    let mut option = XzOptions::with_preset(0);
+    option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, option).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut reader = XzReader::new(Cursor::new(compressed.clone()), false);
+    let layout = reader.block_layout().unwrap();
+    assert!(layout.len() > 1);
+
+    // The uncompressed ranges tile the output exactly.
+    let mut expected_offset = 0;
+    for block in &layout {
+        assert_eq!(block.uncompressed_offset, expected_offset);
+        expected_offset += block.uncompressed_size;
+    }
+    assert_eq!(expected_offset, data.len() as u64);
+
+    // Each block's uncompressed range decodes independently via the record
+    // reader anchored at the reported boundaries.
+    let boundaries: Vec<u64> = layout
+        .iter()
+        .map(|block| block.uncompressed_offset)
+        .collect();
+    let mut records = XzRecordReader::new(Cursor::new(compressed.clone()), boundaries).unwrap();
+    for (index, block) in layout.iter().enumerate() {
+        let mut contents = Vec::new();
+        records
+            .record(index)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        let start = block.uncompressed_offset as usize;
+        let end = start + block.uncompressed_size as usize;
+        assert!(contents == data[start..end], "block {index}");
+    }
+
+    // Every reported compressed offset holds a valid block header start (a
+    // nonzero encoded header size byte), and the ranges stay in the file.
+    for block in &layout {
+        let start = block.compressed_offset as usize;
+        assert!(compressed[start] != 0, "block header indicator");
+        assert!(start + block.compressed_size as usize <= compressed.len());
+    }
+
+    // The reader's position was restored: decoding continues normally.
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+
+    // Calling it again mid-decode also restores the position.
+    let mut reader = XzReader::new(Cursor::new(compressed), false);
+    let mut head = vec![0u8; 40_000];
+    reader.read_exact(&mut head).unwrap();
+    let layout_again = reader.block_layout().unwrap();
+    assert_eq!(layout_again.len(), layout.len());
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    let mut combined = head;
+    combined.extend_from_slice(&rest);
+    assert!(combined == data);
+}
@@ -197,3 +197,462 @@ fn round_trip_pg6800_8() {
 fn round_trip_pg6800_9() {
     test_round_trip(PG6800, 9);
 }
+
+#[test]
+fn single_block_stream_decodes_inline() {
+    use lzma_rust2::XzWriter;
+
+    let data = std::fs::read(PG6800).unwrap();
+
+    // No block size set: the whole stream is one block.
+    let mut compressed = Vec::new();
+
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(3)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut reader = XzReaderMt::new(Cursor::new(compressed), false, 4).unwrap();
+    assert_eq!(reader.block_count(), 1);
+
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed.as_slice() == data);
+}
+
+#[test]
+fn deterministic_output() {
+    let data = std::fs::read(PG6800).unwrap();
+
+    let compress = |num_workers: u32| {
+        let mut option = XzOptions::with_preset(3);
+        option.set_block_size(NonZeroU64::new(64 << 10));
+
+        let mut compressed = Vec::new();
+        let mut writer = XzWriterMt::new(&mut compressed, option, num_workers).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+        compressed
+    };
+
+    // Identical runs produce identical bytes.
+    let first = compress(4);
+    let second = compress(4);
+    assert!(first == second);
+
+    // Block boundaries only depend on the input and options, so even the
+    // worker count does not change the output.
+    let single = compress(1);
+    assert!(first == single);
+}
+
+#[test]
+fn manual_block_flushes() {
+    let data = std::fs::read(PG6800).unwrap();
+    let parts: Vec<&[u8]> = data.chunks(data.len() / 4 + 1).collect();
+
+    let mut option = XzOptions::with_preset(1);
+    // A block size far larger than the input: without manual flushes this
+    // would be a single block.
+    option.set_block_size(NonZeroU64::new(64 << 20));
+
+    let mut compressed = Vec::new();
+    let mut writer = XzWriterMt::new(&mut compressed, option, 2).unwrap();
+    for part in &parts {
+        writer.write_all(part).unwrap();
+        writer.flush_block().unwrap();
+    }
+    // A trailing flush on an empty buffer is a no-op.
+    writer.flush_block().unwrap();
+    writer.finish().unwrap();
+
+    let mut reader = XzReaderMt::new(Cursor::new(compressed), false, 2).unwrap();
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+
+    assert_eq!(reader.block_count(), parts.len());
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn corrupt_block_error_names_the_block() {
+    use std::num::NonZeroU64;
+
+    let data = b"which block failed during the multithreaded decode".repeat(30_000);
+
+    let mut option = XzOptions::with_preset(0);
+    option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriterMt::new(&mut compressed, option, 2).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // Corrupt a byte in the middle of the file, which lands in a block
+    // after the first.
+    let position = compressed.len() / 2;
+    compressed[position] ^= 0xFF;
+
+    let mut reader = XzReaderMt::new(std::io::Cursor::new(compressed), false, 2).unwrap();
+    let mut uncompressed = Vec::new();
+    let error = reader.read_to_end(&mut uncompressed).unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("block "), "message: {message}");
+    assert!(!message.contains("block 0"), "message: {message}");
+}
+
+#[test]
+fn shared_source_round_trip() {
+    use std::{num::NonZeroU64, sync::Arc};
+
+    let data = b"zero copy shared source decode".repeat(40_000);
+
+    let mut option = XzOptions::with_preset(0);
+    option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriterMt::new(&mut compressed, option, 2).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut reader = XzReaderMt::new_shared(Arc::new(compressed), false, 2).unwrap();
+    assert!(reader.block_count() > 1);
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn auto_tuned_block_size_produces_enough_blocks() {
+    use lzma_rust2::XzReader;
+
+    // Enough input for many starting-size blocks at preset 0 (256 KiB dict).
+    let data = b"auto tuned block size policy".repeat(150_000);
+    let num_workers = 4;
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriterMt::new_auto(&mut compressed, 0, num_workers).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // The stream splits into at least num_workers blocks.
+    let mut reader = XzReaderMt::new(std::io::Cursor::new(compressed.clone()), false, 1).unwrap();
+    assert!(
+        reader.block_count() >= num_workers as usize,
+        "only {} blocks",
+        reader.block_count()
+    );
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+
+    // The single-threaded reader agrees.
+    let mut uncompressed = Vec::new();
+    XzReader::new(compressed.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn parallel_verify_checks_integrity() {
+    use std::num::NonZeroU64;
+
+    let data = b"parallel verification of a multi block archive".repeat(40_000);
+
+    let mut option = XzOptions::with_preset(0);
+    option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriterMt::new(&mut compressed, option, 2).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    // An intact archive verifies and reports the uncompressed size.
+    let reader = XzReaderMt::new(std::io::Cursor::new(compressed.clone()), false, 2).unwrap();
+    assert_eq!(reader.verify().unwrap(), data.len() as u64);
+
+    // A corrupted byte fails verification and names the block.
+    let mut bad = compressed.clone();
+    let position = compressed.len() / 2;
+    bad[position] ^= 0xFF;
+    let reader = XzReaderMt::new(std::io::Cursor::new(bad), false, 2).unwrap();
+    let error = reader.verify().unwrap_err();
+    assert!(error.to_string().contains("block "), "{error}");
+}
+
+#[test]
+fn buffered_results_cap_bounds_memory() {
+    use std::num::NonZeroU64;
+
+    // Many blocks, read by a slow consumer in tiny pieces.
+    let data = b"bounded result buffering for slow consumers".repeat(60_000);
+
+    let mut option = XzOptions::with_preset(0);
+    option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriterMt::new(&mut compressed, option, 2).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut reader = XzReaderMt::new(std::io::Cursor::new(compressed), false, 2)
+        .unwrap()
+        .with_max_buffered_results(Some(2));
+    assert!(reader.block_count() > 4);
+
+    let mut uncompressed = Vec::new();
+    let mut piece = [0u8; 4096];
+    loop {
+        let read = reader.read(&mut piece).unwrap();
+        if read == 0 {
+            break;
+        }
+        uncompressed.extend_from_slice(&piece[..read]);
+    }
+    assert!(uncompressed == data);
+}
+
+/// Wraps a byte slice so only `Read` is available, proving the streaming
+/// reader needs no seeking.
+struct NonSeekable<'a>(&'a [u8]);
+
+impl Read for NonSeekable<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.0.len().min(buf.len()).min(997);
+        buf[..read].copy_from_slice(&self.0[..read]);
+        self.0 = &self.0[read..];
+        Ok(read)
+    }
+}
+
+#[test]
+fn streaming_mt_round_trip_over_non_seekable_source() {
+    use std::num::NonZeroU64;
+
+    use lzma_rust2::XzReaderMtStream;
+
+    let data = b"streaming multithreaded reader without seeking".repeat(40_000);
+
+    // The MT writer declares compressed sizes in its block headers, which
+    // the streaming reader requires.
+    let mut option = XzOptions::with_preset(0);
+    option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriterMt::new(&mut compressed, option, 2).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut reader = XzReaderMtStream::new(NonSeekable(&compressed), false, 2).unwrap();
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == data);
+    assert!(reader.block_count() > 1);
+}
+
+#[test]
+fn streaming_mt_rejects_blocks_without_compressed_size() {
+    use lzma_rust2::{XzReaderMtStream, XzWriter};
+
+    // The single-threaded writer does not declare compressed sizes.
+    let data = b"no sizes in these block headers".repeat(1000);
+    let mut compressed = Vec::new();
+    {
+        let mut writer = XzWriter::new(&mut compressed, XzOptions::with_preset(0)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut reader = XzReaderMtStream::new(compressed.as_slice(), false, 2).unwrap();
+    let mut uncompressed = Vec::new();
+    let error = reader.read_to_end(&mut uncompressed).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn streaming_mt_decodes_concatenated_streams() {
+    use std::num::NonZeroU64;
+
+    use lzma_rust2::XzReaderMtStream;
+
+    let first = b"first stream".repeat(30_000);
+    let second = b"second stream".repeat(20_000);
+
+    let mut option = XzOptions::with_preset(0);
+    option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+    let mut file = Vec::new();
+    for part in [&first, &second] {
+        let mut writer = XzWriterMt::new(&mut file, option.clone(), 2).unwrap();
+        writer.write_all(part).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut expected = first.clone();
+    expected.extend_from_slice(&second);
+
+    // With multiple streams allowed, both decode back to back.
+    let mut reader = XzReaderMtStream::new(NonSeekable(&file), true, 2).unwrap();
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == expected);
+
+    // Without, decoding stops cleanly after the first stream, like the
+    // single-threaded reader in single-stream mode.
+    let mut reader = XzReaderMtStream::new(NonSeekable(&file), false, 2).unwrap();
+    let mut uncompressed = Vec::new();
+    reader.read_to_end(&mut uncompressed).unwrap();
+    assert!(uncompressed == first);
+}
+
+#[cfg(feature = "spill-to-disk")]
+#[test]
+fn spilled_results_produce_identical_output() {
+    use std::num::NonZeroU64;
+
+    use lzma_rust2::XzReader;
+
+    let data = b"spill the reorder buffer to disk".repeat(60_000);
+
+    let compress = |spill_threshold: Option<u64>| {
+        let mut option = XzOptions::with_preset(0);
+        option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+        option.set_spill_threshold(spill_threshold);
+
+        let mut compressed = Vec::new();
+        let mut writer = XzWriterMt::new(&mut compressed, option, 4).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+        compressed
+    };
+
+    // A zero threshold forces every buffered out-of-order result through
+    // the temporary-file round trip; the output must be byte-identical to
+    // the unspilled run (the MT writer is deterministic).
+    let unspilled = compress(None);
+    let spilled = compress(Some(0));
+    assert!(spilled == unspilled);
+
+    let mut uncompressed = Vec::new();
+    XzReader::new(spilled.as_slice(), false)
+        .read_to_end(&mut uncompressed)
+        .unwrap();
+    assert!(uncompressed == data);
+}
+
+#[test]
+fn shared_pool_matches_per_instance_output() {
+    use std::num::NonZeroU64;
+    use std::sync::Arc;
+
+    use lzma_rust2::{
+        LzipOptions, LzipReaderMt, LzipWriterMt, Lzma2Options, Lzma2Reader, Lzma2ReaderMt,
+        Lzma2WriterMt, SharedWorkerPool, XzReaderMtStream,
+    };
+
+    let pool = Arc::new(SharedWorkerPool::new(3));
+    assert_eq!(pool.num_threads(), 3);
+
+    // Several files through ONE pool: writer and reader paths, with output
+    // identical to the per-instance-thread path (the MT writers are
+    // deterministic).
+    for index in 0..4u32 {
+        let data = format!("file {index} compressed through a shared pool ")
+            .repeat(30_000)
+            .into_bytes();
+
+        // XZ writer path.
+        let mut option = XzOptions::with_preset(0);
+        option.set_block_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+        let mut per_instance = Vec::new();
+        let mut writer = XzWriterMt::new(&mut per_instance, option.clone(), 3).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut pooled = Vec::new();
+        let mut writer =
+            XzWriterMt::new_with_pool(&mut pooled, option, 3, Arc::clone(&pool)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+        assert!(pooled == per_instance, "file {index}");
+
+        // XZ streaming reader through the same pool.
+        let mut uncompressed = Vec::new();
+        XzReaderMtStream::new_with_pool(pooled.as_slice(), false, 3, Arc::clone(&pool))
+            .unwrap()
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed == data);
+
+        // LZMA2 writer and reader through the pool.
+        let mut option = Lzma2Options::with_preset(0);
+        option.set_chunk_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+        let dict_size = option.lzma_options.dict_size;
+
+        let mut per_instance = Vec::new();
+        let mut writer = Lzma2WriterMt::new(&mut per_instance, option.clone(), 3).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut pooled = Vec::new();
+        let mut writer =
+            Lzma2WriterMt::new_with_pool(&mut pooled, option, 3, Arc::clone(&pool)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+        assert!(pooled == per_instance, "lzma2 file {index}");
+
+        let mut uncompressed = Vec::new();
+        Lzma2ReaderMt::new_with_pool(pooled.as_slice(), dict_size, None, 3, Arc::clone(&pool))
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed == data);
+
+        // Cross-check against the single-threaded reader.
+        let mut st = Vec::new();
+        Lzma2Reader::new(pooled.as_slice(), dict_size, None)
+            .read_to_end(&mut st)
+            .unwrap();
+        assert!(st == data);
+
+        // LZIP writer and reader through the pool.
+        let mut option = LzipOptions::with_preset(0);
+        option.set_member_size(NonZeroU64::new(option.lzma_options.dict_size as u64));
+
+        let mut per_instance = Vec::new();
+        let mut writer = LzipWriterMt::new(&mut per_instance, option.clone(), 3).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+
+        let mut pooled = Vec::new();
+        let mut writer =
+            LzipWriterMt::new_with_pool(&mut pooled, option, 3, Arc::clone(&pool)).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+        assert!(pooled == per_instance, "lzip file {index}");
+
+        let mut uncompressed = Vec::new();
+        LzipReaderMt::new_with_pool(std::io::Cursor::new(pooled), 3, Arc::clone(&pool))
+            .unwrap()
+            .read_to_end(&mut uncompressed)
+            .unwrap();
+        assert!(uncompressed == data);
+    }
+
+    // The pool is still usable afterwards and joins cleanly on drop.
+    drop(pool);
+}